@@ -26,7 +26,7 @@ pub fn check(cfg: &Arc<SourceIpAuthorizer>, input: &mut Input)
     let ip = match (&cfg.forwarded_ip_header, forwarded) {
         (&Some(ref header), true) => {
             let mut ip = None;
-            for (name, value) in input.headers.headers() {
+            for (name, value) in input.ingress_headers() {
                 if name.eq_ignore_ascii_case(header) {
                     let parsed = from_utf8(value).ok()
                           .and_then(|x| x.parse::<IpAddr>().ok());
@@ -5,7 +5,6 @@ use std::borrow::Cow;
 use futures::{AsyncSink};
 use futures::sink::Sink;
 use tk_http::Status;
-use tk_http::server::Head;
 use serde_json::{self, Value as Json};
 
 use crate::http_pools::{REQUESTS, FAILED_503};
@@ -23,10 +22,10 @@ use crate::chat::processor::{Action};
 ///
 /// Send Auth message to proper backend
 /// returninng Hello/Error message.
-fn auth_data(handshake: &Head) -> Result<AuthData, Status> {
+fn auth_data(inp: &Input, settings: &Chat) -> Result<AuthData, Status> {
     let mut cookie = None;
     let mut auth = None;
-    for (key, value) in handshake.headers() {
+    for (key, value) in inp.ingress_headers() {
         if key.eq_ignore_ascii_case("Cookie") {
             if cookie.is_some() {
                 debug!("Duplicate Cookie header");
@@ -41,9 +40,18 @@ fn auth_data(handshake: &Head) -> Result<AuthData, Status> {
             auth = Some(String::from_utf8_lossy(value).into_owned());
         }
     }
-    let url_qs = handshake.path().expect("invalid path for websocket hanshake")
+    let url_qs = inp.headers.path().expect("invalid path for websocket hanshake")
         .splitn(2, "?").nth(1).unwrap_or("").to_string();
 
+    let size = cookie.as_ref().map(|x| x.len()).unwrap_or(0)
+        + auth.as_ref().map(|x| x.len()).unwrap_or(0)
+        + url_qs.len();
+    if size > settings.max_auth_data_size {
+        debug!("Auth data of {} bytes exceeds limit of {}",
+            size, settings.max_auth_data_size);
+        return Err(Status::BadRequest);
+    }
+
     Ok(AuthData {
         http_cookie: cookie,
         http_authorization: auth,
@@ -62,6 +70,7 @@ pub fn start_authorize(inp: &Input, conn_id: Cid, settings: &Arc<Chat>,
     pool.send(Action::NewConnection {
         conn_id: conn_id,
         channel: messages.clone(),
+        max_subscriptions: settings.max_subscriptions_per_connection,
     });
 
     let dest = if settings.use_tangle_prefix() {
@@ -107,7 +116,7 @@ pub fn start_authorize(inp: &Input, conn_id: Cid, settings: &Arc<Chat>,
     };
     let mut up = inp.runtime.http_pools.upstream(&dest.upstream);
 
-    let auth_data = match auth_data(inp.headers) {
+    let auth_data = match auth_data(inp, settings) {
         Ok(data) => data,
         Err(status) => {
             messages.send(FatalError(HttpError(status, None)));
@@ -120,6 +129,7 @@ pub fn start_authorize(inp: &Input, conn_id: Cid, settings: &Arc<Chat>,
         messages.clone(),
         inp.runtime.server_id.clone(),
         settings.weak_content_type(),
+        settings.backend_json_format(),
         &remote,
         pool_config,
     ));
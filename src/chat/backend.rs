@@ -10,14 +10,17 @@ use serde::ser::Serialize;
 use serde_json;
 
 use crate::chat::authorize::{parse_userinfo, good_status};
-use crate::chat::{Cid, ConnectionSender, ConnectionMessage};
+use crate::chat::{Cid, ConnectionSender, ConnectionMessage, PendingGuard};
+use crate::chat::ORPHANED_RESPONSES;
 use crate::chat::ConnectionMessage::{Hello, FatalError};
 use crate::chat::error::MessageError::{HttpError};
 use crate::chat::message::{AuthData, Auth, Call, Meta, Args, Kwargs};
+use crate::chat::message::{self, JsonFormat};
 use crate::chat::processor::{ProcessorPool, Action};
 use crate::chat::replication::{RemotePool, RemoteAction};
 use crate::chat::tangle_auth::{TangleAuth, SwindonAuth};
 use crate::config::SessionPool;
+use crate::config::chat::OrphanPolicy;
 use crate::config::http_destinations::Destination;
 use crate::runtime::{ServerId};
 use crate::intern::SessionId;
@@ -56,6 +59,7 @@ pub struct AuthCodec {
     server_id: ServerId,
     json_content: bool,
     weak_content_type: bool,
+    json_format: JsonFormat,
     remote: RemotePool,
     pool_config: Arc<SessionPool>,
 }
@@ -69,6 +73,10 @@ pub struct CallCodec {
     sender: ConnectionSender,
     json_content: bool,
     weak_content_type: bool,
+    json_format: JsonFormat,
+    on_orphaned: OrphanPolicy,
+    /// Releases `request_id` when response is received
+    pending: Option<PendingGuard>,
 }
 
 pub struct InactivityCodec {
@@ -82,6 +90,7 @@ impl AuthCodec {
     pub fn new(path: String, cid: Cid, req: AuthData,
         chat: ProcessorPool, destination: &Arc<Destination>,
         tx: ConnectionSender, server_id: ServerId, weak_content_type: bool,
+        json_format: JsonFormat,
         remote: &RemotePool, pool_config: &Arc<SessionPool>)
         -> AuthCodec
     {
@@ -94,6 +103,7 @@ impl AuthCodec {
             sender: tx,
             json_content: false,
             weak_content_type,
+            json_format,
             remote: remote.clone(),
             pool_config: pool_config.clone(),
         }
@@ -112,7 +122,9 @@ impl CallCodec {
         meta: &Arc<Meta>, args: Args, kw: Kwargs,
         destination: &Arc<Destination>,
         sender: ConnectionSender,
-        server_id: ServerId, weak_content_type: bool)
+        server_id: ServerId, weak_content_type: bool,
+        json_format: JsonFormat, on_orphaned: OrphanPolicy,
+        pending: Option<PendingGuard>)
         -> CallCodec
     {
         CallCodec {
@@ -129,6 +141,25 @@ impl CallCodec {
             sender: sender,
             json_content: false,
             weak_content_type,
+            json_format,
+            on_orphaned,
+            pending,
+        }
+    }
+
+    /// Sends reply to the connection, accounting for the case when
+    /// connection is closed before backend responded
+    fn reply(&self, code: u16, msg: ConnectionMessage) {
+        if self.sender.try_send(msg) {
+            return;
+        }
+        ORPHANED_RESPONSES.incr(1);
+        if self.on_orphaned == OrphanPolicy::log {
+            warn!("Orphaned response to {}-{} request {}: \
+                status {}, connection is closed",
+                self.server_id, self.conn_id,
+                self.meta.get("request_id").expect("request_id is present"),
+                code);
         }
     }
 
@@ -169,12 +200,13 @@ impl InactivityCodec {
 }
 
 
-fn write_json_request<S, E>(mut e: http::Encoder<S>, data: &E)
+fn write_json_request<S, E>(mut e: http::Encoder<S>, data: &E,
+    format: JsonFormat)
     -> http::EncoderDone<S>
     where E: Serialize,
 {
     e.add_header("Content-Type", "application/json").unwrap();
-    let body = serde_json::to_string(data).unwrap();
+    let body = message::encode(data, format).unwrap();
     let body = body.as_bytes();
     e.add_length(body.len() as u64).unwrap();
     e.done_headers().unwrap();
@@ -211,7 +243,7 @@ impl<S> http::Codec<S> for AuthCodec {
             e.add_header("User-Agent", format!(
                 "swindon/{}", env!("CARGO_PKG_VERSION"))).unwrap();
             ok(write_json_request(e,
-                &Auth(&self.conn_id, &self.server_id, &i)))
+                &Auth(&self.conn_id, &self.server_id, &i), self.json_format))
         } else {
             panic!("wrong state");
         }
@@ -330,7 +362,8 @@ impl<S> http::Codec<S> for CallCodec {
             e.add_header("User-Agent", format!(
                 "swindon/{}", env!("CARGO_PKG_VERSION"))).unwrap();
             let done = write_json_request(e, &Call(
-                &*self.meta, &self.conn_id, &self.server_id, &args, &kw));
+                &*self.meta, &self.conn_id, &self.server_id, &args, &kw),
+                self.json_format);
             self.state = Wait;
             ok(done)
         } else {
@@ -383,26 +416,29 @@ impl<S> http::Codec<S> for CallCodec {
     {
         use self::CallState::*;
         assert!(end);
+        // release id before the client receives the response,
+        // so it can be reused right away
+        self.pending.take();
         match mem::replace(&mut self.state, Void) {
             Headers(Status::Ok) => {
                 match serde_json::from_slice(data) {
                     Ok(x) => {
-                        self.sender.send(ConnectionMessage::Result(
+                        self.reply(200, ConnectionMessage::Result(
                             self.meta.clone(), x));
                     }
                     Err(e) => {
-                        self.sender.send(ConnectionMessage::Error(
+                        self.reply(200, ConnectionMessage::Error(
                             self.meta.clone(), e.into()));
                     }
                 }
             }
             Headers(status) => {
                 if self.json_content {
-                    self.sender.send(
+                    self.reply(status.code(),
                         ConnectionMessage::Error(self.meta.clone(),
                         HttpError(status, serde_json::from_slice(data).ok())));
                 } else {
-                    self.sender.send(
+                    self.reply(status.code(),
                         ConnectionMessage::Error(self.meta.clone(),
                         HttpError(status, None)));
                 }
@@ -491,6 +527,7 @@ impl Drop for AuthCodec {
 
 impl Drop for CallCodec {
     fn drop(&mut self) {
+        self.pending.take();
         match self.state {
             CallState::Void => {},
             ref state => {
@@ -4,4 +4,10 @@ pub enum CloseReason {
     PoolStopped,
     /// Closed by peer, we just propagate the message here
     PeerClose(u16, String),
+    /// Client exceeded `message-rate-limit` with `on-exceed: close`
+    RateLimitExceeded,
+    /// Handler settings changed and handler has `on-reload: reconnect`
+    ConfigReloaded,
+    /// Connection is older than `max-connection-age`
+    MaxConnectionAge,
 }
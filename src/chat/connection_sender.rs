@@ -21,4 +21,8 @@ impl ConnectionSender {
         .map_err(|e| debug!("Error sending connection message: {}. \
             usually these means connection has been closed to soon", e)).ok();
     }
+    /// Same as `send` but returns `false` if connection is already closed
+    pub fn try_send(&self, msg: ConnectionMessage) -> bool {
+        self.sender.unbounded_send(msg).is_ok()
+    }
 }
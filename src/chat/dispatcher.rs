@@ -14,9 +14,11 @@ use serde_json::{Error as JsonError};
 use crate::http_pools::{REQUESTS, FAILED_503};
 use crate::runtime::Runtime;
 use crate::intern::SessionId;
-use crate::config::chat::Chat;
+use crate::config::chat::{Chat, RateLimitPolicy, ReservedMetaPolicy};
 use crate::config::SessionPool;
-use crate::chat::{Cid, ConnectionSender, CloseReason, CONNECTIONS};
+use crate::chat::{Cid, ConnectionSender, CloseReason, RateLimiter};
+use crate::chat::{PendingRequests};
+use crate::chat::CONNECTIONS;
 use crate::chat::message::{self, Meta, Args, Kwargs};
 use crate::chat::processor::{Action, ProcessorPool, ConnectionMessage};
 use crate::chat::backend::CallCodec;
@@ -27,6 +29,9 @@ use crate::metrics::{Counter};
 
 lazy_static! {
     pub static ref FRAMES_RECEIVED: Counter = Counter::new();
+    pub static ref RATE_LIMITED: Counter = Counter::new();
+    pub static ref DUPLICATE_REQUEST_IDS: Counter = Counter::new();
+    pub static ref RESERVED_META_KEYS: Counter = Counter::new();
 }
 
 pub struct Dispatcher {
@@ -40,6 +45,8 @@ pub struct Dispatcher {
     pub remote: RemotePool,
     pub handle: Handle, // Does it belong here?
     pub channel: ConnectionSender,
+    pub rate_limiter: Option<RateLimiter>,
+    pub pending_requests: PendingRequests,
 }
 
 quick_error! {
@@ -50,6 +57,11 @@ quick_error! {
             cause(e)
             from()
         }
+        TooComplex(e: MessageError) {
+            description(e.description())
+            display("{}", e)
+            from()
+        }
         Binary {
             description("binary messages are not supported yet")
         }
@@ -61,16 +73,37 @@ impl websocket::Dispatcher for Dispatcher {
     fn frame(&mut self, frame: &Frame) -> FutureResult<(), WsError> {
         FRAMES_RECEIVED.incr(1);
         match *frame {
-            Text(data) => match message::decode_message(data) {
-                Ok((method, meta, args, kwargs)) => {
-                    self.method_call(method, meta, args, kwargs);
-                    ok(()) // no backpressure, yet
+            Text(data) => {
+                // Charged before the message is parsed, so messages over
+                // the limit are not decoded (unless error has to echo
+                // their request id)
+                let exceeded = self.check_rate_limit();
+                if exceeded == Some(RateLimitPolicy::close) {
+                    return ok(());
                 }
-                Err(e) => {
+                let limits = self.settings.json_limits();
+                if let Err(e) = message::check_limits(data, &limits) {
                     debug!("Message error: {}", e);
-                    err(WsError::custom(Error::from(e)))
+                    return err(WsError::custom(Error::from(e)));
+                }
+                match message::decode_message(data) {
+                    Ok((method, meta, args, kwargs)) => {
+                        if exceeded.is_some() {
+                            // decoded only to echo request id in the error
+                            self.channel.send(ConnectionMessage::Error(
+                                Arc::new(meta),
+                                MessageError::RateLimitExceeded));
+                        } else {
+                            self.method_call(method, meta, args, kwargs);
+                        }
+                        ok(()) // no backpressure, yet
+                    }
+                    Err(e) => {
+                        debug!("Message error: {}", e);
+                        err(WsError::custom(Error::from(e)))
+                    }
                 }
-            },
+            }
             Binary(_) => {
                 debug!("Binary messages are not supported yet");
                 // TODO(tailhook) better error
@@ -88,7 +121,74 @@ impl websocket::Dispatcher for Dispatcher {
 }
 
 impl Dispatcher {
-    fn method_call(&self, name: String, meta: Meta, args: Args, kw: Kwargs) {
+    /// Takes a token for the message, returns policy to apply if
+    /// message is over the limit
+    ///
+    /// Connection is closed right here for the `close` policy, with
+    /// `drop` policy caller sends an error to the client.
+    fn check_rate_limit(&mut self) -> Option<RateLimitPolicy> {
+        let policy = match self.rate_limiter {
+            Some(ref mut lim) => {
+                if lim.check(Instant::now()) {
+                    return None;
+                }
+                lim.policy()
+            }
+            None => return None,
+        };
+        RATE_LIMITED.incr(1);
+        match policy {
+            RateLimitPolicy::drop => {
+                debug!("Connection {:?} exceeded rate limit, \
+                    dropping message", self.cid);
+            }
+            RateLimitPolicy::close => {
+                debug!("Connection {:?} exceeded rate limit, \
+                    closing", self.cid);
+                self.channel.send(ConnectionMessage::StopSock(
+                    CloseReason::RateLimitExceeded));
+            }
+        }
+        Some(policy)
+    }
+
+    /// Applies `reserved-meta-policy`, returns false if message is rejected
+    fn check_reserved_meta(&self, meta: &mut Meta) -> bool {
+        let key = match self.settings.find_reserved_meta_key(meta) {
+            Some(key) => key.to_string(),
+            None => return true,
+        };
+        RESERVED_META_KEYS.incr(1);
+        // `connection_id` is replaced when serializing the call, and is
+        // echoed back as is (like it always was), other reserved keys
+        // are never forwarded nor echoed back
+        for key in &self.settings.reserved_meta_keys {
+            meta.remove(key);
+        }
+        match self.settings.reserved_meta_policy {
+            ReservedMetaPolicy::reject => {
+                debug!("Connection {:?} sent reserved meta key {:?}, \
+                    rejecting message", self.cid, key);
+                self.channel.send(ConnectionMessage::Error(
+                    Arc::new(meta.clone()),
+                    MessageError::ReservedMetaKey(key)));
+                false
+            }
+            ReservedMetaPolicy::log => {
+                warn!("Connection {:?} sent reserved meta key {:?}",
+                    self.cid, key);
+                true
+            }
+            ReservedMetaPolicy::override_ => true,
+        }
+    }
+
+    fn method_call(&self, name: String, mut meta: Meta,
+        args: Args, kw: Kwargs)
+    {
+        if !self.check_reserved_meta(&mut meta) {
+            return;
+        }
         let meta = Arc::new(meta);
         if !message::valid_method(&name) {
             self.channel.send(ConnectionMessage::Error(meta,
@@ -102,6 +202,19 @@ impl Dispatcher {
                     "invalid request id".to_string())));
             return;
         }
+        let pending = if self.settings.reject_duplicate_request_id {
+            match self.pending_requests.start(&meta) {
+                Some(guard) => Some(guard),
+                None => {
+                    DUPLICATE_REQUEST_IDS.incr(1);
+                    self.channel.send(ConnectionMessage::Error(meta,
+                        MessageError::DuplicateRequestId));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
         if let Some(duration) = message::get_active(&meta) {
             self.update_activity(duration);
         }
@@ -129,7 +242,10 @@ impl Dispatcher {
             dest_settings,
             self.channel.clone(),
             self.runtime.server_id.clone(),
-            self.settings.weak_content_type()));
+            self.settings.weak_content_type(),
+            self.settings.backend_json_format(),
+            self.settings.on_orphaned_response,
+            pending));
         match up.get_mut().get_mut() {
             Some(pool) => {
                 match pool.start_send(codec) {
@@ -178,6 +294,7 @@ impl Dispatcher {
 impl Drop for Dispatcher {
     fn drop(&mut self) {
         self.processor.send(Action::Disconnect { conn_id: self.cid });
+        self.runtime.chat_reload.remove(self.cid);
         CONNECTIONS.decr(1)
     }
 }
@@ -54,6 +54,29 @@ quick_error! {
         PoolError {
             description("error sending message to worker pool")
         }
+        /// Client sends messages faster than `message-rate-limit` allows
+        RateLimitExceeded {
+            description("message rate limit exceeded")
+        }
+        /// Connection has `max-subscriptions-per-connection` topics already
+        SubscriptionLimitExceeded {
+            description("subscription limit exceeded")
+        }
+        /// Call with the same `request_id` is still waiting for the backend
+        DuplicateRequestId {
+            description("duplicate request id")
+        }
+        /// Client sets meta key reserved for swindon
+        /// (`reserved-meta-policy: reject`)
+        ReservedMetaKey(key: String) {
+            description("reserved meta key")
+            display("Reserved meta key {:?}", key)
+        }
+        /// Message exceeds `max-json-depth` or `max-json-elements`
+        TooComplex(reason: &'static str) {
+            description("message is too complex")
+            display("Message is too complex: {}", reason)
+        }
     }
 }
 
@@ -96,6 +119,21 @@ impl Serialize for MessageError {
             PoolError => {
                 serializer.serialize_str("unexpected_pool_error")
             }
+            RateLimitExceeded => {
+                serializer.serialize_str("rate_limit_exceeded")
+            }
+            SubscriptionLimitExceeded => {
+                serializer.serialize_str("subscription_limit_exceeded")
+            }
+            DuplicateRequestId => {
+                serializer.serialize_str("duplicate_request_id")
+            }
+            ReservedMetaKey(_) => {
+                serializer.serialize_str("reserved_meta_key")
+            }
+            TooComplex(reason) => {
+                serializer.serialize_str(reason)
+            }
         }
     }
 }
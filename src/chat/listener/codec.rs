@@ -3,14 +3,16 @@ use std::mem;
 use std::sync::Arc;
 use std::net::SocketAddr;
 
-use futures::Async;
-use futures::future::{FutureResult, ok};
+use futures::{Async, Future};
+use futures::future::{ok};
+use futures::sync::oneshot;
 use tk_http::Status;
 use tk_http::server::{Dispatcher, Error, Head};
 use tk_http::server as http;
 use tk_http::server::{EncoderDone, RecvMode};
 use serde_json::{self, Value as Json};
 
+use crate::incoming::Reply;
 use crate::intern::{Topic, Lattice as Namespace, SessionId};
 use crate::chat::cid::PubCid;
 use crate::chat::processor::Action;
@@ -26,6 +28,8 @@ pub struct Handler {
 
 pub enum State {
     Query(Route),
+    /// Waits for processor to accept or reject local subscription
+    Subscribing(oneshot::Receiver<bool>),
     Done,
     Error(Status),
 }
@@ -115,7 +119,7 @@ impl Handler {
     }
 }
 
-impl<S> Dispatcher<S> for Handler {
+impl<S: 'static> Dispatcher<S> for Handler {
     type Codec = Request;
     fn headers_received(&mut self, headers: &Head)
         -> Result<Self::Codec, Error>
@@ -288,8 +292,8 @@ impl Handler {
     }
 }
 
-impl<S> http::Codec<S> for Request {
-    type ResponseFuture = FutureResult<EncoderDone<S>, Error>;
+impl<S: 'static> http::Codec<S> for Request {
+    type ResponseFuture = Reply<S>;
     fn recv_mode(&mut self) -> RecvMode {
         RecvMode::buffered_upfront(self.wdata.settings.max_payload_size)
     }
@@ -304,20 +308,25 @@ impl<S> http::Codec<S> for Request {
         self.state = match query {
             State::Query(Subscribe(PubCid(cid, srv_id), topic)) => {
                 if data.len() == 0 {
-                    if srv_id == my_srv_id {
+                    let state = if srv_id == my_srv_id {
+                        let (tx, rx) = oneshot::channel();
                         self.wdata.processor.send(Action::Subscribe {
                             conn_id: cid,
                             topic: topic.clone(),
+                            reply: Some(tx),
                         });
+                        State::Subscribing(rx)
                     } else {
+                        // limit is checked by the server owning connection
                         debug!("Skipping action with non-local cid");
-                    }
+                        State::Done
+                    };
                     self.wdata.remote.send(RemoteAction::Subscribe {
                         conn_id: cid,
                         server_id: srv_id,
                         topic: topic,
                     });
-                    State::Done
+                    state
                 } else {
                     State::Error(Status::BadRequest)
                 }
@@ -517,23 +526,41 @@ impl<S> http::Codec<S> for Request {
                 State::Done
             }
             State::Done => unreachable!(),
+            State::Subscribing(..) => unreachable!(),
             State::Error(e) => State::Error(e),
         };
         Ok(Async::Ready(data.len()))
     }
-    fn start_response(&mut self, mut e: http::Encoder<S>)
+    fn start_response(&mut self, e: http::Encoder<S>)
         -> Self::ResponseFuture
     {
-        if let State::Error(status) = self.state {
-            e.status(status);
-            // TODO(tailhook) add some body describing the error
-            e.add_length(0).unwrap();
-            e.done_headers().unwrap();
-            ok(e.done())
-        } else {
-            e.status(Status::NoContent);
-            e.done_headers().unwrap();
-            ok(e.done())
+        match mem::replace(&mut self.state, State::Done) {
+            State::Error(status) => Box::new(ok(empty_reply(e, status))),
+            State::Subscribing(rx) => {
+                Box::new(rx.then(move |res| -> Result<_, Error> {
+                    match res {
+                        Ok(false) => Ok(empty_reply(e, Status::Forbidden)),
+                        // processor drops the reply if pool does not exist,
+                        // this is ignored the same way as for other actions
+                        Ok(true) | Err(oneshot::Canceled) => {
+                            Ok(empty_reply(e, Status::NoContent))
+                        }
+                    }
+                }))
+            }
+            _ => Box::new(ok(empty_reply(e, Status::NoContent))),
         }
     }
 }
+
+fn empty_reply<S>(mut e: http::Encoder<S>, status: Status)
+    -> EncoderDone<S>
+{
+    e.status(status);
+    if status != Status::NoContent {
+        // TODO(tailhook) add some body describing the error
+        e.add_length(0).unwrap();
+    }
+    e.done_headers().unwrap();
+    e.done()
+}
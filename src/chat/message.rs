@@ -4,10 +4,13 @@
 /// ["chat.send_message", {"request_id": "123"}, ["text"], {}]
 /// ```
 use std::str;
+use std::collections::BTreeMap;
+
 use serde_json::{self, Value as Json, Map, Error as JsonError};
 use serde::ser::{Serialize, Serializer, SerializeTuple};
 
 use super::cid::Cid;
+use crate::chat::MessageError;
 use crate::runtime::ServerId;
 
 pub type Meta = Map<String, Json>;
@@ -26,6 +29,63 @@ pub fn decode_message(s: &str)
 }
 
 
+/// Limits on complexity of the messages received from clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// Maximum nesting of arrays and objects
+    pub max_depth: usize,
+    /// Maximum number of items in any single array or object
+    pub max_elements: usize,
+}
+
+/// Checks message against the limits before it's decoded
+///
+/// This is a single pass over the data which only tracks strings and
+/// brackets, malformed JSON is left for the real parser to report.
+pub fn check_limits(s: &str, limits: &JsonLimits)
+    -> Result<(), MessageError>
+{
+    // number of commas for every open array or object
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escape = false;
+    for &c in s.as_bytes() {
+        if in_string {
+            if escape {
+                escape = false;
+            } else if c == b'\\' {
+                escape = true;
+            } else if c == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                if stack.len() >= limits.max_depth {
+                    return Err(MessageError::TooComplex("nesting too deep"));
+                }
+                stack.push(0);
+            }
+            b']' | b'}' => {
+                stack.pop();
+            }
+            b',' => {
+                if let Some(commas) = stack.last_mut() {
+                    *commas += 1;
+                    if *commas >= limits.max_elements {
+                        return Err(MessageError::TooComplex(
+                            "too many elements"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 /// Returns true if Meta object contains 'active' key and
 /// it either set to true or uint timeout (in seconds).
 pub fn get_active(meta: &Meta) -> Option<u64>
@@ -64,6 +124,62 @@ pub fn valid_request_id(meta: &Meta) -> bool {
      }
 }
 
+/// Returns request id if it's valid, so it's safe to put it into logs
+pub fn get_request_id(meta: &Meta) -> Option<&Json> {
+    if valid_request_id(meta) {
+        meta.get("request_id")
+    } else {
+        None
+    }
+}
+
+/// How requests to the backend are serialized
+///
+/// Compact form is used unless debugging options are enabled in the
+/// handler settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonFormat {
+    pub pretty: bool,
+    pub sort_keys: bool,
+}
+
+/// Encodes message sent to the backend (`Auth`, `Call`) in specified format
+pub fn encode<T: Serialize>(data: &T, format: JsonFormat)
+    -> Result<String, JsonError>
+{
+    match format {
+        JsonFormat { pretty: false, sort_keys: false } => {
+            serde_json::to_string(data)
+        }
+        JsonFormat { pretty, sort_keys: true } => {
+            let value = sorted(serde_json::to_value(data)?);
+            if pretty {
+                serde_json::to_string_pretty(&value)
+            } else {
+                serde_json::to_string(&value)
+            }
+        }
+        JsonFormat { pretty: true, sort_keys: false } => {
+            serde_json::to_string_pretty(data)
+        }
+    }
+}
+
+fn sorted(value: Json) -> Json {
+    match value {
+        Json::Object(map) => {
+            let map: BTreeMap<_, _> = map.into_iter()
+                .map(|(k, v)| (k, sorted(v)))
+                .collect();
+            Json::Object(map.into_iter().collect())
+        }
+        Json::Array(items) => {
+            Json::Array(items.into_iter().map(sorted).collect())
+        }
+        value => value,
+    }
+}
+
 #[derive(Serialize)]
 pub struct AuthData {
     pub http_cookie: Option<String>,
@@ -151,6 +267,8 @@ mod test {
     use serde_json::to_string as json_encode;
 
     use crate::chat::message::{self, Call, Meta, Args, Kwargs, Auth, AuthData};
+    use crate::chat::message::{encode, JsonFormat};
+    use crate::chat::message::{check_limits, JsonLimits};
 
     #[test]
     fn decode_message_errors() {
@@ -292,6 +410,80 @@ mod test {
             r#"{"room":123}]"#));
     }
 
+    #[test]
+    fn encode_format() {
+        let mut meta = Meta::new();
+        let args = vec![json!("Hello")];
+        let mut kw = Kwargs::new();
+        let cid = "123".parse().unwrap();
+        let sid = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+        meta.insert("request_id".into(), json!("123"));
+        kw.insert("room".into(), json!(123));
+        let call = Call(&meta, &cid, &sid, &args, &kw);
+
+        assert_eq!(encode(&call, JsonFormat::default()).unwrap(),
+                   json_encode(&call).unwrap());
+
+        let pretty = JsonFormat { pretty: true, sort_keys: false };
+        assert_eq!(encode(&call, pretty).unwrap(), concat!(
+            "[\n",
+            "  {\n",
+            "    \"connection_id\": ",
+                "\"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa-123\",\n",
+            "    \"request_id\": \"123\"\n",
+            "  },\n",
+            "  [\n",
+            "    \"Hello\"\n",
+            "  ],\n",
+            "  {\n",
+            "    \"room\": 123\n",
+            "  }\n",
+            "]"));
+
+        let auth = AuthData {
+            http_cookie: Some("auth=ok".to_string()),
+            http_authorization: None,
+            url_querystring: "".to_string(),
+        };
+        let sorted = JsonFormat { pretty: false, sort_keys: true };
+        assert_eq!(encode(&Auth(&cid, &sid, &auth), sorted).unwrap(), concat!(
+            r#"[{"connection_id":"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa-123"},"#,
+            r#"[],{"http_authorization":null,"http_cookie":"auth=ok","#,
+            r#""url_querystring":""}]"#));
+    }
+
+    #[test]
+    fn limits() {
+        let lim = JsonLimits { max_depth: 4, max_elements: 4 };
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, [1, [2, 3]], {}]"#, &lim)
+            .is_ok());
+        // meta is at depth 2, so 3 levels are left for arguments
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, [[[1]]], {}]"#, &lim).is_ok());
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, [[[[1]]]], {}]"#, &lim)
+            .is_err());
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, [1, 2, 3, 4], {}]"#, &lim)
+            .is_ok());
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, [1, 2, 3, 4, 5], {}]"#, &lim)
+            .is_err());
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, [],
+                {"a": 1, "b": 2, "c": 3, "d": 4}]"#,
+            &lim).is_ok());
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1", "x": 1, "y": 2, "z": 3, "w": 4},
+                [], {}]"#,
+            &lim).is_err());
+        // brackets and commas in strings don't count
+        assert!(check_limits(
+            r#"["a.b", {"request_id": "1"}, ["[[[[,,,,\"", "]]"], {}]"#,
+            &lim).is_ok());
+    }
+
     #[test]
     fn get_active() {
         let mut meta = Meta::new();
@@ -9,18 +9,26 @@ mod error;
 mod inactivity_handler;
 mod listener;
 mod message;
+mod pending;
 mod processor;
+mod rate_limit;
+mod reload;
 mod replication;
 pub mod tangle_auth;
 
 pub use self::cid::Cid;
 pub use self::authorize::{start_authorize, good_status};
-pub use self::message::{Meta, Args, Kwargs};
+pub use self::message::{Meta, Args, Kwargs, JsonFormat, JsonLimits};
+pub use self::message::get_request_id;
 pub use self::error::MessageError;
 pub use self::close_reason::CloseReason;
 pub use self::listener::SessionPools;
 pub use self::processor::{Processor, ConnectionMessage, json_err};
+pub use self::processor::json_err_with_connection_id;
 pub use self::dispatcher::Dispatcher;
+pub use self::rate_limit::RateLimiter;
+pub use self::pending::{PendingRequests, PendingGuard};
+pub use self::reload::ReloadTracker;
 pub use self::connection_sender::ConnectionSender;
 pub use self::replication::ReplicationSession;
 
@@ -30,6 +38,7 @@ lazy_static! {
     pub static ref CONNECTS: Counter = Counter::new();
     pub static ref CONNECTIONS: Integer = Integer::new();
     pub static ref FRAMES_SENT: Counter = Counter::new();
+    pub static ref ORPHANED_RESPONSES: Counter = Counter::new();
 }
 
 pub struct Shutdown;
@@ -40,7 +49,15 @@ pub fn metrics() -> List {
         (Metric("websockets.swindon_chat", "connections"), &*CONNECTIONS),
         (Metric("websockets.swindon_chat", "frames_received"),
             &*dispatcher::FRAMES_RECEIVED),
+        (Metric("websockets.swindon_chat", "rate_limited_frames"),
+            &*dispatcher::RATE_LIMITED),
+        (Metric("websockets.swindon_chat", "duplicate_request_ids"),
+            &*dispatcher::DUPLICATE_REQUEST_IDS),
+        (Metric("websockets.swindon_chat", "reserved_meta_keys"),
+            &*dispatcher::RESERVED_META_KEYS),
         (Metric("websockets.swindon_chat", "frames_sent"), &*FRAMES_SENT),
+        (Metric("websockets.swindon_chat", "orphaned_responses"),
+            &*ORPHANED_RESPONSES),
         (Metric("websockets.swindon_chat", "session_pools"),
             &*processor::SESSION_POOLS),
         (Metric("websockets.swindon_chat", "active_sessions"),
@@ -53,6 +70,8 @@ pub fn metrics() -> List {
             &*processor::PUBSUB_OUTPUT),
         (Metric("websockets.swindon_chat.pubsub", "topics"),
             &*processor::TOPICS),
+        (Metric("websockets.swindon_chat.pubsub", "rejected_subscriptions"),
+            &*processor::SUBSCRIPTIONS_REJECTED),
         (Metric("websockets.swindon_chat.lattice", "namespaces"),
             &*processor::LATTICES),
         (Metric("websockets.swindon_chat.lattice.shared", "keys"),
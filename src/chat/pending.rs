@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::chat::Meta;
+
+
+/// Request ids of the method calls of a single connection that are
+/// waiting for the backend (`reject-duplicate-request-id` setting)
+#[derive(Clone)]
+pub struct PendingRequests {
+    ids: Arc<Mutex<HashSet<String>>>,
+}
+
+/// Keeps request id pending, the id is released on drop
+pub struct PendingGuard {
+    id: String,
+    ids: Arc<Mutex<HashSet<String>>>,
+}
+
+impl PendingRequests {
+    pub fn new() -> PendingRequests {
+        PendingRequests {
+            ids: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+    /// Returns a guard unless call with the same `request_id` is pending
+    ///
+    /// Ids are compared in their JSON form, so `"1"` and `1` are different
+    /// ids (as they are for the client).
+    pub fn start(&self, meta: &Meta) -> Option<PendingGuard> {
+        let id = meta.get("request_id")
+            .expect("request id is validated")
+            .to_string();
+        let mut ids = self.ids.lock().expect("pending ids not poisoned");
+        if !ids.insert(id.clone()) {
+            return None;
+        }
+        Some(PendingGuard {
+            id: id,
+            ids: self.ids.clone(),
+        })
+    }
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.ids.lock().expect("pending ids not poisoned")
+            .remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chat::Meta;
+    use super::PendingRequests;
+
+    fn meta(id: serde_json::Value) -> Meta {
+        let mut meta = Meta::new();
+        meta.insert("request_id".into(), id);
+        meta
+    }
+
+    #[test]
+    fn duplicate() {
+        let pending = PendingRequests::new();
+        let g1 = pending.start(&meta(json!("a"))).unwrap();
+        assert!(pending.start(&meta(json!("a"))).is_none());
+        let g2 = pending.start(&meta(json!(1))).unwrap();
+        assert!(pending.start(&meta(json!("1"))).is_some());
+        drop(g1);
+        let g3 = pending.start(&meta(json!("a"))).unwrap();
+        drop((g2, g3));
+        assert!(pending.ids.lock().unwrap().is_empty());
+    }
+}
@@ -16,6 +16,7 @@ pub struct NewConnection {
     pub users_lattice: HashSet<SessionId>,
     pub message_buffer: Vec<(Topic, Arc<Json>)>,
     pub channel: ConnectionSender,
+    pub max_subscriptions: Option<usize>,
 }
 
 
@@ -26,10 +27,12 @@ pub struct Connection {
     pub lattices: HashSet<Namespace>,
     pub users_lattice: bool,
     pub channel: ConnectionSender,
+    pub max_subscriptions: Option<usize>,
 }
 
 impl NewConnection {
-    pub fn new(conn_id: Cid, channel: ConnectionSender)
+    pub fn new(conn_id: Cid, channel: ConnectionSender,
+        max_subscriptions: Option<usize>)
         -> NewConnection
     {
         NewConnection {
@@ -39,6 +42,7 @@ impl NewConnection {
             users_lattice: HashSet::new(),
             message_buffer: Vec::new(),
             channel: channel,
+            max_subscriptions: max_subscriptions,
         }
     }
     pub fn associate(self, session_id: SessionId)
@@ -51,6 +55,7 @@ impl NewConnection {
             lattices: self.lattices,
             users_lattice: self.users_lattice.len() > 0,
             channel: self.channel,
+            max_subscriptions: self.max_subscriptions,
         };
         for (t, m) in self.message_buffer {
             conn.message(t, m);
@@ -60,6 +65,9 @@ impl NewConnection {
     pub fn message(&mut self, topic: Topic, data: Arc<Json>) {
         self.message_buffer.push((topic, data));
     }
+    pub fn can_subscribe(&self, topic: &Topic) -> bool {
+        can_subscribe(&self.topics, self.max_subscriptions, topic)
+    }
     pub fn stop(&mut self, reason: CloseReason) {
         self.channel.send(ConnectionMessage::StopSock(reason));
     }
@@ -70,6 +78,9 @@ impl Connection {
     pub fn message(&mut self, topic: Topic, data: Arc<Json>) {
         self.channel.send(ConnectionMessage::Publish(topic, data));
     }
+    pub fn can_subscribe(&self, topic: &Topic) -> bool {
+        can_subscribe(&self.topics, self.max_subscriptions, topic)
+    }
 
     pub fn lattice(&mut self, namespace: &Namespace,
         update: &Arc<HashMap<LatticeKey, lattice::Values>>)
@@ -82,3 +93,9 @@ impl Connection {
         self.channel.send(ConnectionMessage::StopSock(reason));
     }
 }
+
+fn can_subscribe(topics: &HashSet<Topic>, max: Option<usize>, topic: &Topic)
+    -> bool
+{
+    topics.contains(topic) || max.map(|max| topics.len() < max).unwrap_or(true)
+}
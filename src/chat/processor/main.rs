@@ -18,8 +18,8 @@ fn pool_action(pool: &mut Pool, ts: Instant, action: Action) {
         NewSessionPool {..} => unreachable!(),
         StopSessionPool => unreachable!(),
         // Connection management
-        NewConnection { conn_id, channel } => {
-            pool.add_connection(conn_id, channel);
+        NewConnection { conn_id, channel, max_subscriptions } => {
+            pool.add_connection(conn_id, channel, max_subscriptions);
         }
         Associate { session_id, conn_id, metadata } => {
             pool.associate(conn_id, session_id, ts, metadata);
@@ -31,8 +31,11 @@ fn pool_action(pool: &mut Pool, ts: Instant, action: Action) {
             pool.del_connection(conn_id);
         }
         // Subscriptions
-        Subscribe { conn_id, topic } => {
-            pool.subscribe(conn_id, topic);
+        Subscribe { conn_id, topic, reply } => {
+            let subscribed = pool.subscribe(conn_id, topic);
+            if let Some(reply) = reply {
+                reply.send(subscribed).ok();
+            }
         }
         Unsubscribe { conn_id, topic } => {
             pool.unsubscribe(conn_id, topic);
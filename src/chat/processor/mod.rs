@@ -16,6 +16,7 @@ use std::fmt;
 use serde_json::Value as Json;
 use serde::ser::{Serialize, Serializer, SerializeTuple};
 use futures::sync::mpsc::{UnboundedSender as Sender};
+use futures::sync::oneshot;
 
 use crate::config;
 use crate::intern::{Topic, SessionId, SessionPoolName, Lattice as Namespace};
@@ -39,6 +40,7 @@ pub use self::lattice::Delta;
 pub use self::main::{SESSION_POOLS};
 pub use self::pool::{ACTIVE_SESSIONS, INACTIVE_SESSIONS};
 pub use self::pool::{PUBSUB_INPUT, PUBSUB_OUTPUT, TOPICS};
+pub use self::pool::{SUBSCRIPTIONS_REJECTED};
 pub use self::pool::{LATTICES};
 pub use self::lattice::{SHARED_KEYS, PRIVATE_KEYS};
 pub use self::lattice::{SHARED_COUNTERS, PRIVATE_COUNTERS};
@@ -110,6 +112,8 @@ pub enum Action {
     NewConnection {
         conn_id: Cid,
         channel: ConnectionSender,
+        /// `max-subscriptions-per-connection` of the chat handler
+        max_subscriptions: Option<usize>,
     },
     Associate {
         conn_id: Cid,
@@ -130,6 +134,9 @@ pub enum Action {
     Subscribe {
         conn_id: Cid,
         topic: Topic,
+        /// Receives `false` if subscription is rejected because of
+        /// `max-subscriptions-per-connection`
+        reply: Option<oneshot::Sender<bool>>,
     },
     Unsubscribe {
         conn_id: Cid,
@@ -196,12 +203,36 @@ pub fn json_err(err: &MessageError) -> Json {
         &MessageError::ValidationError(_) => {
             json!({"error_kind": "validation_error"})
         }
+        &MessageError::RateLimitExceeded => {
+            json!({"error_kind": "rate_limit_exceeded"})
+        }
+        &MessageError::SubscriptionLimitExceeded => {
+            json!({"error_kind": "subscription_limit_exceeded"})
+        }
+        &MessageError::DuplicateRequestId => {
+            json!({"error_kind": "duplicate_request_id"})
+        }
+        &MessageError::ReservedMetaKey(_) => {
+            json!({"error_kind": "reserved_meta_key"})
+        }
         _ => {
             json!({"error_kind": "internal_error"})
         }
     }
 }
 
+/// Error message with `connection_id` added to the metadata
+/// (`connection-id-in-errors` setting of a chat handler)
+pub fn json_err_with_connection_id(meta: &Meta, err: &MessageError,
+    connection_id: &str)
+    -> Json
+{
+    let mut extra = json_err(err);
+    extra["connection_id"] = Json::String(connection_id.to_string());
+    let meta = MetaWithExtra { meta: meta, extra: extra };
+    json!(["error", meta, err])
+}
+
 impl Serialize for ConnectionMessage {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
     {
@@ -286,7 +317,7 @@ impl fmt::Debug for Action {
             &Disconnect { ref conn_id } => {
                 write!(f, "Action::Disconnect({:?})", conn_id)
             }
-            &Subscribe { ref conn_id, ref topic } => {
+            &Subscribe { ref conn_id, ref topic, .. } => {
                 write!(f, "Action::Subscribe({:?}, {:?})", conn_id, topic)
             }
             &Unsubscribe { ref conn_id, ref topic } => {
@@ -9,7 +9,7 @@ use futures::sync::mpsc::{UnboundedSender as Sender};
 use crate::intern::{Topic, SessionId, SessionPoolName, Lattice as Namespace};
 use crate::intern::{LatticeKey};
 use crate::config;
-use crate::chat::{Cid, CloseReason, ConnectionSender};
+use crate::chat::{Cid, CloseReason, ConnectionSender, Meta, MessageError};
 use super::{ConnectionMessage, PoolMessage};
 use super::session::Session;
 use super::connection::{NewConnection, Connection};
@@ -30,6 +30,7 @@ lazy_static! {
     pub static ref PUBSUB_INPUT: Counter = Counter::new();
     pub static ref PUBSUB_OUTPUT: Counter = Counter::new();
     pub static ref TOPICS: Integer = Integer::new();
+    pub static ref SUBSCRIPTIONS_REJECTED: Counter = Counter::new();
 
     pub static ref LATTICES: Integer = Integer::new();
 }
@@ -112,10 +113,10 @@ impl Pool {
     }
 
     pub fn add_connection(&mut self, conn_id: Cid,
-        channel: ConnectionSender)
+        channel: ConnectionSender, max_subscriptions: Option<usize>)
     {
         let old = self.pending_connections.insert(conn_id,
-            NewConnection::new(conn_id, channel));
+            NewConnection::new(conn_id, channel, max_subscriptions));
         debug!("Add new connection {:?}", conn_id);
         debug_assert!(old.is_none());
     }
@@ -393,8 +394,22 @@ impl Pool {
         self.sessions.active.peek().map(|(_, &x, _)| x)
     }
 
-    pub fn subscribe(&mut self, cid: Cid, topic: Topic) {
+    /// Returns `false` if subscription is rejected because of
+    /// `max-subscriptions-per-connection`
+    ///
+    /// Subscribing a connection that does not exist any more is not
+    /// an error.
+    pub fn subscribe(&mut self, cid: Cid, topic: Topic) -> bool {
         if let Some(conn) = self.connections.get_mut(&cid) {
+            if !conn.can_subscribe(&topic) {
+                debug!("Connection {:?} has too many subscriptions, \
+                    not subscribing to {:?}", cid, topic);
+                SUBSCRIPTIONS_REJECTED.incr(1);
+                conn.channel.send(ConnectionMessage::Error(
+                    Arc::new(Meta::new()),
+                    MessageError::SubscriptionLimitExceeded));
+                return false;
+            }
             conn.topics.insert(topic.clone());
             self.topics.entry(topic)
                 .or_insert_with(|| {
@@ -403,6 +418,13 @@ impl Pool {
                 })
                 .insert(cid, Subscription::Session);
         } else if let Some(conn) = self.pending_connections.get_mut(&cid) {
+            if !conn.can_subscribe(&topic) {
+                // can't send error before `hello`, so just skip it
+                debug!("Connection {:?} has too many subscriptions, \
+                    not subscribing to {:?}", cid, topic);
+                SUBSCRIPTIONS_REJECTED.incr(1);
+                return false;
+            }
             conn.topics.insert(topic.clone());
             self.topics.entry(topic)
                 .or_insert_with(|| {
@@ -413,6 +435,7 @@ impl Pool {
         } else {
             debug!("Connection {:?} does not exist any more", cid);
         }
+        return true;
     }
 
     pub fn unsubscribe(&mut self, cid: Cid, topic: Topic) {
@@ -837,12 +860,12 @@ mod test {
     use futures::stream::Stream;
     use futures::sync::mpsc::{unbounded as channel};
     use futures::sync::mpsc::{UnboundedReceiver as Receiver};
-    use crate::intern::{SessionId, SessionPoolName, Lattice as Ns};
+    use crate::intern::{SessionId, SessionPoolName, Lattice as Ns, Topic};
 
     use string_intern::{Symbol, Validator};
     use crate::config;
     use crate::config::listen::Listen;
-    use crate::chat::{Cid, ConnectionSender};
+    use crate::chat::{Cid, ConnectionSender, MessageError};
 
     use super::Pool;
     use super::super::lattice::{Delta, Values};
@@ -876,7 +899,7 @@ mod test {
     fn add_u1(pool: &mut Pool) -> (Cid, Receiver<ConnectionMessage>) {
         let cid = Cid::new();
         let (tx, rx) = ConnectionSender::new();
-        pool.add_connection(cid, tx);
+        pool.add_connection(cid, tx, None);
         pool.associate(cid, SessionId::from("user1"), Instant::now(),
             Arc::new(json!({"user_id": "user1"})));
         return (cid, rx);
@@ -885,7 +908,7 @@ mod test {
     fn add_u2(pool: &mut Pool) -> (Cid, Receiver<ConnectionMessage>) {
         let cid = Cid::new();
         let (tx, rx) = ConnectionSender::new();
-        pool.add_connection(cid, tx);
+        pool.add_connection(cid, tx, None);
         pool.associate(cid, SessionId::from("user2"), Instant::now(),
             Arc::new(json!({"user_id": "user2"})));
         return (cid, rx);
@@ -946,6 +969,27 @@ mod test {
         assert_eq!(pool.sessions.inactive.len(), 0);
     }
 
+    #[test]
+    fn subscription_limit() {
+        let (mut pool, _rx) = pool();
+        let cid = Cid::new();
+        let (tx, mut rx) = ConnectionSender::new();
+        pool.add_connection(cid, tx, Some(2));
+        pool.associate(cid, SessionId::from("user1"), Instant::now(),
+            Arc::new(json!({"user_id": "user1"})));
+        assert!(pool.subscribe(cid, Topic::from("t1")));
+        assert!(pool.subscribe(cid, Topic::from("t2")));
+        // already subscribed topic doesn't count
+        assert!(pool.subscribe(cid, Topic::from("t1")));
+        assert!(!pool.subscribe(cid, Topic::from("t3")));
+        assert_matches!(get_item(&mut rx), ConnectionMessage::Error(_,
+            MessageError::SubscriptionLimitExceeded));
+        assert_eq!(pool.topics.len(), 2);
+        assert!(!pool.topics.contains_key(&Topic::from("t3")));
+        pool.del_connection(cid);
+        assert_eq!(pool.topics.len(), 0);
+    }
+
     trait Builder {
         type Key;
         type Value;
@@ -974,7 +1018,7 @@ mod test {
         let (mut pool, _rx) = pool();
         let cid = Cid::new();
         let (tx, mut rx) = ConnectionSender::new();
-        pool.add_connection(cid, tx);
+        pool.add_connection(cid, tx, None);
         pool.lattice_update(Ns::from("rooms"), Delta {
             shared: builder(),
             private: builder().add("user1", builder()),
@@ -0,0 +1,95 @@
+use std::time::Instant;
+
+use crate::config::chat::{MessageRateLimit, RateLimitPolicy};
+
+
+/// Token bucket limiting number of messages received by single connection
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated_at: Instant,
+    policy: RateLimitPolicy,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &MessageRateLimit, now: Instant) -> RateLimiter {
+        RateLimiter {
+            rate: cfg.rate as f64,
+            burst: cfg.burst as f64,
+            tokens: cfg.burst as f64,
+            updated_at: now,
+            policy: cfg.on_exceed,
+        }
+    }
+    pub fn policy(&self) -> RateLimitPolicy {
+        self.policy
+    }
+    /// Returns `true` if message received at `now` is within the limit
+    pub fn check(&mut self, now: Instant) -> bool {
+        if now > self.updated_at {
+            let elapsed = now - self.updated_at;
+            let secs = elapsed.as_secs() as f64 +
+                elapsed.subsec_nanos() as f64 * 1e-9;
+            self.tokens = (self.tokens + secs * self.rate).min(self.burst);
+            self.updated_at = now;
+        }
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Instant, Duration};
+    use crate::config::chat::{MessageRateLimit, RateLimitPolicy};
+    use super::RateLimiter;
+
+    fn limiter(now: Instant) -> RateLimiter {
+        RateLimiter::new(&MessageRateLimit {
+            rate: 2,
+            burst: 3,
+            on_exceed: RateLimitPolicy::drop,
+        }, now)
+    }
+
+    #[test]
+    fn burst() {
+        let now = Instant::now();
+        let mut lim = limiter(now);
+        assert!(lim.check(now));
+        assert!(lim.check(now));
+        assert!(lim.check(now));
+        assert!(!lim.check(now));
+        assert!(!lim.check(now));
+    }
+
+    #[test]
+    fn refill() {
+        let now = Instant::now();
+        let mut lim = limiter(now);
+        for _ in 0..3 {
+            assert!(lim.check(now));
+        }
+        assert!(!lim.check(now + Duration::from_millis(400)));
+        assert!(lim.check(now + Duration::from_millis(600)));
+        assert!(!lim.check(now + Duration::from_millis(700)));
+        assert!(lim.check(now + Duration::from_millis(1200)));
+    }
+
+    #[test]
+    fn refill_is_capped_by_burst() {
+        let now = Instant::now();
+        let mut lim = limiter(now);
+        let later = now + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(lim.check(later));
+        }
+        assert!(!lim.check(later));
+    }
+}
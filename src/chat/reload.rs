@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::chat::{Cid, CloseReason, ConnectionSender, ConnectionMessage};
+use crate::config::Config;
+use crate::config::chat::{Chat, ReloadPolicy};
+use crate::config::handlers::Handler;
+use crate::config::http_destinations::Destination;
+use crate::config::session_pools::SessionPool;
+use crate::intern::Upstream;
+
+
+/// Established connections of handlers with `on-reload: reconnect`
+///
+/// Connections of handlers with default `on-reload: keep` are not tracked.
+#[derive(Clone)]
+pub struct ReloadTracker {
+    connections: Arc<Mutex<HashMap<Cid, (Settings, ConnectionSender)>>>,
+}
+
+/// Parts of the config that connection depends on
+///
+/// Besides handler settings itself, these are the session pool and all
+/// http destinations that messages and inactivity notifications are
+/// sent to.
+struct Settings {
+    chat: Arc<Chat>,
+    session_pool: Option<Arc<SessionPool>>,
+    destinations: Vec<(Upstream, Option<Arc<Destination>>)>,
+}
+
+impl Settings {
+    fn new(chat: &Arc<Chat>, config: &Config) -> Settings {
+        let session_pool = config.session_pools.get(&chat.session_pool)
+            .cloned();
+        let mut upstreams = Vec::new();
+        {
+            let handlers = &chat.message_handlers;
+            let inactivity = session_pool.iter()
+                .flat_map(|p| p.inactivity_handlers.iter());
+            let all = Some(&handlers.default).into_iter()
+                .chain(handlers.map.values())
+                .chain(inactivity);
+            for dest in all {
+                if !upstreams.contains(&dest.upstream) {
+                    upstreams.push(dest.upstream.clone());
+                }
+            }
+        }
+        Settings {
+            chat: chat.clone(),
+            session_pool: session_pool,
+            destinations: upstreams.into_iter().map(|up| {
+                let dest = config.http_destinations.get(&up).cloned();
+                (up, dest)
+            }).collect(),
+        }
+    }
+    fn unchanged(&self, config: &Config) -> bool {
+        let chat = config.handlers.values().any(|h| match *h {
+            Handler::SwindonLattice(ref new) => *new == self.chat,
+            _ => false,
+        });
+        chat &&
+            config.session_pools.get(&self.chat.session_pool)
+                == self.session_pool.as_ref() &&
+            self.destinations.iter().all(|&(ref up, ref dest)| {
+                config.http_destinations.get(up) == dest.as_ref()
+            })
+    }
+}
+
+impl ReloadTracker {
+    pub fn new() -> ReloadTracker {
+        ReloadTracker {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    /// Starts tracking connection, `config` is the one connection
+    /// is authorized with
+    pub fn add(&self, cid: Cid, settings: &Arc<Chat>, config: &Config,
+        channel: &ConnectionSender)
+    {
+        if settings.on_reload != ReloadPolicy::reconnect {
+            return;
+        }
+        self.connections.lock().expect("reload tracker not poisoned")
+            .insert(cid, (Settings::new(settings, config), channel.clone()));
+    }
+    pub fn remove(&self, cid: Cid) {
+        self.connections.lock().expect("reload tracker not poisoned")
+            .remove(&cid);
+    }
+    /// Asks connections to close if their handler settings are not in
+    /// the new config anymore, or if session pool or http destinations
+    /// they use have changed
+    pub fn update(&self, config: &Config) {
+        let mut conns = self.connections.lock()
+            .expect("reload tracker not poisoned");
+        conns.retain(|_, &mut (ref settings, ref channel)| {
+            let unchanged = settings.unchanged(config);
+            if !unchanged {
+                channel.send(ConnectionMessage::StopSock(
+                    CloseReason::ConfigReloaded));
+            }
+            unchanged
+        });
+    }
+}
@@ -98,6 +98,7 @@ impl Into<Action> for RemoteAction {
                 Action::Subscribe {
                     conn_id: conn_id,
                     topic: topic,
+                    reply: None,
                 }
             }
             Unsubscribe { conn_id, topic, server_id: _ } => {
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use quire::validate::{Structure, Mapping, Scalar};
+use serde::de::{Deserializer, Deserialize, Error};
+
+use crate::intern::HandlerName;
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ByExtension {
+    /// Lowercase extension (without a dot) to a handler name
+    pub extensions: HashMap<String, HandlerName>,
+    pub default: HandlerName,
+}
+
+pub fn validator<'x>() -> Structure<'x> {
+    Structure::new()
+    .member("extensions", Mapping::new(Scalar::new(), Scalar::new()))
+    .member("default", Scalar::new())
+}
+
+impl ByExtension {
+    /// Returns handler for the path (without a query string)
+    pub fn resolve(&self, path: &str) -> &HandlerName {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        match name.rfind('.') {
+            Some(idx) if idx > 0 => {
+                let ext = name[idx+1..].to_lowercase();
+                self.extensions.get(&ext).unwrap_or(&self.default)
+            }
+            _ => &self.default,
+        }
+    }
+}
+
+impl<'a> Deserialize<'a> for ByExtension {
+    fn deserialize<D: Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        pub struct Internal {
+            pub extensions: HashMap<String, HandlerName>,
+            pub default: HandlerName,
+        }
+        let int = Internal::deserialize(d)?;
+        let mut extensions = HashMap::new();
+        for (ext, handler) in int.extensions {
+            let key = ext.trim_start_matches('.').to_lowercase();
+            if key.is_empty() || key.contains(|c| c == '.' || c == '/') {
+                return Err(D::Error::custom(
+                    format!("invalid extension {:?}", ext)));
+            }
+            if extensions.insert(key, handler).is_some() {
+                return Err(D::Error::custom(
+                    format!("duplicate extension {:?}", ext)));
+            }
+        }
+        Ok(ByExtension {
+            extensions: extensions,
+            default: int.default,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use crate::intern::HandlerName;
+    use super::ByExtension;
+
+    #[test]
+    fn resolve() {
+        let mut extensions = HashMap::new();
+        extensions.insert("html".to_string(), HandlerName::from("pages"));
+        let h = ByExtension {
+            extensions: extensions,
+            default: HandlerName::from("raw"),
+        };
+        assert_eq!(h.resolve("/index.html"), &HandlerName::from("pages"));
+        assert_eq!(h.resolve("/a/b/INDEX.HTML"), &HandlerName::from("pages"));
+        assert_eq!(h.resolve("/img/logo.png"), &HandlerName::from("raw"));
+        assert_eq!(h.resolve("/html"), &HandlerName::from("raw"));
+        assert_eq!(h.resolve("/.html"), &HandlerName::from("raw"));
+        assert_eq!(h.resolve("/dir.html/file"), &HandlerName::from("raw"));
+        assert_eq!(h.resolve("/"), &HandlerName::from("raw"));
+    }
+}
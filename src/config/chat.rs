@@ -1,11 +1,14 @@
 use std::collections::BTreeMap;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::time::Duration;
 
 use serde::de::{Deserialize, Deserializer, Error};
-use quire::validate::{Structure, Scalar, Mapping};
+use quire::validate::{Structure, Scalar, Mapping, Numeric, Enum, Nothing};
+use quire::validate::{Sequence};
 
 use super::http;
+use crate::chat::{JsonFormat, JsonLimits, Meta};
 use crate::intern::{HandlerName, SessionPoolName};
 use crate::config::visitors::FromStrVisitor;
 use crate::config::version::Version;
@@ -23,6 +26,55 @@ pub enum Compatibility {
     latest
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum RateLimitPolicy {
+    /// Reply with `rate_limit_exceeded` error, don't forward the message
+    drop,
+    /// Close the websocket
+    close,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum ReloadPolicy {
+    /// Established connections keep using the settings they were
+    /// authorized with
+    keep,
+    /// Close established connections when handler settings change, so
+    /// clients reconnect with new settings
+    reconnect,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum OrphanPolicy {
+    /// Silently discard the response
+    drop,
+    /// Log the response, then discard it
+    log,
+}
+
+/// What to do when client sets a meta key that is reserved for swindon
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum ReservedMetaPolicy {
+    /// Silently replace (or remove) the key
+    #[serde(rename="override")]
+    override_,
+    /// Reply with `reserved_meta_key` error, don't forward the message
+    reject,
+    /// Log a warning, then replace (or remove) the key
+    log,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MessageRateLimit {
+    /// Messages per second
+    pub rate: u32,
+    pub burst: u32,
+    pub on_exceed: RateLimitPolicy,
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Chat {
@@ -30,6 +82,24 @@ pub struct Chat {
     pub session_pool: SessionPoolName,
     pub http_route: Option<HandlerName>,
     pub message_handlers: RoutingTable,
+    pub message_rate_limit: Option<MessageRateLimit>,
+    pub handshake_timeout: Duration,
+    pub connection_id_in_errors: bool,
+    pub max_auth_data_size: usize,
+    pub auth_timeout: Duration,
+    /// Websocket close code sent when `auth_timeout` is exceeded
+    pub auth_timeout_close_code: u16,
+    pub on_reload: ReloadPolicy,
+    pub on_orphaned_response: OrphanPolicy,
+    pub max_subscriptions_per_connection: Option<usize>,
+    pub pretty_backend_json: bool,
+    pub sort_backend_json_keys: bool,
+    pub reject_duplicate_request_id: bool,
+    pub max_json_depth: usize,
+    pub max_json_elements: usize,
+    pub reserved_meta_policy: ReservedMetaPolicy,
+    /// Keys reserved in addition to `connection_id`
+    pub reserved_meta_keys: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -58,6 +128,27 @@ impl Chat {
     pub fn weak_content_type(&self) -> bool {
         self.compatibility <= Compatibility::v0_6_2
     }
+    pub fn backend_json_format(&self) -> JsonFormat {
+        JsonFormat {
+            pretty: self.pretty_backend_json,
+            sort_keys: self.sort_backend_json_keys,
+        }
+    }
+    /// Returns first reserved key set in the meta by the client
+    pub fn find_reserved_meta_key<'x>(&self, meta: &'x Meta)
+        -> Option<&'x str>
+    {
+        meta.keys()
+            .find(|k| *k == "connection_id" ||
+                      self.reserved_meta_keys.iter().any(|r| r == *k))
+            .map(|k| &k[..])
+    }
+    pub fn json_limits(&self) -> JsonLimits {
+        JsonLimits {
+            max_depth: self.max_json_depth,
+            max_elements: self.max_json_elements,
+        }
+    }
 }
 
 pub fn validator<'x>() -> Structure<'x> {
@@ -68,6 +159,45 @@ pub fn validator<'x>() -> Structure<'x> {
     .member("http_route", http::destination_validator().optional())
     .member("message_handlers",
         Mapping::new(Scalar::new(), http::destination_validator()))
+    .member("message_rate_limit", Structure::new()
+        .member("rate", Numeric::new().min(1).max(1 << 20))
+        .member("burst", Numeric::new().min(1).max(1 << 20))
+        .member("on_exceed", Enum::new()
+            .option("drop", Nothing)
+            .option("close", Nothing)
+            .allow_plain()
+            .plain_default("drop"))
+        .optional())
+    .member("handshake_timeout", Scalar::new().default("60s"))
+    .member("connection_id_in_errors", Scalar::new().default(false))
+    .member("max_auth_data_size", Numeric::new().min(0).default(16384))
+    .member("auth_timeout", Scalar::new().default("60s"))
+    .member("auth_timeout_close_code",
+        Numeric::new().min(4000).max(4999).default(4408))
+    .member("on_reload", Enum::new()
+        .option("keep", Nothing)
+        .option("reconnect", Nothing)
+        .allow_plain()
+        .plain_default("keep"))
+    .member("on_orphaned_response", Enum::new()
+        .option("drop", Nothing)
+        .option("log", Nothing)
+        .allow_plain()
+        .plain_default("drop"))
+    .member("max_subscriptions_per_connection",
+        Numeric::new().min(1).optional())
+    .member("pretty_backend_json", Scalar::new().default(false))
+    .member("sort_backend_json_keys", Scalar::new().default(false))
+    .member("reject_duplicate_request_id", Scalar::new().default(false))
+    .member("max_json_depth", Numeric::new().min(2).max(128).default(32))
+    .member("max_json_elements", Numeric::new().min(4).default(65536))
+    .member("reserved_meta_policy", Enum::new()
+        .option("override", Nothing)
+        .option("reject", Nothing)
+        .option("log", Nothing)
+        .allow_plain()
+        .plain_default("override"))
+    .member("reserved_meta_keys", Sequence::new(Scalar::new()))
 }
 
 impl FromStr for Pattern {
@@ -146,6 +276,24 @@ impl<'a> Deserialize<'a> for Chat {
             session_pool: SessionPoolName,
             http_route: Option<HandlerName>,
             message_handlers: RoutingTable,
+            message_rate_limit: Option<MessageRateLimit>,
+            #[serde(with="::quire::duration")]
+            handshake_timeout: Duration,
+            connection_id_in_errors: bool,
+            max_auth_data_size: usize,
+            #[serde(with="::quire::duration")]
+            auth_timeout: Duration,
+            auth_timeout_close_code: u16,
+            on_reload: ReloadPolicy,
+            on_orphaned_response: OrphanPolicy,
+            max_subscriptions_per_connection: Option<usize>,
+            pretty_backend_json: bool,
+            sort_backend_json_keys: bool,
+            reject_duplicate_request_id: bool,
+            max_json_depth: usize,
+            max_json_elements: usize,
+            reserved_meta_policy: ReservedMetaPolicy,
+            reserved_meta_keys: Vec<String>,
         }
 
         let int = Internal::deserialize(d)?;
@@ -164,6 +312,23 @@ impl<'a> Deserialize<'a> for Chat {
             session_pool: int.session_pool,
             http_route: int.http_route,
             message_handlers: int.message_handlers,
+            message_rate_limit: int.message_rate_limit,
+            handshake_timeout: int.handshake_timeout,
+            connection_id_in_errors: int.connection_id_in_errors,
+            max_auth_data_size: int.max_auth_data_size,
+            auth_timeout: int.auth_timeout,
+            auth_timeout_close_code: int.auth_timeout_close_code,
+            on_reload: int.on_reload,
+            on_orphaned_response: int.on_orphaned_response,
+            max_subscriptions_per_connection:
+                int.max_subscriptions_per_connection,
+            pretty_backend_json: int.pretty_backend_json,
+            sort_backend_json_keys: int.sort_backend_json_keys,
+            reject_duplicate_request_id: int.reject_duplicate_request_id,
+            max_json_depth: int.max_json_depth,
+            max_json_elements: int.max_json_elements,
+            reserved_meta_policy: int.reserved_meta_policy,
+            reserved_meta_keys: int.reserved_meta_keys,
         })
     }
 }
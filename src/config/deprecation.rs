@@ -0,0 +1,86 @@
+use std::time::SystemTime;
+
+use humantime::parse_rfc3339_weak;
+use quire::validate::{Structure, Scalar};
+use serde::de::{Deserializer, Deserialize, Error};
+
+
+/// Headers announcing that route is going away (RFC 8594)
+#[derive(Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    pub deprecated_since: Option<SystemTime>,
+    pub sunset: Option<SystemTime>,
+    pub link: Option<String>,
+}
+
+pub fn validator<'x>() -> Structure<'x> {
+    Structure::new()
+    .member("deprecated_since", Scalar::new().optional())
+    .member("sunset", Scalar::new().optional())
+    .member("link", Scalar::new().optional())
+}
+
+/// Parses either a date `2020-01-31` or a timestamp `2020-01-31T12:00:00Z`
+fn parse_date(val: &str) -> Result<SystemTime, String> {
+    let result = if val.len() == 10 {
+        parse_rfc3339_weak(&format!("{}T00:00:00Z", val))
+    } else {
+        parse_rfc3339_weak(val)
+    };
+    result.map_err(|e| format!("invalid date {:?}: {}", val, e))
+}
+
+impl<'a> Deserialize<'a> for Deprecation {
+    fn deserialize<D: Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        pub struct Internal {
+            pub deprecated_since: Option<String>,
+            pub sunset: Option<String>,
+            pub link: Option<String>,
+        }
+        let int = Internal::deserialize(d)?;
+        let since = match int.deprecated_since {
+            Some(ref x) => Some(parse_date(x).map_err(D::Error::custom)?),
+            None => None,
+        };
+        let sunset = match int.sunset {
+            Some(ref x) => Some(parse_date(x).map_err(D::Error::custom)?),
+            None => None,
+        };
+        if let (Some(since), Some(sunset)) = (since, sunset) {
+            if sunset < since {
+                return Err(D::Error::custom(
+                    "sunset must not be earlier than deprecated-since"));
+            }
+        }
+        if let Some(ref link) = int.link {
+            if link.is_empty() || link.chars().any(|c| {
+                c.is_whitespace() || c.is_control() || c == '<' || c == '>'
+            }) {
+                return Err(D::Error::custom(
+                    format!("invalid deprecation link {:?}", link)));
+            }
+        }
+        Ok(Deprecation {
+            deprecated_since: since,
+            sunset: sunset,
+            link: int.link,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{UNIX_EPOCH, Duration};
+    use super::parse_date;
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("2030-01-01").unwrap(),
+                   UNIX_EPOCH + Duration::from_secs(1893456000));
+        assert_eq!(parse_date("2030-01-01T00:00:10Z").unwrap(),
+                   UNIX_EPOCH + Duration::from_secs(1893456010));
+        assert!(parse_date("2030-13-01").is_err());
+        assert!(parse_date("tomorrow").is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use quire::validate::{Structure, Scalar};
+
+use crate::intern::HandlerName;
+
+
+/// Serves a JSON document describing a chat handler
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct Discovery {
+    /// Name of the `!SwindonLattice` handler
+    pub chat: HandlerName,
+    #[serde(with="::quire::duration")]
+    pub max_age: Duration,
+}
+
+pub fn validator<'x>() -> Structure<'x> {
+    Structure::new()
+    .member("chat", Scalar::new())
+    .member("max_age", Scalar::new().default("5 min"))
+}
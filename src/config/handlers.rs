@@ -2,10 +2,14 @@ use std::sync::Arc;
 
 use quire::validate::{Enum, Nothing};
 
+use super::by_extension;
 use super::chat;
+use super::discovery;
 use super::empty_gif;
+use super::health;
 use super::proxy;
 use super::redirect;
+use super::robots_txt;
 use super::self_status;
 use super::static_files;
 
@@ -18,6 +22,7 @@ pub enum Handler {
     VersionedStatic(Arc<static_files::VersionedStatic>),
     Proxy(Arc<proxy::Proxy>),
     EmptyGif(Arc<empty_gif::EmptyGif>),
+    RobotsTxt(Arc<robots_txt::RobotsTxt>),
     NotFound,
     HttpBin,
     /// This endpoints is for testing websocket implementation. It's not
@@ -25,13 +30,24 @@ pub enum Handler {
     /// autobahn tests, but we might choose to change test suite, so don't use
     /// it for something serious.
     WebsocketEcho,
+    /// Replies with parsed query parameters as JSON. Used for tests only,
+    /// not guaranteed to be stable.
+    QueryEcho,
+    /// Panics on every request. Used for tests only, so it's compiled
+    /// only with `test-handlers` feature.
+    #[cfg(feature="test-handlers")]
+    Panic,
     BaseRedirect(Arc<redirect::BaseRedirect>),
     StripWWWRedirect,
+    CanonicalRedirect(Arc<redirect::CanonicalRedirect>),
     SelfStatus(Arc<self_status::SelfStatus>),
+    ByExtension(Arc<by_extension::ByExtension>),
+    Discovery(Arc<discovery::Discovery>),
+    Health(Arc<health::Health>),
 }
 
 pub fn validator<'x>() -> Enum<'x> {
-    Enum::new()
+    let handlers = Enum::new()
     .option("SwindonLattice", chat::validator())
     .option("Static", static_files::validator())
     .option("SingleFile", static_files::single_file())
@@ -39,8 +55,37 @@ pub fn validator<'x>() -> Enum<'x> {
     .option("Proxy", proxy::validator())
     .option("HttpBin", Nothing)
     .option("EmptyGif", empty_gif::validator())
+    .option("RobotsTxt", robots_txt::validator())
     .option("WebsocketEcho", Nothing)
+    .option("QueryEcho", Nothing)
     .option("BaseRedirect", redirect::base_redirect())
     .option("StripWWWRedirect", Nothing)
+    .option("CanonicalRedirect", redirect::canonical_redirect())
     .option("SelfStatus", self_status::validator())
+    .option("ByExtension", by_extension::validator())
+    .option("Discovery", discovery::validator())
+    .option("Health", health::validator());
+    test_handlers(handlers)
+}
+
+#[cfg(feature="test-handlers")]
+fn test_handlers<'x>(handlers: Enum<'x>) -> Enum<'x> {
+    handlers.option("Panic", Nothing)
+}
+
+#[cfg(not(feature="test-handlers"))]
+fn test_handlers<'x>(handlers: Enum<'x>) -> Enum<'x> {
+    handlers
+}
+
+impl Handler {
+    /// Handler is served during `warmup-period` instead of `503`
+    pub fn serves_during_warmup(&self) -> bool {
+        matches!(*self, Handler::Health(..))
+    }
+    /// Handler passes request method through as is, so methods unknown
+    /// to swindon are not rejected (upstream decides whether to serve them)
+    pub fn passes_any_method(&self) -> bool {
+        matches!(*self, Handler::Proxy(..))
+    }
 }
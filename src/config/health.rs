@@ -0,0 +1,30 @@
+use quire::validate::{Structure, Scalar, Enum, Nothing};
+
+
+/// Which signal the `!Health` handler reports
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum Probe {
+    /// Process is up and serves requests
+    liveness,
+    /// Process is ready to receive traffic
+    readiness,
+}
+
+/// Liveness or readiness endpoint for orchestration systems
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct Health {
+    pub probe: Probe,
+    /// Readiness fails when every http destination is unhealthy
+    pub check_upstreams: bool,
+}
+
+pub fn validator<'x>() -> Structure<'x> {
+    Structure::new()
+    .member("probe", Enum::new()
+        .option("liveness", Nothing)
+        .option("readiness", Nothing)
+        .allow_plain()
+        .plain_default("readiness"))
+    .member("check_upstreams", Scalar::new().default(true))
+}
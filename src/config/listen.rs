@@ -22,6 +22,15 @@ impl Listen {
     pub fn len(&self) -> usize {
         self.0.len()
     }
+    /// Returns true if any of the addresses has the specified port
+    pub fn has_port(&self, port: u16) -> bool {
+        self.0.iter().any(|x| match *x {
+            ListenSocket::Tcp(ref s) => {
+                s.rsplit(':').next().and_then(|p| p.parse().ok())
+                    == Some(port)
+            }
+        })
+    }
 }
 
 impl<'a> IntoIterator for &'a Listen {
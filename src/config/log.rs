@@ -2,6 +2,7 @@ use trimmer::{Template, Options, ParseError};
 use serde::de::{Deserialize, Deserializer, Error};
 use quire::validate::{Structure, Scalar};
 
+use crate::logging::http::check_format;
 use crate::template;
 
 lazy_static! {
@@ -24,8 +25,12 @@ impl<'a> Deserialize<'a> for Format {
             template: String,
         }
         let raw = FormatRaw::deserialize(d)?;
-        Format::from_string(raw.template)
-            .map_err(|e| D::Error::custom(&format!("{}", e)))
+        let format = Format::from_string(raw.template)
+            .map_err(|e| D::Error::custom(&format!("{}", e)))?;
+        check_format(&format.template)
+            .map_err(|e| D::Error::custom(&format!(
+                "log format refers to unknown variable: {}", e)))?;
+        Ok(format)
     }
 }
 
@@ -11,6 +11,7 @@ mod version;
 mod replication;
 mod session_pools;
 pub mod authorizers;
+pub mod deprecation;
 pub mod handlers;
 pub mod http_destinations;
 pub mod ldap;
@@ -20,16 +21,20 @@ pub mod networks;
 pub mod routing;
 pub mod visitors;
 // handlers
+pub mod by_extension;
 pub mod chat;
+pub mod discovery;
+pub mod health;
 pub mod static_files;
 pub mod proxy;
 pub mod disk;
 pub mod empty_gif;
 pub mod redirect;
+pub mod robots_txt;
 pub mod self_status;
 
 pub use self::read::Error;
-pub use self::root::{ConfigData, ConfigSource};
+pub use self::root::{ConfigData, ConfigSource, DuplicateQueryParams};
 pub use self::listen::ListenSocket;
 pub use self::handlers::Handler;
 pub use self::authorizers::Authorizer;
@@ -46,6 +51,7 @@ use quire::{parse_string, Options};
 pub struct Configurator {
     path: PathBuf,
     file_metadata: Vec<(PathBuf, String, Metadata)>,
+    mixin_sources: read::MixinSources,
     cell: ConfigCell,
 }
 
@@ -92,9 +98,11 @@ impl ConfigCell {
 impl Configurator {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Configurator, Error> {
         let path = path.as_ref();
-        let (cfg, meta) = read::read_config(path)?;
+        let (cfg, meta, sources) = read::read_config(path,
+            &read::MixinSources::new())?;
         Ok(Configurator {
             path: path.to_path_buf(),
+            mixin_sources: sources,
             cell: ConfigCell::new(Config {
                 data: cfg,
                 fingerprint: fingerprint::calc(&meta)?,
@@ -127,7 +135,9 @@ impl Configurator {
         if !changed {
             return Ok(false);
         }
-        let (new_cfg, new_meta) = read::read_config(&self.path)?;
+        let (new_cfg, new_meta, sources) = read::read_config(&self.path,
+            &self.mixin_sources)?;
+        self.mixin_sources = sources;
         if **self.config().get() != new_cfg {
             let print = fingerprint::calc(&new_meta)?;
             self.file_metadata = new_meta;
@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use super::http;
 
-use quire::validate::{Nothing, Enum, Structure, Scalar, Numeric};
+use quire::validate::{Nothing, Enum, Structure, Sequence, Scalar, Numeric};
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
@@ -9,6 +11,21 @@ pub enum Mode {
     forward,
 }
 
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ServeStale {
+    pub on_error: bool,
+    #[serde(with="::quire::duration")]
+    pub max_stale: Duration,
+    pub age_header: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct Compression {
+    /// Empty list means the built-in list of textual types
+    pub content_types: Vec<String>,
+    pub max_size: usize,
+}
+
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 pub struct Proxy {
     pub mode: Mode,
@@ -16,10 +33,19 @@ pub struct Proxy {
     // NOTE: option is deprecated.
     pub request_id_header: Option<String>,
     pub destination: http::Destination,
+    pub shadow_upstream: Option<http::Destination>,
     // TODO(tailhook) this might needs to be u64
     pub max_payload_size: usize,
     pub stream_requests: bool,
     pub response_buffer_size: usize,
+    pub max_response_size: Option<usize>,
+    pub merge_slashes: bool,
+    pub serve_stale: Option<ServeStale>,
+    pub default_content_type: Option<String>,
+    pub nosniff: bool,
+    pub negotiate_encoding: bool,
+    pub negotiate_encoding_max_size: usize,
+    pub proxy_compression: Option<Compression>,
 }
 
 pub fn validator<'x>() -> Structure<'x> {
@@ -35,5 +61,24 @@ pub fn validator<'x>() -> Structure<'x> {
     .member("stream_requests", Scalar::new().default(false))
     .member("response_buffer_size",
         Numeric::new().min(0).max(1 << 40).default(10 << 20))
+    .member("max_response_size",
+        Numeric::new().min(0).max(1 << 40).optional())
+    .member("merge_slashes", Scalar::new().default(false))
     .member("destination", http::destination_validator())
+    .member("shadow_upstream", http::destination_validator().optional())
+    .member("serve_stale", Structure::new()
+        .member("on_error", Scalar::new().default(true))
+        .member("max_stale", Scalar::new().default("1 hour"))
+        .member("age_header", Scalar::new().default(true))
+        .optional())
+    .member("default_content_type", Scalar::new().optional())
+    .member("nosniff", Scalar::new().default(false))
+    .member("negotiate_encoding", Scalar::new().default(false))
+    .member("negotiate_encoding_max_size",
+        Numeric::new().min(0).max(1 << 40).default(1 << 20))
+    .member("proxy_compression", Structure::new()
+        .member("content_types", Sequence::new(Scalar::new()))
+        .member("max_size",
+            Numeric::new().min(0).max(1 << 40).default(1 << 20))
+        .optional())
 }
@@ -3,22 +3,28 @@ use std::collections::HashMap;
 use std::fmt;
 use std::fs::{File, Metadata, metadata};
 use std::io::{self, Read};
+use std::mem;
 use std::path::{PathBuf, Path, Component};
 use std::rc::Rc;
 use std::sync::Arc;
 
-use quire::{self, Pos, Include, ErrorCollector, Options, parse_config};
+use quire::{self, Pos, Include, ErrorCollector, Options};
+use quire::{parse_string};
+use quire::validate::Validator;
 use quire::{raw_parse as parse_yaml};
 use quire::ast::{Ast, process as process_ast};
 use regex;
+use serde::de::DeserializeOwned;
 
 use crate::config::authorizers::Authorizer;
-use crate::config::root::{ConfigData, ConfigSource, Mixin};
+use crate::config::root::{ConfigData, ConfigSource, Mixin, ReloadMode};
 use crate::config::root::{config_validator, mixin_validator};
 use super::Handler;
-use crate::config::static_files::Mode;
+use crate::config::routing::{Host, HostPath};
+use crate::config::static_files::{Mode, IndexPolicy};
 use crate::config::log;
 use crate::intern::{LogFormatName, Authorizer as AuthorizerName, HandlerName};
+use crate::intern::{DeprecationName};
 use crate::routing::RoutingTable;
 
 
@@ -74,6 +80,14 @@ quick_error! {
             display("authorizer {:?} not found", name)
             description("authorizer not found")
         }
+        NoDeprecation(name: DeprecationName) {
+            display("deprecation {:?} not found", name)
+            description("deprecation not found")
+        }
+        NoLogFormat(name: LogFormatName) {
+            display("log format {:?} not found", name)
+            description("log format not found")
+        }
     }
 }
 
@@ -98,8 +112,50 @@ fn join_filename(base: &Path, relative: &Path) -> Result<PathBuf, ()> {
     Ok(path)
 }
 
-#[allow(dead_code)]
-pub fn include_file(files: &RefCell<&mut Vec<(PathBuf, String, Metadata)>>,
+/// Text of the config file and of all the files it includes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    text: String,
+    includes: HashMap<PathBuf, String>,
+}
+
+/// Sources of the mixin files by path
+pub type MixinSources = HashMap<PathBuf, Source>;
+
+/// Included files
+///
+/// Files are read from disk if `files` is set (metadata is pushed there),
+/// otherwise only `texts` recorded on previous read are used.
+struct Includes<'a> {
+    files: Option<&'a mut Vec<(PathBuf, String, Metadata)>>,
+    texts: HashMap<PathBuf, String>,
+}
+
+fn read_include(includes: &RefCell<Includes>, path: &Path, name: &str)
+    -> io::Result<String>
+{
+    let mut includes = includes.borrow_mut();
+    let includes = &mut *includes;
+    match includes.files {
+        Some(ref mut files) => {
+            let mut body = String::new();
+            let mut f = File::open(path)?;
+            let meta = f.metadata()?;
+            f.read_to_string(&mut body)?;
+            files.push((path.to_path_buf(), String::from(name), meta));
+            includes.texts.insert(path.to_path_buf(), body.clone());
+            Ok(body)
+        }
+        None => {
+            includes.texts.get(path).cloned().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound,
+                    "file was not included in previous version")
+            })
+        }
+    }
+}
+
+fn include_file(includes: &RefCell<Includes>,
     pos: &Pos, include: &Include,
     err: &ErrorCollector, options: &Options)
     -> Ast
@@ -121,22 +177,13 @@ pub fn include_file(files: &RefCell<&mut Vec<(PathBuf, String, Metadata)>>,
 
             debug!("{} Including {:?}", pos, path);
 
-            let mut body = String::new();
-            File::open(&path)
-            .and_then(|mut f| {
-                let m = f.metadata();
-                f.read_to_string(&mut body)?;
-                m
-            })
+            // nested includes are read while parsing, so the cell must
+            // not be borrowed at that time
+            read_include(includes, &path, filename)
             .map_err(|e| {
                 err.add_error(quire::Error::open_error(&path, e))
             }).ok()
-            .and_then(|metadata| {
-                files.borrow_mut().push((
-                    path.to_path_buf(),
-                    String::from(filename),
-                    metadata,
-                ));
+            .and_then(|body| {
                 parse_yaml(Rc::new(path.display().to_string()), &body,
                     |doc| { process_ast(&options, doc, err) },
                 ).map_err(|e| err.add_error(e)).ok()
@@ -146,6 +193,53 @@ pub fn include_file(files: &RefCell<&mut Vec<(PathBuf, String, Metadata)>>,
     }
 }
 
+fn parse_text<T>(filename: &Path, text: &str, validator: &dyn Validator,
+    includes: &RefCell<Includes>)
+    -> Result<T, Error>
+    where T: DeserializeOwned,
+{
+    let mut opt = Options::default();
+    opt.allow_include(
+        |a, b, c, d| include_file(includes, a, b, c, d));
+    Ok(parse_string(&filename.display().to_string(), text,
+                    validator, &opt)?)
+}
+
+/// Reads and parses the file from disk, recording included files
+fn read_source<T>(filename: &Path, name: String, validator: &dyn Validator,
+    files: &mut Vec<(PathBuf, String, Metadata)>)
+    -> Result<(Source, T), Error>
+    where T: DeserializeOwned,
+{
+    files.push((filename.to_path_buf(), name, metadata(filename)?));
+    let mut text = String::new();
+    File::open(filename)?.read_to_string(&mut text)?;
+    let includes = RefCell::new(Includes {
+        files: Some(files),
+        texts: HashMap::new(),
+    });
+    let value = parse_text(filename, &text, validator, &includes)?;
+    let source = Source {
+        text: text,
+        includes: includes.into_inner().texts,
+    };
+    Ok((source, value))
+}
+
+/// Parses the file previously read by `read_source`, without reading
+/// anything from disk
+fn parse_source<T>(filename: &Path, source: &Source,
+    validator: &dyn Validator)
+    -> Result<T, Error>
+    where T: DeserializeOwned,
+{
+    let includes = RefCell::new(Includes {
+        files: None,
+        texts: source.includes.clone(),
+    });
+    parse_text(filename, &source.text, validator, &includes)
+}
+
 fn prefix_error<N: fmt::Display>(prefix: &str, filename: &Path,
     typ: &'static str, name: &N)
     -> Error
@@ -160,78 +254,193 @@ fn conflict<N: fmt::Display>(filename: &Path, typ: &'static str, name: N)
     Error::MixinConflict(filename.to_path_buf(), typ, name.to_string())
 }
 
-fn mix_in<K, V>(
+/// Checks items of the mixin section without adding them to the config
+fn check_mix_in<K, V>(
     filename: &Path, prefix: &str,
-    dest: &mut HashMap<K, V>, src: HashMap<K, V>,
+    dest: &HashMap<K, V>, src: &HashMap<K, V>,
     typ: &'static str)
     -> Result<(), Error>
     where K: ::std::hash::Hash + ::std::ops::Deref<Target=str>,
           K: ::std::fmt::Display + Eq,
 {
-    for (h, handler) in src {
+    for h in src.keys() {
         if !(&*h).starts_with(prefix) {
-            return Err(prefix_error(prefix, filename, typ, &h));
+            return Err(prefix_error(prefix, filename, typ, h));
         }
-        if dest.contains_key(&h) {
-            return Err(conflict(filename, typ, &h));
+        if dest.contains_key(h) {
+            return Err(conflict(filename, typ, h));
         }
-        dest.insert(h, handler);
     }
     Ok(())
 }
 
-pub fn read_config<P: AsRef<Path>>(filename: P)
-    -> Result<(ConfigData, Vec<(PathBuf, String, Metadata)>), Error>
+fn mix_in<K, V>(dest: &mut HashMap<K, V>, src: HashMap<K, V>)
+    where K: ::std::hash::Hash + Eq,
+{
+    dest.extend(src);
+}
+
+/// Adds items of the mixin to the config, config is not modified on error
+fn merge_mixin(src: &mut ConfigSource, filename: &Path, prefix: &str,
+    mixin: Mixin)
+    -> Result<(), Error>
+{
+    check_mix_in(filename, prefix,
+        &src.handlers, &mixin.handlers, "handler")?;
+    check_mix_in(filename, prefix,
+        &src.authorizers, &mixin.authorizers, "authorizer")?;
+    check_mix_in(filename, prefix,
+        &src.deprecations, &mixin.deprecations, "deprecation")?;
+    check_mix_in(filename, prefix,
+        &src.session_pools, &mixin.session_pools, "session-pool")?;
+    check_mix_in(filename, prefix, &src.http_destinations,
+        &mixin.http_destinations, "http-destination")?;
+    check_mix_in(filename, prefix, &src.ldap_destinations,
+        &mixin.ldap_destinations, "ldap-destination")?;
+    check_mix_in(filename, prefix,
+        &src.networks, &mixin.networks, "network")?;
+    check_mix_in(filename, prefix,
+        &src.log_formats, &mixin.log_formats, "log-format")?;
+    check_mix_in(filename, prefix,
+        &src.disk_pools, &mixin.disk_pools, "disk-pools")?;
+    mix_in(&mut src.handlers, mixin.handlers);
+    mix_in(&mut src.authorizers, mixin.authorizers);
+    mix_in(&mut src.deprecations, mixin.deprecations);
+    mix_in(&mut src.session_pools, mixin.session_pools);
+    mix_in(&mut src.http_destinations, mixin.http_destinations);
+    mix_in(&mut src.ldap_destinations, mixin.ldap_destinations);
+    mix_in(&mut src.networks, mixin.networks);
+    mix_in(&mut src.log_formats, mixin.log_formats);
+    mix_in(&mut src.disk_pools, mixin.disk_pools);
+    Ok(())
+}
+
+/// Mixin file used in the config
+struct MixinFile {
+    prefix: String,
+    path: PathBuf,
+    source: Source,
+    /// Version used in the current config if it differs from `source`
+    previous: Option<Source>,
+}
+
+fn merge_mixins(mut src: ConfigSource, mixins: &[MixinFile],
+    parsed: Vec<Mixin>)
+    -> Result<ConfigSource, Error>
+{
+    for (m, mixin) in mixins.iter().zip(parsed) {
+        merge_mixin(&mut src, &m.path, &m.prefix, mixin)?;
+    }
+    Ok(src)
+}
+
+/// Builds config from the sources that have been read before
+fn build_config(filename: &Path, main: &Source, mixins: &[MixinFile])
+    -> Result<ConfigData, Error>
+{
+    let src = parse_source(filename, main, &config_validator())?;
+    let parsed = mixins.iter()
+        .map(|m| parse_source(&m.path, &m.source, &mixin_validator()))
+        .collect::<Result<Vec<_>, _>>()?;
+    postprocess_config(merge_mixins(src, mixins, parsed)?)
+}
+
+/// Uses previous versions of the changed mixins which make config invalid
+///
+/// Every changed mixin is checked on top of the previous versions of the
+/// ones not checked yet, so this needs at most `N + 1` builds.
+fn fall_back(filename: &Path, main: &Source, mixins: &mut [MixinFile],
+    error: Error)
+    -> Result<ConfigData, Error>
+{
+    let changed = (0..mixins.len())
+        .filter(|&i| mixins[i].previous.is_some())
+        .collect::<Vec<_>>();
+    let swap = |m: &mut MixinFile| {
+        mem::swap(&mut m.source,
+                  m.previous.as_mut().expect("mixin is changed"));
+    };
+    for &i in &changed {
+        swap(&mut mixins[i]);
+    }
+    let mut cfg = match build_config(filename, main, mixins) {
+        Ok(cfg) => cfg,
+        // main file is not compatible with previous mixins
+        Err(_) => return Err(error),
+    };
+    for &i in &changed {
+        swap(&mut mixins[i]);
+        match build_config(filename, main, mixins) {
+            Ok(new_cfg) => cfg = new_cfg,
+            Err(e) => {
+                error!("Error in mixin {:?}: {}. \
+                    Keeping its previous version.", mixins[i].path, e);
+                swap(&mut mixins[i]);
+            }
+        }
+    }
+    Ok(cfg)
+}
+
+/// Reads config with all the mixins
+///
+/// `previous` is the sources of mixins of the current config, with
+/// `reload-mode: best-effort` previous version of a mixin is used when the
+/// new one can't be read or makes config invalid. Returns the sources of
+/// mixins that are actually used.
+pub fn read_config<P: AsRef<Path>>(filename: P, previous: &MixinSources)
+    -> Result<(ConfigData, Vec<(PathBuf, String, Metadata)>, MixinSources),
+              Error>
 {
     let filename = filename.as_ref();
     let mut files = Vec::new();
-    files.push((
-        filename.to_path_buf(),
-        String::from("<main>"),
-        metadata(filename)?,
-    ));
-    let mut src: ConfigSource = {
-        let cell = RefCell::new(&mut files);
-        let mut opt = Options::default();
-        opt.allow_include(
-            |a, b, c, d| include_file(&cell, a, b, c, d));
-        parse_config(filename, &config_validator(), &opt)?
-    };
+    let (main, src): (_, ConfigSource) = read_source(filename,
+        String::from("<main>"), &config_validator(), &mut files)?;
+    let best_effort = src.reload_mode == ReloadMode::best_effort;
 
-    for (prefix, ref incl) in &src.mixins {
-        let incl_path = join_filename(filename, &Path::new(incl))
+    let mut mixins = Vec::new();
+    let mut parsed = Vec::new();
+    for (prefix, incl) in src.mixins.clone() {
+        let incl_path = join_filename(filename, &incl)
             .map_err(|()| Error::BadMixinPath(filename.to_path_buf()))?;
-        files.push((
-            incl_path.clone(),
-            incl.display().to_string(),
-            metadata(&incl_path)?,
-        ));
-
-        let mixin: Mixin = {
-            let cell = RefCell::new(&mut files);
-            let mut opt = Options::default();
-            opt.allow_include(
-                |a, b, c, d| include_file(&cell, a, b, c, d));
-            parse_config(&incl_path, &mixin_validator(), &opt)?
+        let old = previous.get(&incl_path);
+        let result = read_source(&incl_path, incl.display().to_string(),
+            &mixin_validator(), &mut files);
+        let (source, mixin, changed) = match (result, old) {
+            (Ok((source, mixin)), _) => {
+                let changed = old.filter(|old| **old != source).cloned();
+                (source, mixin, changed)
+            }
+            (Err(ref e), Some(old)) if best_effort => {
+                error!("Error reading mixin {:?}: {}. \
+                    Keeping its previous version.", incl_path, e);
+                let mixin = parse_source(&incl_path, old,
+                    &mixin_validator())?;
+                (old.clone(), mixin, None)
+            }
+            (Err(e), _) => return Err(e),
         };
-        mix_in(&incl_path, prefix,
-               &mut src.handlers, mixin.handlers, "handler")?;
-        mix_in(&incl_path, prefix,
-               &mut src.authorizers, mixin.authorizers, "authorizer")?;
-        mix_in(&incl_path, prefix,
-               &mut src.session_pools, mixin.session_pools, "session-pool")?;
-        mix_in(&incl_path, prefix, &mut src.http_destinations,
-            mixin.http_destinations, "http-destination")?;
-        mix_in(&incl_path, prefix, &mut src.ldap_destinations,
-            mixin.ldap_destinations, "ldap-destination")?;
-        mix_in(&incl_path, prefix,
-            &mut src.networks, mixin.networks, "network")?;
-        mix_in(&incl_path, prefix,
-            &mut src.log_formats, mixin.log_formats, "log-format")?;
-        mix_in(&incl_path, prefix,
-            &mut src.disk_pools, mixin.disk_pools, "disk-pools")?;
+        parsed.push(mixin);
+        mixins.push(MixinFile {
+            prefix: prefix,
+            path: incl_path,
+            source: source,
+            previous: changed,
+        });
     }
-    return Ok((postprocess_config(src)?, files));
+    let cfg = match merge_mixins(src, &mixins, parsed)
+        .and_then(postprocess_config)
+    {
+        Ok(cfg) => cfg,
+        Err(e) if best_effort => {
+            fall_back(filename, &main, &mut mixins, e)?
+        }
+        Err(e) => return Err(e),
+    };
+    let sources = mixins.into_iter()
+        .map(|m| (m.path, m.source))
+        .collect();
+    return Ok((cfg, files, sources));
 }
 
 pub fn postprocess_config(mut src: ConfigSource)
@@ -259,11 +468,45 @@ pub fn postprocess_config(mut src: ConfigSource)
                                Authorizer::AllowAll);
     }
 
+    // Check limits before building routing table, as building it for
+    // a misgenerated config might take a lot of time
+    if let Some(max) = src.max_routes {
+        if src.routing.len() > max {
+            err!("{} routes defined, but `max-routes` is {}",
+                src.routing.len(), max);
+        }
+    }
+    if let Some(max) = src.max_handlers {
+        if src.handlers.len() > max {
+            err!("{} handlers defined, but `max-handlers` is {}",
+                src.handlers.len(), max);
+        }
+    }
+    for (HostPath(Host(star, host), path), route) in &src.routing {
+        if let Some(port) = route.listen_port {
+            if !src.listen.has_port(port) {
+                err!("Route {}{}{} is bound to port {}, \
+                    but there is no such port in `listen`",
+                    if *star { "*." } else { "" }, host,
+                    path.as_ref().map(|x| &x[..]).unwrap_or(""), port);
+            }
+        }
+    }
+    if src.routing.len() > src.warn_routes_above {
+        warn!("{} routes defined, which is more than {} \
+            (`warn-routes-above`), probably config is misgenerated",
+            src.routing.len(), src.warn_routes_above);
+    }
+
     let mut cfg = ConfigData {
         routing: RoutingTable::new(&src)?,
 
         listen: src.listen,
         max_connections: src.max_connections,
+        max_connections_per_ip: src.max_connections_per_ip,
+        max_upstream_connections: src.max_upstream_requests,
+        max_header_value_size: src.max_header_value_size,
+        extension_methods: src.extension_methods,
         pipeline_depth: src.pipeline_depth,
         listen_error_timeout: src.listen_error_timeout,
         first_byte_timeout: src.first_byte_timeout,
@@ -273,9 +516,16 @@ pub fn postprocess_config(mut src: ConfigSource)
         input_body_whole_timeout: src.input_body_whole_timeout,
         output_body_byte_timeout: src.output_body_byte_timeout,
         output_body_whole_timeout: src.output_body_whole_timeout,
+        warmup_period: src.warmup_period,
+        shutdown_drain_period: src.shutdown_drain_period,
+        max_connection_age: src.max_connection_age,
 
+        default_host: src.default_host,
+        ingress_remove_headers: src.ingress_remove_headers,
+        duplicate_query_params: src.duplicate_query_params,
         handlers: src.handlers,
         authorizers: src.authorizers,
+        deprecations: src.deprecations,
         session_pools: src.session_pools,
         http_destinations: src.http_destinations,
         ldap_destinations: src.ldap_destinations,
@@ -286,6 +536,9 @@ pub fn postprocess_config(mut src: ConfigSource)
         replication: src.replication,
         debug_routing: src.debug_routing,
         debug_logging: src.debug_logging,
+        access_log_sample_rate: src.access_log_sample_rate,
+        access_log_slow_request: src.access_log_slow_request,
+        debug_tracing: src.debug_tracing,
         server_name: src.server_name,
 
         set_user: src.set_user,
@@ -373,6 +626,12 @@ pub fn postprocess_config(mut src: ConfigSource)
                 if !cfg.http_destinations.contains_key(u) {
                     err!("{:?}: unknown http destination {:?}", name, u)
                 }
+                if let Some(ref shadow) = proxy.shadow_upstream {
+                    let u = &shadow.upstream;
+                    if !cfg.http_destinations.contains_key(u) {
+                        err!("{:?}: unknown http destination {:?}", name, u)
+                    }
+                }
                 if proxy.request_id_header.is_some() {
                     warn!(concat!(
                         "{:?}: request_id_header is deprecated",
@@ -387,6 +646,71 @@ pub fn postprocess_config(mut src: ConfigSource)
                     err!("{:?}: `strip-host-suffix` only \
                         works when `mode: with-hostname`", name);
                 }
+                if config.host_in_path && config.mode == Mode::with_hostname {
+                    err!("{:?}: `$host` in `path` can't be used \
+                        with `mode: with-hostname`", name);
+                }
+                let needs_index = [config.root_index, config.dir_index].iter()
+                    .any(|p| match *p {
+                        Some(IndexPolicy::index) => true,
+                        Some(IndexPolicy::redirect) => true,
+                        _ => false,
+                    });
+                if needs_index && config.index_files.is_empty() {
+                    err!("{:?}: `index` and `redirect` directory policies \
+                        require non-empty `index-files`", name);
+                }
+                for netw in config.restrict_files.values() {
+                    if !cfg.networks.contains_key(netw) {
+                        err!("{:?}: unknown network {:?} \
+                            in `restrict-files`", name, netw);
+                    }
+                }
+            }
+            &Handler::VersionedStatic(ref config) => {
+                for netw in config.restrict_files.values() {
+                    if !cfg.networks.contains_key(netw) {
+                        err!("{:?}: unknown network {:?} \
+                            in `restrict-files`", name, netw);
+                    }
+                }
+            }
+            &Handler::ByExtension(ref config) => {
+                let targets = config.extensions.values()
+                    .chain(Some(&config.default));
+                for h in targets {
+                    match cfg.handlers.get(h) {
+                        None => {
+                            err!("{:?}: unknown handler {:?}", name, h);
+                        }
+                        Some(&Handler::ByExtension(..)) => {
+                            err!("{:?}: handler {:?} can't be \
+                                `!ByExtension` itself", name, h);
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            &Handler::Discovery(ref config) => {
+                match cfg.handlers.get(&config.chat) {
+                    Some(&Handler::SwindonLattice(..)) => {}
+                    Some(_) => {
+                        err!("{:?}: handler {:?} is not `!SwindonLattice`",
+                            name, config.chat);
+                    }
+                    None => {
+                        err!("{:?}: unknown handler {:?}", name, config.chat);
+                    }
+                }
+            }
+            &Handler::CanonicalRedirect(ref config) => {
+                match config.status {
+                    301 | 302 | 307 | 308 => {}
+                    status => {
+                        err!("{:?}: redirect status must be one of \
+                            301, 302, 307, 308, got {}", name, status);
+                    }
+                }
             }
             _ => {}
         }
@@ -1,4 +1,4 @@
-use quire::validate::{Structure, Scalar};
+use quire::validate::{Structure, Scalar, Enum, Nothing, Numeric};
 
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -6,8 +6,37 @@ pub struct BaseRedirect {
     pub redirect_to_domain: String,
 }
 
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub enum Scheme {
+    http,
+    https,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct CanonicalRedirect {
+    pub host: String,
+    pub scheme: Scheme,
+    pub status: u16,
+}
+
 
 pub fn base_redirect<'x>() -> Structure<'x> {
     Structure::new()
     .member("redirect_to_domain", Scalar::new())
 }
+
+fn scheme<'x>() -> Enum<'x> {
+    Enum::new()
+        .option("http", Nothing)
+        .option("https", Nothing)
+        .allow_plain()
+        .plain_default("https")
+}
+
+pub fn canonical_redirect<'x>() -> Structure<'x> {
+    Structure::new()
+    .member("host", Scalar::new())
+    .member("scheme", scheme())
+    .member("status", Numeric::new().min(300).max(399).default(301))
+}
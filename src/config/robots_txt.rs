@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use blake2::Blake2b;
+use digest::{Input, FixedOutput};
+use quire::validate::{Structure, Scalar};
+use serde::de::{Deserializer, Deserialize};
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RobotsTxt {
+    pub content: String,
+    pub max_age: Duration,
+    // Computed values
+    /// Strong `ETag` (with quotes) derived from the `content`
+    pub etag: String,
+}
+
+pub fn validator<'x>() -> Structure<'x> {
+    Structure::new()
+    .member("content", Scalar::new())
+    .member("max_age", Scalar::new().default("1 day"))
+}
+
+impl<'a> Deserialize<'a> for RobotsTxt {
+    fn deserialize<D: Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        pub struct Internal {
+            pub content: String,
+            #[serde(with="::quire::duration")]
+            pub max_age: Duration,
+        }
+        let int = Internal::deserialize(d)?;
+        let mut digest = Blake2b::default();
+        digest.process(int.content.as_bytes());
+        let mut etag = String::with_capacity(34);
+        etag.push('"');
+        for b in &digest.fixed_result()[..16] {
+            etag.push_str(&format!("{:02x}", b));
+        }
+        etag.push('"');
+        return Ok(RobotsTxt {
+            content: int.content,
+            max_age: int.max_age,
+            etag: etag,
+        })
+    }
+}
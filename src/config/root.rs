@@ -5,14 +5,16 @@ use std::time::Duration;
 use std::path::PathBuf;
 
 use quire::validate::{Structure, Sequence, Mapping, Scalar, Numeric};
+use quire::validate::{Enum, Nothing};
 
 use crate::intern::{HandlerName, Upstream, SessionPoolName, DiskPoolName};
 use crate::intern::{LdapUpstream, Network, Authorizer as AuthorizerName};
-use crate::intern::{LogFormatName};
+use crate::intern::{LogFormatName, DeprecationName};
 use crate::config::listen::{self, Listen};
 use crate::config::routing::{self, HostPath, RouteDef};
 use crate::config::handlers::{self, Handler};
 use crate::config::authorizers::{self, Authorizer};
+use crate::config::deprecation::{self, Deprecation};
 use crate::config::session_pools::{self, SessionPool};
 use crate::config::http_destinations::{self, Destination};
 use crate::config::ldap;
@@ -23,10 +25,33 @@ use crate::config::replication::{self, Replication};
 use crate::routing::RoutingTable;
 
 
+/// Which values of a repeated query parameter are seen by handlers and
+/// forwarded to upstreams
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum DuplicateQueryParams {
+    all,
+    first,
+    last,
+}
+
+/// What to do if some of the mixins can't be read on reload
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub enum ReloadMode {
+    /// Keep previous config entirely
+    #[serde(rename="all-or-nothing")]
+    all_or_nothing,
+    /// Use previous version of the failed mixins, apply everything else
+    #[serde(rename="best-effort")]
+    best_effort,
+}
+
 #[derive(Deserialize, PartialEq, Eq, Debug)]
 pub struct Mixin {
     pub handlers: HashMap<HandlerName, Handler>,
     pub authorizers: HashMap<AuthorizerName, Authorizer>,
+    pub deprecations: HashMap<DeprecationName, Arc<Deprecation>>,
     pub session_pools: HashMap<SessionPoolName, Arc<SessionPool>>,
     pub http_destinations: HashMap<Upstream, Arc<Destination>>,
     pub ldap_destinations: HashMap<LdapUpstream, ldap::Destination>,
@@ -42,6 +67,10 @@ pub struct Mixin {
 pub struct ConfigSource {
     pub listen: Listen,
     pub max_connections: usize,
+    pub max_connections_per_ip: Option<usize>,
+    pub max_upstream_connections: Option<usize>,
+    pub max_header_value_size: Option<usize>,
+    pub extension_methods: Vec<String>,
     pub pipeline_depth: usize,
     #[serde(with="::quire::duration")]
     pub listen_error_timeout: Duration,
@@ -59,11 +88,25 @@ pub struct ConfigSource {
     pub output_body_byte_timeout: Duration,
     #[serde(with="::quire::duration")]
     pub output_body_whole_timeout: Duration,
+    #[serde(with="::quire::duration")]
+    pub warmup_period: Duration,
+    #[serde(with="::quire::duration")]
+    pub shutdown_drain_period: Duration,
+    #[serde(with="::quire::duration")]
+    pub max_connection_age: Duration,
+
+    pub max_routes: Option<usize>,
+    pub max_handlers: Option<usize>,
+    pub warn_routes_above: usize,
 
+    pub default_host: Option<String>,
+    pub ingress_remove_headers: Vec<String>,
+    pub duplicate_query_params: DuplicateQueryParams,
     pub routing: HashMap<HostPath, RouteDef>,
 
     pub handlers: HashMap<HandlerName, Handler>,
     pub authorizers: HashMap<AuthorizerName, Authorizer>,
+    pub deprecations: HashMap<DeprecationName, Arc<Deprecation>>,
     pub session_pools: HashMap<SessionPoolName, Arc<SessionPool>>,
     pub http_destinations: HashMap<Upstream, Arc<Destination>>,
     pub ldap_destinations: HashMap<LdapUpstream, ldap::Destination>,
@@ -77,6 +120,10 @@ pub struct ConfigSource {
     pub replication: Arc<Replication>,
     pub debug_routing: bool,
     pub debug_logging: bool,
+    pub access_log_sample_rate: u32,
+    #[serde(with="::quire::duration")]
+    pub access_log_slow_request: Duration,
+    pub debug_tracing: bool,
     pub server_name: Option<String>,
 
     pub set_user: Option<String>,
@@ -85,12 +132,17 @@ pub struct ConfigSource {
     /// We need to keep order of mixins stable for the purpose
     /// of fingerprinting
     pub mixins: BTreeMap<String, PathBuf>,
+    pub reload_mode: ReloadMode,
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct ConfigData {
     pub listen: Listen,
     pub max_connections: usize,
+    pub max_connections_per_ip: Option<usize>,
+    pub max_upstream_connections: Option<usize>,
+    pub max_header_value_size: Option<usize>,
+    pub extension_methods: Vec<String>,
     pub pipeline_depth: usize,
     pub listen_error_timeout: Duration,
     pub first_byte_timeout: Duration,
@@ -100,11 +152,18 @@ pub struct ConfigData {
     pub input_body_whole_timeout: Duration,
     pub output_body_byte_timeout: Duration,
     pub output_body_whole_timeout: Duration,
+    pub warmup_period: Duration,
+    pub shutdown_drain_period: Duration,
+    pub max_connection_age: Duration,
 
+    pub default_host: Option<String>,
+    pub ingress_remove_headers: Vec<String>,
+    pub duplicate_query_params: DuplicateQueryParams,
     pub routing: RoutingTable,
 
     pub handlers: HashMap<HandlerName, Handler>,
     pub authorizers: HashMap<AuthorizerName, Authorizer>,
+    pub deprecations: HashMap<DeprecationName, Arc<Deprecation>>,
     pub session_pools: HashMap<SessionPoolName, Arc<SessionPool>>,
     pub http_destinations: HashMap<Upstream, Arc<Destination>>,
     pub ldap_destinations: HashMap<LdapUpstream, ldap::Destination>,
@@ -115,6 +174,9 @@ pub struct ConfigData {
     pub replication: Arc<Replication>,
     pub debug_routing: bool,
     pub debug_logging: bool,
+    pub access_log_sample_rate: u32,
+    pub access_log_slow_request: Duration,
+    pub debug_tracing: bool,
     pub server_name: Option<String>,
 
     pub set_user: Option<String>,
@@ -131,6 +193,8 @@ impl<'a> MixinSections for Structure<'a> {
         .member("handlers", Mapping::new(Scalar::new(), handlers::validator()))
         .member("authorizers",
             Mapping::new(Scalar::new(), authorizers::validator()))
+        .member("deprecations",
+            Mapping::new(Scalar::new(), deprecation::validator()))
         .member("session_pools",
             Mapping::new(Scalar::new(), session_pools::validator()))
         .member("http_destinations",
@@ -155,6 +219,13 @@ pub fn config_validator<'a>() -> Structure<'a> {
     .member("listen", Sequence::new(listen::validator()))
     .member("max_connections",
         Numeric::new().min(1).max(1 << 31).default(1000))
+    .member("max_connections_per_ip",
+        Numeric::new().min(1).max(1 << 31).optional())
+    .member("extension_methods", Sequence::new(Scalar::new()))
+    .member("max_upstream_connections",
+        Numeric::new().min(1).max(1 << 31).optional())
+    .member("max_header_value_size",
+        Numeric::new().min(1).max(1 << 31).optional())
     .member("pipeline_depth",
         Numeric::new().min(1).max(10000).default(2))
     .member("listen_error_timeout", Scalar::new().default("100ms"))
@@ -165,17 +236,41 @@ pub fn config_validator<'a>() -> Structure<'a> {
     .member("input_body_whole_timeout", Scalar::new().default("1 hour"))
     .member("output_body_byte_timeout", Scalar::new().default("15s"))
     .member("output_body_whole_timeout", Scalar::new().default("1 hour"))
+    .member("warmup_period", Scalar::new().default("0s"))
+    .member("shutdown_drain_period", Scalar::new().default("0s"))
+    .member("max_connection_age", Scalar::new().default("0s"))
+
+    .member("max_routes", Numeric::new().min(1).optional())
+    .member("max_handlers", Numeric::new().min(1).optional())
+    .member("warn_routes_above", Numeric::new().min(1).default(10000))
 
+    .member("default_host", Scalar::new().optional())
+    .member("ingress_remove_headers", Sequence::new(Scalar::new()))
+    .member("duplicate_query_params", Enum::new()
+        .option("all", Nothing)
+        .option("first", Nothing)
+        .option("last", Nothing)
+        .allow_plain()
+        .plain_default("all"))
     .member("routing", routing::validator())
 
     .member("replication", replication::validator())
     .member("debug_routing", Scalar::new().default(false))
     .member("debug_logging", Scalar::new().default(false))
+    .member("access_log_sample_rate",
+        Numeric::new().min(1).max(1 << 20).default(1))
+    .member("access_log_slow_request", Scalar::new().default("1s"))
+    .member("debug_tracing", Scalar::new().default(false))
     .member("server_name", Scalar::new().optional()
         .default(concat!("swindon/", env!("CARGO_PKG_VERSION"))))
     .member("set_user", Scalar::new().optional())
     .member("set_group", Scalar::new().optional())
 
     .member("mixins", Mapping::new(Scalar::new(), Scalar::new()))
+    .member("reload_mode", Enum::new()
+        .option("all-or-nothing", Nothing)
+        .option("best-effort", Nothing)
+        .allow_plain()
+        .plain_default("all-or-nothing"))
     .add_sections()
 }
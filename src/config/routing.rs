@@ -5,7 +5,8 @@ use serde::de::{Deserializer, Deserialize};
 use quire::validate::{Mapping, Scalar};
 
 use crate::config::visitors::FromStrVisitor;
-use crate::intern::{HandlerName, Authorizer};
+use crate::intern::{HandlerName, Authorizer, DeprecationName};
+use crate::intern::LogFormatName;
 
 lazy_static! {
     static ref ROUTING_RE: Regex = Regex::new(
@@ -18,6 +19,15 @@ lazy_static! {
 pub struct RouteDef {
     pub handler: HandlerName,
     pub authorizer: Option<Authorizer>,
+    /// Route is only reachable through the listener with this port
+    pub listen_port: Option<u16>,
+    /// Adds `Deprecation` and `Sunset` headers to every response
+    pub deprecation: Option<DeprecationName>,
+    /// Log format used instead of `debug-log` for requests of this route
+    pub log_format: Option<LogFormatName>,
+    /// Connection is closed after every response of this route
+    /// (`keep-alive=false`)
+    pub keep_alive: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -85,6 +95,10 @@ impl FromStr for RouteDef {
         let mut val = val.trim();
         let mut handler = None;
         let mut authorizer = None;
+        let mut listen_port = None;
+        let mut deprecation = None;
+        let mut log_format = None;
+        let mut keep_alive = true;
         while val.len() > 0 {
             if let Some(m) = ROUTING_RE.captures(val) {
                 if let Some(dest) = m.get(5) {
@@ -101,10 +115,41 @@ impl FromStr for RouteDef {
                     } else {
                         authorizer = Some(auth.as_str().parse().unwrap());
                     }
-                } else if let Some(_) = m.get(2) {
-                    panic!("Logs are not implemented yet");
+                } else if let Some(log) = m.get(2) {
+                    if let Some(old) = log_format {
+                        return Err(format!("Two log formats {:?} and {:?}",
+                            old, log.as_str()));
+                    } else {
+                        log_format = Some(log.as_str().parse().unwrap());
+                    }
                 } else if let Some(name) = m.get(3) {
-                    panic!("Key {:?} is not implemented yet", name);
+                    let value = m.get(4).unwrap().as_str();
+                    match name.as_str() {
+                        "listen-port" => {
+                            listen_port = Some(value.parse()
+                                .map_err(|_| format!("Invalid port {:?}",
+                                                     value))?);
+                        }
+                        "deprecation" => {
+                            if value.len() == 0 {
+                                return Err(String::from(
+                                    "Deprecation name is required"));
+                            }
+                            deprecation = Some(value.parse().unwrap());
+                        }
+                        "keep-alive" => {
+                            keep_alive = match value {
+                                "true" => true,
+                                "false" => false,
+                                _ => return Err(format!(
+                                    "Invalid keep-alive {:?}, \
+                                     must be true or false", value)),
+                            };
+                        }
+                        name => {
+                            panic!("Key {:?} is not implemented yet", name);
+                        }
+                    }
                 }
                 val = &val[m.get(0).unwrap().end()..];
             } else {
@@ -115,6 +160,10 @@ impl FromStr for RouteDef {
             return Ok(RouteDef {
                 handler: dest,
                 authorizer: authorizer,
+                listen_port: listen_port,
+                deprecation: deprecation,
+                log_format: log_format,
+                keep_alive: keep_alive,
             })
         } else {
             return Err(String::from("handler is required"));
@@ -133,6 +182,10 @@ mod test {
         assert_eq!(RouteDef::from_str("handler").unwrap(), RouteDef {
             handler: Symbol::from("handler"),
             authorizer: None,
+            listen_port: None,
+            deprecation: None,
+            log_format: None,
+            keep_alive: true,
         });
     }
 
@@ -141,17 +194,99 @@ mod test {
         assert_eq!(RouteDef::from_str("handler@auth").unwrap(), RouteDef {
             handler: Symbol::from("handler"),
             authorizer: Some(Symbol::from("auth")),
+            listen_port: None,
+            deprecation: None,
+            log_format: None,
+            keep_alive: true,
         });
         assert_eq!(RouteDef::from_str("handler   @auth").unwrap(),
             RouteDef {
                 handler: Symbol::from("handler"),
                 authorizer: Some(Symbol::from("auth")),
+                listen_port: None,
+                deprecation: None,
+                log_format: None,
+                keep_alive: true,
             });
         assert_eq!(RouteDef::from_str("handler @auth").unwrap(), RouteDef {
             handler: Symbol::from("handler"),
             authorizer: Some(Symbol::from("auth")),
+            listen_port: None,
+            deprecation: None,
+            log_format: None,
+            keep_alive: true,
         });
     }
+
+    #[test]
+    fn parse_listen_port() {
+        assert_eq!(RouteDef::from_str("handler listen-port=8081").unwrap(),
+            RouteDef {
+                handler: Symbol::from("handler"),
+                authorizer: None,
+                listen_port: Some(8081),
+                deprecation: None,
+                log_format: None,
+                keep_alive: true,
+            });
+        assert_eq!(RouteDef::from_str("handler @auth listen-port=80")
+            .unwrap(),
+            RouteDef {
+                handler: Symbol::from("handler"),
+                authorizer: Some(Symbol::from("auth")),
+                listen_port: Some(80),
+                deprecation: None,
+                log_format: None,
+                keep_alive: true,
+            });
+        assert!(RouteDef::from_str("handler listen-port=x").is_err());
+        assert!(RouteDef::from_str("handler listen-port=70000").is_err());
+    }
+
+    #[test]
+    fn parse_deprecation() {
+        assert_eq!(RouteDef::from_str("handler deprecation=old-api")
+            .unwrap(),
+            RouteDef {
+                handler: Symbol::from("handler"),
+                authorizer: None,
+                listen_port: None,
+                deprecation: Some(Symbol::from("old-api")),
+                log_format: None,
+                keep_alive: true,
+            });
+        assert!(RouteDef::from_str("handler deprecation=").is_err());
+    }
+
+    #[test]
+    fn parse_log_format() {
+        assert_eq!(RouteDef::from_str("handler @auth ->api-log").unwrap(),
+            RouteDef {
+                handler: Symbol::from("handler"),
+                authorizer: Some(Symbol::from("auth")),
+                listen_port: None,
+                deprecation: None,
+                log_format: Some(Symbol::from("api-log")),
+                keep_alive: true,
+            });
+        assert!(RouteDef::from_str("handler ->a ->b").is_err());
+    }
+
+    #[test]
+    fn parse_keep_alive() {
+        assert_eq!(RouteDef::from_str("handler keep-alive=false").unwrap(),
+            RouteDef {
+                handler: Symbol::from("handler"),
+                authorizer: None,
+                listen_port: None,
+                deprecation: None,
+                log_format: None,
+                keep_alive: false,
+            });
+        assert!(RouteDef::from_str("handler keep-alive=true").unwrap()
+            .keep_alive);
+        assert!(RouteDef::from_str("handler keep-alive=no").is_err());
+    }
 }
 
 #[cfg(test)]
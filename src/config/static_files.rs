@@ -5,9 +5,10 @@ use std::collections::HashMap;
 use http_file_headers::{Config as HeadersConfig};
 use quire::validate::{Nothing, Enum, Structure, Scalar, Mapping, Sequence};
 use quire::validate::{Numeric};
+use regex::{self, Regex};
 use serde::de::{Deserializer, Deserialize, Error};
 
-use crate::intern::DiskPoolName;
+use crate::intern::{DiskPoolName, Network};
 
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
@@ -34,6 +35,30 @@ pub enum FallbackMode {
     never,        // don't serve anything without valid version
 }
 
+/// What to serve when directory is requested
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub enum IndexPolicy {
+    /// Serve first existing file of `index-files`
+    index,
+    /// Serve generated list of files
+    listing,
+    /// Redirect to first existing file of `index-files`
+    redirect,
+    /// Always respond with `403 Forbidden`
+    forbidden,
+}
+
+/// How to serve a `Range` request if response is compressed
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[allow(non_camel_case_types)]
+pub enum CompressedRanges {
+    /// Ignore the range and serve whole (compressed) file
+    full,
+    /// Serve the range of compressed bytes
+    compressed_bytes,
+}
+
 #[derive(Debug)]
 pub struct Static {
     pub mode: Mode,
@@ -41,12 +66,25 @@ pub struct Static {
     pub text_charset: Option<String>,
     pub pool: DiskPoolName,
     pub extra_headers: HashMap<String, String>,
+    pub compressed_ranges: CompressedRanges,
     pub strip_host_suffix: Option<String>,
     pub index_files: Vec<String>,
     pub generate_index: bool,
     pub generated_index_max_files: usize,
+    /// Maps glob of a file name to the network allowed to fetch the file
+    pub restrict_files: HashMap<String, Network>,
+    /// Policy for the root directory, default depends on `index-files` and
+    /// `generate-index`
+    pub root_index: Option<IndexPolicy>,
+    /// Same as `root_index` but for all other directories
+    pub dir_index: Option<IndexPolicy>,
     // Computed values
     pub headers_config: Arc<HeadersConfig>,
+    /// Same as `headers_config` but without `index-files`
+    pub headers_config_no_index: Arc<HeadersConfig>,
+    /// Path contains `$host` which is replaced by the request's host
+    pub host_in_path: bool,
+    pub restricted_patterns: Vec<(Regex, Network)>,
 }
 
 #[derive(Debug)]
@@ -55,6 +93,7 @@ pub struct SingleFile {
     pub content_type: Option<String>,
     pub pool: DiskPoolName,
     pub extra_headers: HashMap<String, String>,
+    pub compressed_ranges: CompressedRanges,
     // Computed values
     pub headers_config: Arc<HeadersConfig>,
 }
@@ -71,10 +110,32 @@ pub struct VersionedStatic {
     pub text_charset: Option<String>,
     pub pool: DiskPoolName,
     pub extra_headers: HashMap<String, String>,
+    pub compressed_ranges: CompressedRanges,
+    /// Same as `restrict_files` of `Static`, applies to both versioned
+    /// and plain files
+    pub restrict_files: HashMap<String, Network>,
     // Computed values
     pub version_len: usize,
     pub fallback: Arc<Static>,
     pub headers_config: Arc<HeadersConfig>,
+    pub restricted_patterns: Vec<(Regex, Network)>,
+}
+
+fn index_policy<'x>() -> Enum<'x> {
+    Enum::new()
+        .option("index", Nothing)
+        .option("listing", Nothing)
+        .option("redirect", Nothing)
+        .option("forbidden", Nothing)
+        .allow_plain()
+}
+
+fn compressed_ranges<'x>() -> Enum<'x> {
+    Enum::new()
+        .option("full", Nothing)
+        .option("compressed_bytes", Nothing)
+        .allow_plain()
+        .plain_default("full")
 }
 
 fn serve_mode<'x>() -> Enum<'x> {
@@ -93,11 +154,15 @@ pub fn validator<'x>() -> Structure<'x> {
     .member("text_charset", Scalar::new().default("utf-8").optional())
     .member("pool", Scalar::new().default("default"))
     .member("extra_headers", Mapping::new(Scalar::new(), Scalar::new()))
+    .member("compressed_ranges", compressed_ranges())
     .member("strip_host_suffix", Scalar::new().optional())
     .member("index_files", Sequence::new(Scalar::new()))
     .member("generate_index", Scalar::new().default(false))
     .member("generated_index_max_files",
         Numeric::new().min(0).default(100000))
+    .member("restrict_files", Mapping::new(Scalar::new(), Scalar::new()))
+    .member("root_index", index_policy().optional())
+    .member("dir_index", index_policy().optional())
 }
 
 pub fn single_file<'x>() -> Structure<'x> {
@@ -106,6 +171,7 @@ pub fn single_file<'x>() -> Structure<'x> {
     .member("content_type", Scalar::new().optional())
     .member("pool", Scalar::new().default("default"))
     .member("extra_headers", Mapping::new(Scalar::new(), Scalar::new()))
+    .member("compressed_ranges", compressed_ranges())
 }
 
 pub fn versioned_validator<'x>() -> Structure<'x> {
@@ -129,7 +195,9 @@ pub fn versioned_validator<'x>() -> Structure<'x> {
     .member("text_charset", Scalar::new().default("utf-8").optional())
     .member("pool", Scalar::new().default("default"))
     .member("extra_headers", Mapping::new(Scalar::new(), Scalar::new()))
+    .member("compressed_ranges", compressed_ranges())
     .member("strip_host_suffix", Scalar::new().optional())
+    .member("restrict_files", Mapping::new(Scalar::new(), Scalar::new()))
 }
 
 impl<'a> Deserialize<'a> for Static {
@@ -141,34 +209,54 @@ impl<'a> Deserialize<'a> for Static {
             pub text_charset: Option<String>,
             pub pool: DiskPoolName,
             pub extra_headers: HashMap<String, String>,
+            pub compressed_ranges: CompressedRanges,
             pub index_files: Vec<String>,
             pub generate_index: bool,
             pub generated_index_max_files: usize,
             pub strip_host_suffix: Option<String>,
+            pub restrict_files: HashMap<String, Network>,
+            pub root_index: Option<IndexPolicy>,
+            pub dir_index: Option<IndexPolicy>,
         }
         let int = Internal::deserialize(d)?;
-        let mut config = HeadersConfig::new();
-        match int.text_charset {
-            Some(ref charset) => { config.text_charset(charset); }
-            None => { config.no_text_charset(); }
-        }
-        if header_contains(&int.extra_headers, "Content-Type") {
-            config.content_type(false);
-        }
-        for index_file in &int.index_files {
-            config.add_index_file(&index_file);
-        }
+        let restricted_patterns = compile_restrictions::<D::Error>(
+            &int.restrict_files)?;
+        let headers_config = |index_files: &[String]| {
+            let mut config = HeadersConfig::new();
+            match int.text_charset {
+                Some(ref charset) => { config.text_charset(charset); }
+                None => { config.no_text_charset(); }
+            }
+            if header_contains(&int.extra_headers, "Content-Type") {
+                config.content_type(false);
+            }
+            for index_file in index_files {
+                config.add_index_file(&index_file);
+            }
+            config.done()
+        };
+        let config = headers_config(&int.index_files);
+        let no_index_config = headers_config(&[]);
+        let host_in_path = int.path.to_str()
+            .map(|p| p.contains("$host")).unwrap_or(false);
         return Ok(Static {
             mode: int.mode,
             path: int.path,
             text_charset: int.text_charset,
             pool: int.pool,
             extra_headers: int.extra_headers,
+            compressed_ranges: int.compressed_ranges,
             index_files: int.index_files,
             generate_index: int.generate_index,
             generated_index_max_files: int.generated_index_max_files,
             strip_host_suffix: int.strip_host_suffix,
-            headers_config: config.done(),
+            restrict_files: int.restrict_files,
+            root_index: int.root_index,
+            dir_index: int.dir_index,
+            headers_config: config,
+            headers_config_no_index: no_index_config,
+            host_in_path: host_in_path,
+            restricted_patterns: restricted_patterns,
         })
     }
 }
@@ -181,6 +269,7 @@ impl<'a> Deserialize<'a> for SingleFile {
             pub content_type: Option<String>,
             pub pool: DiskPoolName,
             pub extra_headers: HashMap<String, String>,
+            pub compressed_ranges: CompressedRanges,
         }
         let int = Internal::deserialize(d)?;
         if header_contains(&int.extra_headers, "Content-Type") {
@@ -198,6 +287,7 @@ impl<'a> Deserialize<'a> for SingleFile {
             content_type: int.content_type,
             pool: int.pool,
             extra_headers: int.extra_headers,
+            compressed_ranges: int.compressed_ranges,
             headers_config: config.done(),
         })
     }
@@ -217,8 +307,12 @@ impl<'a> Deserialize<'a> for VersionedStatic {
             pub text_charset: Option<String>,
             pub pool: DiskPoolName,
             pub extra_headers: HashMap<String, String>,
+            pub compressed_ranges: CompressedRanges,
+            pub restrict_files: HashMap<String, Network>,
         }
         let int = Internal::deserialize(d)?;
+        let restricted_patterns = compile_restrictions::<D::Error>(
+            &int.restrict_files)?;
         let mut config = HeadersConfig::new();
         match int.text_charset {
             Some(ref charset) => { config.text_charset(charset); }
@@ -236,11 +330,18 @@ impl<'a> Deserialize<'a> for VersionedStatic {
                 text_charset: int.text_charset.clone(),
                 pool: int.pool.clone(),
                 extra_headers: int.extra_headers.clone(),
+                compressed_ranges: int.compressed_ranges,
                 index_files: Vec::new(),
                 generate_index: false,
                 generated_index_max_files: 0,
                 strip_host_suffix: None,
+                restrict_files: HashMap::new(),
+                root_index: None,
+                dir_index: None,
                 headers_config: config.clone(),
+                headers_config_no_index: config.clone(),
+                host_in_path: false,
+                restricted_patterns: Vec::new(),
             }),
             versioned_root: int.versioned_root,
             plain_root: int.plain_root,
@@ -252,11 +353,46 @@ impl<'a> Deserialize<'a> for VersionedStatic {
             text_charset: int.text_charset,
             pool: int.pool,
             extra_headers: int.extra_headers,
+            compressed_ranges: int.compressed_ranges,
+            restrict_files: int.restrict_files,
             headers_config: config,
+            restricted_patterns: restricted_patterns,
         })
     }
 }
 
+/// Compiles globs of `restrict-files` into regexes
+fn compile_restrictions<E: Error>(restrict_files: &HashMap<String, Network>)
+    -> Result<Vec<(Regex, Network)>, E>
+{
+    let mut result = Vec::new();
+    for (glob, netw) in restrict_files {
+        let regex = Regex::new(&glob_to_regex(glob))
+            .map_err(|e| E::custom(format!(
+                "bad pattern {:?} in `restrict-files`: {}", glob, e)))?;
+        result.push((regex, netw.clone()));
+    }
+    return Ok(result);
+}
+
+/// Converts glob of a file name to an anchored regex
+///
+/// Only `*` (any number of characters) and `?` (single character) are
+/// supported, neither of them matches a slash.
+fn glob_to_regex(glob: &str) -> String {
+    let mut result = String::with_capacity(glob.len() + 8);
+    result.push('^');
+    for c in glob.chars() {
+        match c {
+            '*' => result.push_str("[^/]*"),
+            '?' => result.push_str("[^/]"),
+            c => result.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    result.push('$');
+    return result;
+}
+
 pub fn header_contains(map: &HashMap<String, String>, name: &str) -> bool {
     map.iter().any(|(header, _)| header.eq_ignore_ascii_case(name))
 }
@@ -269,11 +405,18 @@ impl PartialEq for Static {
             text_charset: ref a_text_charset,
             pool: ref a_pool,
             extra_headers: ref a_extra_headers,
+            compressed_ranges: ref a_compressed_ranges,
             strip_host_suffix: ref a_strip_host_suffix,
             index_files: ref a_index_files,
             generate_index: ref a_generate_index,
             generated_index_max_files: ref a_generated_index_max_files,
+            restrict_files: ref a_restrict_files,
+            root_index: ref a_root_index,
+            dir_index: ref a_dir_index,
             headers_config: _,
+            headers_config_no_index: _,
+            host_in_path: _,
+            restricted_patterns: _,
         } = *self;
         let Static {
             mode: ref b_mode,
@@ -281,21 +424,32 @@ impl PartialEq for Static {
             text_charset: ref b_text_charset,
             pool: ref b_pool,
             extra_headers: ref b_extra_headers,
+            compressed_ranges: ref b_compressed_ranges,
             strip_host_suffix: ref b_strip_host_suffix,
             index_files: ref b_index_files,
             generate_index: ref b_generate_index,
             generated_index_max_files: ref b_generated_index_max_files,
+            restrict_files: ref b_restrict_files,
+            root_index: ref b_root_index,
+            dir_index: ref b_dir_index,
             headers_config: _,
+            headers_config_no_index: _,
+            host_in_path: _,
+            restricted_patterns: _,
         } = *other;
         return a_mode == b_mode &&
                a_path == b_path &&
                a_text_charset == b_text_charset &&
                a_pool == b_pool &&
                a_extra_headers == b_extra_headers &&
+               a_compressed_ranges == b_compressed_ranges &&
                a_strip_host_suffix == b_strip_host_suffix &&
                a_index_files == b_index_files &&
                a_generate_index == b_generate_index &&
-               a_generated_index_max_files == b_generated_index_max_files;
+               a_generated_index_max_files == b_generated_index_max_files &&
+               a_restrict_files == b_restrict_files &&
+               a_root_index == b_root_index &&
+               a_dir_index == b_dir_index;
 
     }
 }
@@ -307,6 +461,7 @@ impl PartialEq for SingleFile {
             content_type: ref a_content_type,
             pool: ref a_pool,
             extra_headers: ref a_extra_headers,
+            compressed_ranges: ref a_compressed_ranges,
             headers_config: _,
         } = *self;
         let SingleFile {
@@ -314,12 +469,14 @@ impl PartialEq for SingleFile {
             content_type: ref b_content_type,
             pool: ref b_pool,
             extra_headers: ref b_extra_headers,
+            compressed_ranges: ref b_compressed_ranges,
             headers_config: _,
         } = *other;
         return a_path == b_path &&
                a_content_type == b_content_type &&
                a_pool == b_pool &&
-               a_extra_headers == b_extra_headers;
+               a_extra_headers == b_extra_headers &&
+               a_compressed_ranges == b_compressed_ranges;
     }
 }
 
@@ -336,9 +493,12 @@ impl PartialEq for VersionedStatic {
             text_charset: ref a_text_charset,
             pool: ref a_pool,
             extra_headers: ref a_extra_headers,
+            compressed_ranges: ref a_compressed_ranges,
+            restrict_files: ref a_restrict_files,
             version_len: _,
             fallback: _,
             headers_config: _,
+            restricted_patterns: _,
         } = *self;
         let VersionedStatic {
             versioned_root: ref b_versioned_root,
@@ -351,9 +511,12 @@ impl PartialEq for VersionedStatic {
             text_charset: ref b_text_charset,
             pool: ref b_pool,
             extra_headers: ref b_extra_headers,
+            compressed_ranges: ref b_compressed_ranges,
+            restrict_files: ref b_restrict_files,
             version_len: _,
             fallback: _,
             headers_config: _,
+            restricted_patterns: _,
         } = *other;
         return a_versioned_root == b_versioned_root &&
                a_plain_root == b_plain_root &&
@@ -364,10 +527,35 @@ impl PartialEq for VersionedStatic {
                a_fallback_mode == b_fallback_mode &&
                a_text_charset == b_text_charset &&
                a_pool == b_pool &&
-               a_extra_headers == b_extra_headers;
+               a_extra_headers == b_extra_headers &&
+               a_compressed_ranges == b_compressed_ranges &&
+               a_restrict_files == b_restrict_files;
     }
 }
 
 impl Eq for Static {}
 impl Eq for SingleFile {}
 impl Eq for VersionedStatic {}
+
+#[cfg(test)]
+mod test {
+    use regex::Regex;
+    use super::glob_to_regex;
+
+    fn matches(glob: &str, name: &str) -> bool {
+        Regex::new(&glob_to_regex(glob)).unwrap().is_match(name)
+    }
+
+    #[test]
+    fn glob() {
+        assert!(matches("*.map", "app.js.map"));
+        assert!(matches("*.map", ".map"));
+        assert!(!matches("*.map", "app.js"));
+        assert!(!matches("*.map", "app.map.js"));
+        assert!(!matches("*.map", "js/app.map"));
+        assert!(matches("app.?s", "app.js"));
+        assert!(!matches("app.?s", "app.jjs"));
+        assert!(!matches("a+b", "aab"));
+        assert!(matches("a+b", "a+b"));
+    }
+}
@@ -22,10 +22,20 @@ pub fn serve_error_page<S: 'static, C: IntoContext>(status: Status, ctx: C)
     reply(ctx, move |e| Box::new(error_page(status, e)))
 }
 
-pub fn error_page<S: 'static>(status: Status, mut e: Encoder<S>)
+pub fn error_page<S: 'static>(status: Status, e: Encoder<S>)
+    -> FutureResult<EncoderDone<S>, Error>
+{
+    error_page_with_headers(status, &[], e)
+}
+
+pub fn error_page_with_headers<S: 'static>(status: Status,
+    headers: &[(&str, &str)], mut e: Encoder<S>)
     -> FutureResult<EncoderDone<S>, Error>
 {
     e.status(status);
+    for &(name, value) in headers {
+        e.add_header(name, value);
+    }
     if status.response_has_body() {
         let status_var = StatusVar(status);
         let mut ctx = Context::new();
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use tk_http::Status;
+use tk_http::server::Error;
+
+use crate::config::by_extension::ByExtension;
+use crate::default_error_page::serve_error_page;
+use crate::incoming::{Request, Input, Transport};
+
+
+pub fn serve<S: Transport>(settings: &Arc<ByExtension>, inp: Input)
+    -> Result<Request<S>, Error>
+{
+    let path = inp.headers.path().unwrap_or("/");
+    let path = match path.find(|c| c == '?' || c == '#') {
+        Some(idx) => &path[..idx],
+        None => path,
+    };
+    let hname = settings.resolve(path);
+    match inp.config.handlers.get(hname) {
+        Some(handler) => handler.serve(inp),
+        None => {
+            warn!("No such handler for `!ByExtension`: {:?}", hname);
+            Ok(serve_error_page(Status::NotFound, inp))
+        }
+    }
+}
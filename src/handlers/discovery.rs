@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use futures::future::{ok};
+use serde_json::{self, Value as Json};
+use tk_http::Status;
+
+use crate::config::Handler;
+use crate::config::chat::Chat;
+use crate::config::discovery::Discovery;
+use crate::default_error_page::serve_error_page;
+use crate::handlers::method;
+use crate::handlers::swindon_chat::{SUBPROTOCOL, MAX_MESSAGE_SIZE};
+use crate::incoming::{reply, Request, Input};
+use crate::routing::parse_host;
+
+
+pub fn serve<S: 'static>(settings: &Arc<Discovery>, inp: Input)
+    -> Request<S>
+{
+    if !method::is_get_or_head(&inp) {
+        return method::method_not_allowed(inp);
+    }
+    let chat = match inp.config.handlers.get(&settings.chat) {
+        Some(&Handler::SwindonLattice(ref chat)) => chat,
+        // checked when config is read
+        _ => {
+            error!("Handler {:?} is not a chat", settings.chat);
+            return serve_error_page(Status::InternalServerError, inp);
+        }
+    };
+    let host = parse_host(inp.host);
+    let endpoint = inp.config.routing.handler_path(host, &settings.chat,
+        inp.local_port);
+    let endpoint = match endpoint {
+        Some("") => "/",
+        Some(path) => path,
+        None => {
+            warn!("Handler {:?} is not routed on host {:?} port {:?}, \
+                can't serve discovery document",
+                settings.chat, host, inp.local_port);
+            return serve_error_page(Status::NotFound, inp);
+        }
+    };
+    let body = serde_json::to_vec(&document(chat, endpoint))
+        .expect("can always serialize discovery document");
+    let max_age = settings.max_age.as_secs();
+    reply(inp, move |mut e| {
+        e.status(Status::Ok);
+        e.add_length(body.len() as u64);
+        e.add_header("Content-Type", "application/json");
+        e.format_header("Cache-Control",
+            format_args!("public, max-age={}", max_age));
+        if e.done_headers() {
+            e.write_body(body);
+        }
+        Box::new(ok(e.done()))
+    })
+}
+
+fn document(chat: &Chat, endpoint: &str) -> Json {
+    json!({
+        "endpoint": endpoint,
+        "subprotocols": [SUBPROTOCOL],
+        "max_message_size": MAX_MESSAGE_SIZE,
+        "max_json_depth": chat.max_json_depth,
+        "max_json_elements": chat.max_json_elements,
+        "max_subscriptions_per_connection":
+            chat.max_subscriptions_per_connection,
+        "message_rate_limit": chat.message_rate_limit.as_ref().map(|r| {
+            json!({"rate": r.rate, "burst": r.burst})
+        }),
+    })
+}
@@ -4,6 +4,7 @@ use tk_http::Status;
 use futures::future::{ok};
 
 use crate::config::empty_gif::EmptyGif;
+use crate::handlers::method;
 use crate::incoming::{reply, Request, Input};
 
 
@@ -13,6 +14,9 @@ const EMPTY_GIF: &'static [u8] = include_bytes!("../empty.gif");
 pub fn serve<S: 'static>(settings: &Arc<EmptyGif>, inp: Input)
     -> Request<S>
 {
+    if !method::is_get_or_head(&inp) {
+        return method::method_not_allowed(inp);
+    }
     let settings = settings.clone();
     reply(inp, move |mut e| {
         e.status(Status::Ok);
@@ -12,6 +12,8 @@ use crate::incoming::{self, Input, Request, Transport, Encoder};
 pub enum NotFile {
     Status(Status),
     Directory(Vec<u8>),
+    /// Redirect to the specified path (used for directory index)
+    Redirect(String),
 }
 
 
@@ -93,6 +95,13 @@ pub fn reply_file<S, A, X>(inp: Input, pool: CpuPool,
                 Err((NotFile::Status(status), _)) => {
                     Either::A(error_page(status, e))
                 }
+                Err((NotFile::Redirect(location), _)) => {
+                    e.status(Status::Found);
+                    e.add_header("Location", location);
+                    e.add_length(0);
+                    e.done_headers();
+                    Either::A(ok(e.done()))
+                }
                 Err((NotFile::Directory(data), x)) => {
                     e.status(Status::Ok);
                     e.add_length(data.len() as u64);
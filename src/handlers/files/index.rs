@@ -3,6 +3,7 @@ use std::path::Path;
 use std::fs::read_dir;
 use std::sync::Arc;
 
+use regex::Regex;
 use tk_http::Status;
 use trimmer::{Template, Context, Variable, Var, DataError};
 
@@ -29,15 +30,19 @@ lazy_static! {
         .expect("default dir index is a valid template");
 }
 
-fn read_files(path: &Path, settings: &Arc<Static>)
+fn read_files(path: &Path, settings: &Arc<Static>, hidden: &[Regex])
     -> Result<Vec<Entry>, Error>
 {
     let mut result = Vec::new();
     for entry in read_dir(path)? {
         let entry = entry?;
+        let name = Path::new(&entry.file_name()).display().to_string();
+        if hidden.iter().any(|pattern| pattern.is_match(&name)) {
+            continue;
+        }
         let typ = entry.file_type()?;
         result.push(Entry {
-            name: Path::new(&entry.file_name()).display().to_string(),
+            name: name,
             is_dir: typ.is_dir(),
         });
         if result.len() >= settings.generated_index_max_files {
@@ -47,11 +52,13 @@ fn read_files(path: &Path, settings: &Arc<Static>)
     Ok(result)
 }
 
+/// Renders directory listing, files matching `hidden` patterns
+/// are omitted
 pub fn generate_index(path: &Path, virtual_path: &str,
-    settings: &Arc<Static>)
+    settings: &Arc<Static>, hidden: &[Regex])
     -> Result<Vec<u8>, Status>
 {
-    let files = match read_files(path, settings) {
+    let files = match read_files(path, settings, hidden) {
         Ok(files) => files,
         Err(Error::TooManyFiles) => return Err(Status::Forbidden),
         Err(Error::Io(e)) => {
@@ -2,6 +2,7 @@ mod common;
 mod decode;
 mod index;
 mod pools;
+mod ranges;
 
 mod normal;
 mod single;
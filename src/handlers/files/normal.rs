@@ -1,17 +1,20 @@
 use std::io;
-use std::path::{PathBuf};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc};
 use std::str::from_utf8;
 
 use tk_http::Status;
-use http_file_headers::{Input as HeadersInput, Output};
+use http_file_headers::{Output};
+use regex::Regex;
 
-use crate::config::static_files::{Static, Mode};
+use crate::config::static_files::{Static, Mode, IndexPolicy};
 use crate::default_error_page::{serve_error_page};
 use crate::incoming::{Input, Request, Transport};
+use crate::intern::Network;
 use crate::handlers::files::decode::decode_component;
 use crate::handlers::files::pools::get_pool;
 use crate::handlers::files::common::{reply_file, NotFile};
+use crate::handlers::files::ranges::Probe;
 use crate::handlers::files::index::generate_index;
 
 
@@ -25,19 +28,48 @@ pub fn serve_dir<S: Transport>(settings: &Arc<Static>, mut inp: Input)
             return serve_error_page(Status::Forbidden, inp);
         }
     };
+    if !allowed_file(settings, &path, &mut inp) {
+        // pretend file doesn't exist, so it's not known to public
+        return serve_error_page(Status::NotFound, inp);
+    }
     let virtual_path = strip_query(inp.headers.path().unwrap_or("/"))
         .to_string();
     inp.debug.set_fs_path(&path);
     let pool = get_pool(&inp.runtime, &settings.pool);
     let settings = settings.clone();
     let settings2 = settings.clone();
+    let policy = if is_root(&settings, &inp) {
+        settings.root_index
+    } else {
+        settings.dir_index
+    };
 
-    let hinp = HeadersInput::from_headers(&settings.headers_config,
-        inp.headers.method(), inp.headers.headers());
+    let headers_config = match policy {
+        None | Some(IndexPolicy::index) => &settings.headers_config,
+        Some(_) => &settings.headers_config_no_index,
+    };
+    let hidden = hidden_patterns(&settings.restricted_patterns, &inp);
+    let hinp = Probe::new(headers_config, &inp, settings.compressed_ranges);
     let fut = pool.spawn_fn(move || {
         match hinp.probe_file(&path) {
-            Ok(Output::Directory) if settings2.generate_index => {
-                generate_index(&path, &virtual_path, &settings2)
+            Ok(Output::Directory) if policy == Some(IndexPolicy::redirect) => {
+                match find_index_file(&path, &settings2.index_files) {
+                    Some(name) => {
+                        let mut dest = virtual_path;
+                        if !dest.ends_with('/') {
+                            dest.push('/');
+                        }
+                        dest.push_str(name);
+                        Err((NotFile::Redirect(dest), ()))
+                    }
+                    None => Err((NotFile::Status(Status::Forbidden), ())),
+                }
+            }
+            Ok(Output::Directory)
+            if policy == Some(IndexPolicy::listing) ||
+               policy.is_none() && settings2.generate_index
+            => {
+                generate_index(&path, &virtual_path, &settings2, &hidden)
                 .map(|x| Err((NotFile::Directory(x), ())))
                 .unwrap_or_else(|s| Err((NotFile::Status(s), ())))
             }
@@ -61,6 +93,72 @@ pub fn serve_dir<S: Transport>(settings: &Arc<Static>, mut inp: Input)
     })
 }
 
+/// Returns true if request is for the root directory of the handler
+fn is_root(settings: &Static, inp: &Input) -> bool {
+    let path = match settings.mode {
+        Mode::relative_to_domain_root | Mode::with_hostname => {
+            inp.headers.path().unwrap_or("/")
+        }
+        Mode::relative_to_route => inp.suffix,
+    };
+    strip_query(path).split("/").all(|cmp| cmp == "" || cmp == ".")
+}
+
+fn find_index_file<'x>(dir: &Path, index_files: &'x [String])
+    -> Option<&'x str>
+{
+    index_files.iter()
+        .find(|name| dir.join(name).is_file())
+        .map(|name| &name[..])
+}
+
+/// Checks `restrict-files` patterns against file name of the path
+fn allowed_file(settings: &Static, path: &Path, inp: &mut Input) -> bool {
+    match path.file_name().and_then(|x| x.to_str()) {
+        Some(name) => allowed_name(&settings.restricted_patterns, name, inp),
+        None => true,
+    }
+}
+
+/// Checks `restrict-files` patterns against the file name
+pub fn allowed_name(patterns: &[(Regex, Network)], name: &str,
+    inp: &mut Input)
+    -> bool
+{
+    for &(ref pattern, ref netw) in patterns {
+        if !pattern.is_match(name) {
+            continue;
+        }
+        let ip = inp.addr.ip();
+        match inp.config.networks.get(netw).and_then(|n| n.get_subnet(ip)) {
+            Some(subnet) => {
+                inp.debug.add_allow(
+                    format_args!("restrict-files {}", subnet));
+            }
+            None => {
+                inp.debug.set_deny(format!("restrict-files {}", ip));
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+/// Returns `restrict-files` patterns the peer is not allowed to fetch,
+/// so that matching files are also omitted from the generated index
+fn hidden_patterns(patterns: &[(Regex, Network)], inp: &Input)
+    -> Vec<Regex>
+{
+    let ip = inp.addr.ip();
+    patterns.iter()
+        .filter(|&&(_, ref netw)| {
+            inp.config.networks.get(netw)
+                .and_then(|n| n.get_subnet(ip)).is_none()
+        })
+        .map(|&(ref pattern, _)| pattern.clone())
+        .collect()
+}
+
 fn strip_query(path: &str) -> &str {
     match path.find(|c| c == '?' || c == '#') {
         Some(idx) => &path[..idx],
@@ -78,38 +176,34 @@ pub fn path(settings: &Static, inp: &Input) -> Result<PathBuf, ()> {
     let path = strip_query(path);
     let mut buf = Vec::with_capacity(path.len());
     if settings.mode == Mode::with_hostname {
-        match inp.headers.host()  {
-            Some(host) => {
-                if host.find("/").is_some() {
-                    // no slashes allowed
-                    return Err(());
-                }
-                let name: &str = if let Some(colon) = host.find(":") {
-                    &host[..colon]
-                } else {
-                    &host[..]
-                };
-                let name = if let Some(ref suf) = settings.strip_host_suffix {
-                    if suf.len() >= name.len() {
-                        // empty prefix is not allowed yet
-                        return Err(());
-                    }
-                    if !name.ends_with(suf) {
-                        // only this suffix should work
-                        return Err(());
-                    }
-                    let final_dot = name.len() - suf.len() - 1;
-                    if !name[final_dot..].starts_with('.') {
-                        return Err(())
-                    }
-                    &name[..final_dot]
-                } else {
-                    name
-                };
-                buf.extend(name.as_bytes());
-            }
-            None => return Err(()),
+        let host = inp.host;
+        if host.find("/").is_some() {
+            // no slashes allowed
+            return Err(());
         }
+        let name: &str = if let Some(colon) = host.find(":") {
+            &host[..colon]
+        } else {
+            &host[..]
+        };
+        let name = if let Some(ref suf) = settings.strip_host_suffix {
+            if suf.len() >= name.len() {
+                // empty prefix is not allowed yet
+                return Err(());
+            }
+            if !name.ends_with(suf) {
+                // only this suffix should work
+                return Err(());
+            }
+            let final_dot = name.len() - suf.len() - 1;
+            if !name[final_dot..].starts_with('.') {
+                return Err(())
+            }
+            &name[..final_dot]
+        } else {
+            name
+        };
+        buf.extend(name.as_bytes());
     }
     for cmp in path.split("/") {
         match cmp {
@@ -130,5 +224,48 @@ pub fn path(settings: &Static, inp: &Input) -> Result<PathBuf, ()> {
 
     // only valid utf-8 supported so far
     let utf8 = from_utf8(&buf).map_err(|_| ())?;
+    if settings.host_in_path {
+        let host = sanitize_host(inp.host).ok_or(())?;
+        let root = settings.path.to_string_lossy().replace("$host", &host);
+        return Ok(PathBuf::from(root).join(utf8));
+    }
     Ok(settings.path.join(utf8))
 }
+
+/// Returns lowercase host name without port if it's safe to use as a
+/// directory name
+fn sanitize_host(host: &str) -> Option<String> {
+    let name = match host.find(':') {
+        Some(colon) => &host[..colon],
+        None => host,
+    };
+    if name.is_empty() || name.starts_with('.') || name.contains("..") {
+        return None;
+    }
+    if !name.bytes().all(|c| c.is_ascii_alphanumeric() ||
+                             c == b'-' || c == b'.')
+    {
+        return None;
+    }
+    Some(name.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod test {
+    use super::sanitize_host;
+
+    #[test]
+    fn host() {
+        assert_eq!(sanitize_host("a.example.com"),
+            Some("a.example.com".to_string()));
+        assert_eq!(sanitize_host("A.Example.COM:8080"),
+            Some("a.example.com".to_string()));
+        assert_eq!(sanitize_host(""), None);
+        assert_eq!(sanitize_host(":80"), None);
+        assert_eq!(sanitize_host(".."), None);
+        assert_eq!(sanitize_host("..example.com"), None);
+        assert_eq!(sanitize_host("a/../b"), None);
+        assert_eq!(sanitize_host("a\\b"), None);
+        assert_eq!(sanitize_host("[::1]:80"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::io;
+use std::path::Path;
+use std::str::from_utf8;
+use std::sync::Arc;
+
+use http_file_headers::{Config as HeadersConfig, Input as HeadersInput};
+use http_file_headers::{Output};
+
+use crate::config::static_files::CompressedRanges;
+use crate::incoming::Input;
+
+
+/// Probes the file deciding whether `Range` of the request is honored
+///
+/// The decision table is:
+///
+/// 1. No `Range` header -- full response
+/// 2. Response is compressed and `compressed-ranges: full` -- full response
+/// 3. `If-Range` is a weak validator or doesn't match `ETag` (or
+///    `Last-Modified` date) of the representation chosen by encoding --
+///    full response
+/// 4. Otherwise -- partial response (or `416` for unsatisfiable range)
+///
+/// Since encoding is known only after probing the file, request is probed
+/// the second time without range headers when range is not honored.
+pub struct Probe {
+    input: HeadersInput,
+    /// Same as `input` but with `Range` and `If-Range` stripped, only
+    /// present when request has a `Range` header
+    full: Option<HeadersInput>,
+    if_range: Option<String>,
+    compressed_ranges: CompressedRanges,
+}
+
+fn is_range_header(name: &str) -> bool {
+    name.eq_ignore_ascii_case("Range") || name.eq_ignore_ascii_case("If-Range")
+}
+
+impl Probe {
+    pub fn new(config: &Arc<HeadersConfig>, inp: &Input,
+        compressed_ranges: CompressedRanges)
+        -> Probe
+    {
+        let method = inp.headers.method();
+        let has_range = inp.ingress_headers()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Range"));
+        let full = if has_range {
+            Some(HeadersInput::from_headers(config, method,
+                inp.ingress_headers()
+                .filter(|&(name, _)| !is_range_header(name))))
+        } else {
+            None
+        };
+        let if_range = inp.ingress_headers()
+            .find(|&(name, _)| name.eq_ignore_ascii_case("If-Range"))
+            .map(|(_, value)| from_utf8(value)
+                .map(|v| v.trim().to_string())
+                // invalid value never matches
+                .unwrap_or_else(|_| String::from("W/")));
+        Probe {
+            input: HeadersInput::from_headers(config, method,
+                inp.ingress_headers()),
+            full: full,
+            if_range: if_range,
+            compressed_ranges: compressed_ranges,
+        }
+    }
+    pub fn probe_file(&self, path: &Path) -> io::Result<Output> {
+        let out = self.input.probe_file(path)?;
+        let full = match self.full {
+            Some(ref full) => full,
+            None => return Ok(out),
+        };
+        let honor = match out {
+            Output::InvalidRange => {
+                // Encoding and validators are only known for the full
+                // response, range which would be ignored can't be invalid
+                let full_out = full.probe_file(path)?;
+                return match response_headers(&full_out) {
+                    Some(ref hdr) if !self.honor_range(hdr) => Ok(full_out),
+                    _ => Ok(Output::InvalidRange),
+                };
+            }
+            ref out => match response_headers(out) {
+                Some(ref hdr) if is_partial(out) => self.honor_range(hdr),
+                _ => true,
+            },
+        };
+        if honor {
+            Ok(out)
+        } else {
+            full.probe_file(path)
+        }
+    }
+    fn honor_range(&self, headers: &[(String, String)]) -> bool {
+        honor_range(headers, self.if_range.as_ref().map(|x| &x[..]),
+                    self.compressed_ranges)
+    }
+}
+
+fn is_partial(out: &Output) -> bool {
+    match *out {
+        Output::FileRange(ref f) => f.is_partial(),
+        Output::FileHead(ref h) => h.is_partial(),
+        _ => false,
+    }
+}
+
+fn response_headers(out: &Output) -> Option<Vec<(String, String)>> {
+    match *out {
+        Output::File(ref f) | Output::FileRange(ref f) => {
+            Some(f.headers()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect())
+        }
+        Output::FileHead(ref h) => {
+            Some(h.headers()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect())
+        }
+        _ => None,
+    }
+}
+
+fn honor_range(headers: &[(String, String)], if_range: Option<&str>,
+    compressed_ranges: CompressedRanges)
+    -> bool
+{
+    let header = |name: &str| {
+        headers.iter()
+            .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, ref v)| v.trim())
+    };
+    if header("Content-Encoding").is_some() &&
+        compressed_ranges == CompressedRanges::full
+    {
+        return false;
+    }
+    match if_range {
+        None => true,
+        // If-Range requires strong comparison (RFC 7233, section 3.2)
+        Some(cond) if cond.starts_with("W/") => false,
+        Some(cond) if cond.starts_with('"') => header("ETag") == Some(cond),
+        Some(date) => header("Last-Modified") == Some(date),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::static_files::CompressedRanges::*;
+    use super::honor_range;
+
+    fn hdr(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|&(n, v)| (n.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn identity() {
+        let h = hdr(&[("ETag", "\"abc\""),
+                      ("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT")]);
+        assert!(honor_range(&h, None, full));
+        assert!(honor_range(&h, Some("\"abc\""), full));
+        assert!(!honor_range(&h, Some("\"abd\""), full));
+        assert!(!honor_range(&h, Some("W/\"abc\""), full));
+        assert!(honor_range(&h,
+            Some("Sun, 06 Nov 1994 08:49:37 GMT"), full));
+        assert!(!honor_range(&h,
+            Some("Sun, 06 Nov 1994 08:49:38 GMT"), full));
+    }
+
+    #[test]
+    fn compressed() {
+        let h = hdr(&[("Content-Encoding", "gzip"), ("ETag", "\"abc-gz\"")]);
+        assert!(!honor_range(&h, None, full));
+        assert!(!honor_range(&h, Some("\"abc-gz\""), full));
+        assert!(honor_range(&h, None, compressed_bytes));
+        assert!(honor_range(&h, Some("\"abc-gz\""), compressed_bytes));
+        // validator of the identity representation
+        assert!(!honor_range(&h, Some("\"abc\""), compressed_bytes));
+    }
+}
@@ -2,13 +2,13 @@ use std::io;
 use std::sync::{Arc};
 
 use tk_http::Status;
-use http_file_headers::{Input as HeadersInput};
 
 use crate::config::static_files::{SingleFile};
 use crate::default_error_page::{serve_error_page};
 use crate::incoming::{Input, Request, Transport};
 use crate::handlers::files::pools::get_pool;
 use crate::handlers::files::common::{reply_file, NotFile};
+use crate::handlers::files::ranges::Probe;
 
 
 pub fn serve_file<S: Transport>(settings: &Arc<SingleFile>, mut inp: Input)
@@ -23,8 +23,8 @@ pub fn serve_file<S: Transport>(settings: &Arc<SingleFile>, mut inp: Input)
     let settings = settings.clone();
     let settings2 = settings.clone();
 
-    let hinp = HeadersInput::from_headers(&settings.headers_config,
-        inp.headers.method(), inp.headers.headers());
+    let hinp = Probe::new(&settings.headers_config, &inp,
+        settings.compressed_ranges);
     let fut = pool.spawn_fn(move || {
         hinp.probe_file(&settings2.path)
         .map(|x| (x, ()))
@@ -4,7 +4,7 @@ use std::sync::{Arc};
 use std::str::from_utf8;
 use std::time::{SystemTime, Duration};
 
-use http_file_headers::{Input as HeadersInput, Output};
+use http_file_headers::{Output};
 use httpdate::HttpDate;
 use tk_http::Status;
 
@@ -15,6 +15,7 @@ use crate::handlers::files::decode::decode_component;
 use crate::handlers::files::normal;
 use crate::handlers::files::pools::get_pool;
 use crate::handlers::files::common::{reply_file, NotFile};
+use crate::handlers::files::ranges::Probe;
 
 
 const VERSIONED_CACHE: &str = "public, max-age=31536000, immutable";
@@ -96,6 +97,23 @@ fn path(settings: &VersionedStatic, inp: &Input)
     Ok(settings.versioned_root.join(utf8))
 }
 
+/// Returns decoded file name of the request, which is the same for
+/// versioned and plain file, to match against `restrict-files`
+fn requested_name(inp: &Input) -> Option<String> {
+    let path = inp.headers.path().unwrap_or("/");
+    let path = match path.find(|c| c == '?' || c == '#') {
+        Some(idx) => &path[..idx],
+        None => path
+    };
+    let file_name = match path.rfind('/') {
+        Some(idx) => &path[idx+1..],
+        None => path,
+    };
+    let mut buf = Vec::with_capacity(file_name.len());
+    decode_component(&mut buf, file_name).ok()?;
+    String::from_utf8(buf).ok()
+}
+
 pub fn serve_versioned<S: Transport>(settings: &Arc<VersionedStatic>,
     mut inp: Input)
     -> Request<S>
@@ -106,6 +124,16 @@ pub fn serve_versioned<S: Transport>(settings: &Arc<VersionedStatic>,
         &path.as_ref().ok().map(|x| -> &Path { x.as_ref() })
         .or(npath.as_ref().map(|x| -> &Path { x.as_ref() }))
         .unwrap_or(&Path::new("")));
+    let allowed = requested_name(&inp)
+        .map(|name| normal::allowed_name(&settings.restricted_patterns,
+                                         &name, &mut inp))
+        .unwrap_or(true);
+    if !allowed {
+        // pretend file doesn't exist, so it's not known to public
+        return reply(inp, move |e| {
+            Box::new(error_page(Status::NotFound, e))
+        });
+    }
     let pool = get_pool(&inp.runtime, &settings.pool);
     let settings = settings.clone();
     let settings2 = settings.clone();
@@ -116,8 +144,8 @@ pub fn serve_versioned<S: Transport>(settings: &Arc<VersionedStatic>,
         });
     }
 
-    let hinp = HeadersInput::from_headers(&settings.headers_config,
-        inp.headers.method(), inp.headers.headers());
+    let hinp = Probe::new(&settings.headers_config, &inp,
+        settings.compressed_ranges);
     let fut = pool.spawn_fn(move || {
         use self::VersionError::*;
         use crate::config::static_files::FallbackMode::*;
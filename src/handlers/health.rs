@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+use futures::future::{ok};
+use tk_http::Status;
+
+use crate::config::health::{Health, Probe};
+use crate::handlers::method;
+use crate::incoming::{reply, Request, Input};
+use crate::runtime::Runtime;
+
+
+pub fn serve<S: 'static>(settings: &Arc<Health>, inp: Input)
+    -> Request<S>
+{
+    if !method::is_get_or_head(&inp) {
+        return method::method_not_allowed(inp);
+    }
+    let result = match settings.probe {
+        Probe::liveness => Ok(()),
+        Probe::readiness => readiness(settings, &inp.runtime),
+    };
+    let (status, body) = match result {
+        Ok(()) => (Status::Ok, "ok\n"),
+        Err(reason) => {
+            debug!("Readiness check failed: {}", reason.trim());
+            (Status::ServiceUnavailable, reason)
+        }
+    };
+    reply(inp, move |mut e| {
+        e.status(status);
+        e.add_length(body.len() as u64);
+        e.add_header("Content-Type", "text/plain; charset=utf-8");
+        e.add_header("Cache-Control", "no-cache, no-store");
+        if e.done_headers() {
+            e.write_body(body);
+        }
+        Box::new(ok(e.done()))
+    })
+}
+
+fn readiness(settings: &Health, runtime: &Runtime)
+    -> Result<(), &'static str>
+{
+    if !runtime.ready.load(Ordering::SeqCst) {
+        return Err("warming up\n");
+    }
+    if runtime.draining.load(Ordering::SeqCst) {
+        return Err("shutting down\n");
+    }
+    if settings.check_upstreams && runtime.http_pools.all_unhealthy() {
+        return Err("all upstreams are unhealthy\n");
+    }
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use tk_http::Status;
+
+use crate::default_error_page::error_page_with_headers;
+use crate::incoming::{reply, Request, Input};
+
+
+/// Returns true for methods synthetic handlers (i.e. the ones which don't
+/// have any resource behind them, like `!EmptyGif`) can serve
+///
+/// Response to `HEAD` is the same as to `GET` except the body is omitted
+/// by the encoder
+pub fn is_get_or_head(inp: &Input) -> bool {
+    match inp.headers.method() {
+        "GET" | "HEAD" => true,
+        _ => false,
+    }
+}
+
+/// Replies with `405 Method Not Allowed` and `Allow: GET, HEAD`
+pub fn method_not_allowed<S: 'static>(inp: Input) -> Request<S> {
+    reply(inp, |e| {
+        Box::new(error_page_with_headers(Status::MethodNotAllowed,
+            &[("Allow", "GET, HEAD")], e))
+    })
+}
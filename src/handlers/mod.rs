@@ -1,7 +1,15 @@
+pub mod by_extension;
+pub mod discovery;
 pub mod empty_gif;
 pub mod files;
+pub mod health;
+pub mod method;
+#[cfg(feature="test-handlers")]
+pub mod panic;
 pub mod websocket_echo;
 pub mod swindon_chat;
 pub mod proxy;
+pub mod query_echo;
 pub mod redirect;
+pub mod robots_txt;
 pub mod self_status;
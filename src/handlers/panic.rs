@@ -0,0 +1,9 @@
+use crate::incoming::{reply, Request, Input};
+
+
+pub fn serve<S: 'static>(inp: Input) -> Request<S> {
+    if inp.suffix == "/early" {
+        panic!("panic requested before reply");
+    }
+    reply(inp, |_| panic!("panic requested"))
+}
@@ -11,10 +11,6 @@ use crate::default_error_page::serve_error_page;
 pub fn serve<S: 'static>(settings: &Arc<Proxy>, inp: Input)
     -> Request<S>
 {
-    if inp.headers.host().is_none() {
-        // Can't proxy without Host
-        return serve_error_page(Status::BadRequest, inp)
-    }
     if matches!(*inp.headers.request_target(), Authority(..)) {
         // Can't proxy without Host
         return serve_error_page(Status::BadRequest, inp)
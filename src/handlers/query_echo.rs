@@ -0,0 +1,27 @@
+use futures::future::{ok};
+use serde_json;
+use tk_http::Status;
+
+use crate::default_error_page::serve_error_page;
+use crate::incoming::{reply, Request, Input};
+
+
+pub fn serve<S: 'static>(mut inp: Input) -> Request<S> {
+    let body = match inp.query_params() {
+        Ok(params) => serde_json::to_vec(params.as_map())
+            .expect("can always serialize query"),
+        Err(e) => {
+            debug!("Bad query: {}", e);
+            return serve_error_page(Status::BadRequest, inp);
+        }
+    };
+    reply(inp, move |mut e| {
+        e.status(Status::Ok);
+        e.add_length(body.len() as u64);
+        e.add_header("Content-Type", "application/json");
+        if e.done_headers() {
+            e.write_body(body);
+        }
+        Box::new(ok(e.done()))
+    })
+}
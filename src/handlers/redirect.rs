@@ -4,8 +4,9 @@ use tk_http::Status;
 use futures::future::ok;
 
 use crate::default_error_page::serve_error_page;
-use crate::config::redirect::BaseRedirect;
+use crate::config::redirect::{BaseRedirect, CanonicalRedirect, Scheme};
 use crate::incoming::{reply, Request, Input};
+use crate::routing::parse_host;
 
 
 pub fn base_redirect<S: 'static>(settings: &Arc<BaseRedirect>, inp: Input)
@@ -20,25 +21,76 @@ pub fn strip_www_redirect<S: 'static>(inp: Input)
     -> Request<S>
 {
 
-    let base_host = inp.headers.host().and_then(|h| {
-        if h.len() > 4 && h[0..4].eq_ignore_ascii_case("www.") {
-            Some(&h[4..])
-        } else {
-            None
-        }
-    });
+    let h = inp.host;
+    let base_host = if h.len() > 4 && h[0..4].eq_ignore_ascii_case("www.") {
+        Some(&h[4..])
+    } else {
+        None
+    };
     match base_host {
         Some(host) => serve_redirect(host, Status::MovedPermanently, inp),
         None => serve_error_page(Status::NotFound, inp),
     }
 }
 
+pub fn canonical_redirect<S: 'static>(settings: &Arc<CanonicalRedirect>,
+    inp: Input)
+    -> Request<S>
+{
+    let scheme = request_scheme(&inp);
+    let same_host = if settings.host.contains(':') {
+        inp.host.trim().eq_ignore_ascii_case(&settings.host)
+    } else {
+        parse_host(inp.host).eq_ignore_ascii_case(&settings.host)
+    };
+    if same_host && scheme == settings.scheme {
+        // redirecting would loop
+        return serve_error_page(Status::NotFound, inp);
+    }
+    let dest = format!("{}://{}{}",
+        match settings.scheme {
+            Scheme::http => "http",
+            Scheme::https => "https",
+        },
+        settings.host, inp.headers.path().unwrap_or("/"));
+    let status = match settings.status {
+        302 => Status::Found,
+        307 => Status::TemporaryRedirect,
+        308 => Status::PermanentRedirect,
+        _ => Status::MovedPermanently,
+    };
+    send_redirect(dest, status, inp)
+}
+
+/// Scheme of the original request as reported by a TLS terminator in front
+/// of swindon (we can only receive plain http ourselves)
+///
+/// Header is only trusted if the peer is a trusted proxy.
+fn request_scheme(inp: &Input) -> Scheme {
+    if !inp.trusted_peer {
+        return Scheme::http;
+    }
+    let https = inp.ingress_headers()
+        .find(|&(name, _)| name.eq_ignore_ascii_case("X-Forwarded-Proto"))
+        .and_then(|(_, value)| std::str::from_utf8(value).ok())
+        .and_then(|v| v.split(',').next())
+        .map(|v| v.trim().eq_ignore_ascii_case("https"))
+        .unwrap_or(false);
+    if https { Scheme::https } else { Scheme::http }
+}
+
 
 fn serve_redirect<S: 'static>(host: &str, status: Status, inp: Input)
     -> Request<S>
 {
     // TODO: properly identify request scheme
     let dest = format!("http://{}{}", host, inp.headers.path().unwrap_or("/"));
+    send_redirect(dest, status, inp)
+}
+
+fn send_redirect<S: 'static>(dest: String, status: Status, inp: Input)
+    -> Request<S>
+{
     reply(inp, move |mut e| {
         e.status(status);
         e.add_header("Location", dest);
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::str::from_utf8;
+
+use tk_http::Status;
+use futures::future::{ok};
+
+use crate::config::robots_txt::RobotsTxt;
+use crate::handlers::method;
+use crate::incoming::{reply, Request, Input};
+
+
+pub fn serve<S: 'static>(settings: &Arc<RobotsTxt>, inp: Input)
+    -> Request<S>
+{
+    if !method::is_get_or_head(&inp) {
+        return method::method_not_allowed(inp);
+    }
+    let not_modified = inp.ingress_headers()
+        .filter(|&(name, _)| name.eq_ignore_ascii_case("If-None-Match"))
+        .any(|(_, value)| etag_matches(value, &settings.etag));
+    let settings = settings.clone();
+    reply(inp, move |mut e| {
+        if not_modified {
+            e.status(Status::NotModified);
+        } else {
+            e.status(Status::Ok);
+            e.add_length(settings.content.len() as u64);
+            e.add_header("Content-Type", "text/plain; charset=utf-8");
+        }
+        e.add_header("ETag", &settings.etag);
+        e.format_header("Cache-Control",
+            format_args!("public, max-age={}", settings.max_age.as_secs()));
+        if e.done_headers() {
+            e.write_body(settings.content.as_bytes());
+        }
+        Box::new(ok(e.done()))
+    })
+}
+
+/// Checks `If-None-Match` value, which uses weak comparison
+/// (RFC 7232, section 3.2)
+fn etag_matches(value: &[u8], etag: &str) -> bool {
+    let value = match from_utf8(value) {
+        Ok(value) => value,
+        // invalid value never matches
+        Err(_) => return false,
+    };
+    value.split(',').any(|tag| {
+        let tag = tag.trim();
+        tag == "*" || tag.trim_start_matches("W/") == etag
+    })
+}
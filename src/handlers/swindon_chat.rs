@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use futures::{Async, Future};
 use futures::stream::{Stream};
@@ -9,16 +10,18 @@ use tk_http::server::{Error, Codec, RecvMode};
 use tk_http::server as http;
 use tk_http::websocket::{self, ServerCodec as WebsocketCodec, Packet, Accept};
 use tk_bufstream::{ReadBuf, WriteBuf};
-use futures::future::{ok};
+use futures::future::{ok, empty};
 use futures::sync::mpsc::{UnboundedReceiver as Receiver};
-use tokio_core::reactor::Handle;
+use tokio_core::reactor::{Handle, Timeout};
 use tokio_io::{AsyncRead, AsyncWrite};
 use serde_json::{to_string as json_encode, Value as Json};
 
-use crate::chat::ConnectionMessage::{Hello, FatalError};
+use crate::chat::ConnectionMessage::{Hello, FatalError, StopSock};
 use crate::chat::MessageError::HttpError;
 use crate::chat::{self, Cid, ConnectionMessage, ConnectionSender};
-use crate::chat::{json_err, good_status};
+use crate::chat::{CloseReason, RateLimiter, PendingRequests};
+use crate::chat::{json_err, json_err_with_connection_id, good_status};
+use crate::chat::get_request_id;
 use crate::chat::tangle_auth::{SwindonAuth, TangleAuth};
 use crate::config::chat::{Chat};
 use crate::default_error_page::serve_error_page;
@@ -26,12 +29,19 @@ use crate::incoming::{Context, IntoContext};
 use crate::incoming::{Request, Input, Reply, Encoder, Transport};
 use crate::runtime::Runtime;
 
+/// Websocket subprotocol of the chat
+pub const SUBPROTOCOL: &str = "v1.swindon-lattice+json";
+
+/// Maximum size of websocket message (default of the websocket config)
+pub const MAX_MESSAGE_SIZE: usize = 10 << 20;
+
 struct WebsockReply {
     cid: Cid,
     handle: Handle,
     runtime: Arc<Runtime>,
     settings: Arc<Chat>,
     reply_data: Option<ReplyData>,
+    deadline: Option<Instant>,
     channel: Option<(ConnectionSender, Receiver<ConnectionMessage>)>,
 }
 
@@ -91,13 +101,31 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
         let r1 = self.runtime.clone();
         let s1 = self.settings.clone();
         let cid = self.cid;
+        let deadline = self.deadline;
+        let connection_id = format!("{}-{}", self.runtime.server_id, cid);
 
         let (tx, rx) = self.channel.take()
             .expect("hijack called only once");
         let log_err_io = |e| debug!("closing websocket closed: {}", e);
         let log_err_sock = |e| debug!("closing websocket closed: {}", e);
 
-        self.handle.spawn(rx.into_future()
+        let timeout = Timeout::new(self.settings.auth_timeout, &self.handle)
+            .expect("can always add a timeout");
+        let timeout_code = self.settings.auth_timeout_close_code;
+        let handshake = rx.into_future().select2(timeout)
+            .then(move |result| match result {
+                Ok(Either::A((pair, _))) => Ok(pair),
+                Ok(Either::B(_)) | Err(Either::B(_)) => {
+                    debug!("Aborted handshake because of auth timeout");
+                    Err(("auth_timeout", timeout_code, "auth_timeout"))
+                }
+                Err(Either::A(_)) => {
+                    error!("Aborted handshake because pool closed");
+                    Err(("pool_closed", 1011, ""))
+                }
+            });
+
+        self.handle.spawn(handshake
             .then(move |result| match result {
                 Ok((Some(Hello(session_id, data)), rx)) => {
                     // Cache formatted auth
@@ -113,10 +141,56 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
                             .expect("every message can be encoded")))
                         .map_err(|e| info!("error sending userinfo: {:?}", e))
                         .and_then(move |out| {
-                            let rx = rx.map(|x| {
+                            let echo_cid = s1.connection_id_in_errors;
+                            let expire = match deadline {
+                                Some(deadline) => Either::A(
+                                    Timeout::new_at(deadline, &h1)
+                                    .expect("can always add a timeout")
+                                    .map_err(|e| error!("Connection age \
+                                        timer error: {}", e))),
+                                None => Either::B(empty()),
+                            };
+                            let rx = rx.select(expire.into_stream()
+                                .map(|()| {
+                                    StopSock(CloseReason::MaxConnectionAge)
+                                }))
+                            .map(move |x| {
                                 chat::FRAMES_SENT.incr(1);
-                                Packet::Text(json_encode(&x)
-                                    .expect("any data can be serialized"))
+                                match x {
+                                    StopSock(CloseReason::RateLimitExceeded)
+                                    => {
+                                        Packet::Close(1008,
+                                            "rate_limit_exceeded".into())
+                                    }
+                                    StopSock(CloseReason::ConfigReloaded)
+                                    => {
+                                        Packet::Close(1001,
+                                            "config_reloaded".into())
+                                    }
+                                    StopSock(CloseReason::MaxConnectionAge)
+                                    => {
+                                        Packet::Close(1001,
+                                            "max_connection_age".into())
+                                    }
+                                    ConnectionMessage::Error(ref meta, ref err)
+                                    => {
+                                        log_error(&connection_id, meta, err);
+                                        let data = if echo_cid {
+                                            json_encode(
+                                                &json_err_with_connection_id(
+                                                    meta, err,
+                                                    &connection_id))
+                                        } else {
+                                            json_encode(&x)
+                                        };
+                                        Packet::Text(data
+                                        .expect("any data can be serialized"))
+                                    }
+                                    x => {
+                                        Packet::Text(json_encode(&x)
+                                        .expect("any data can be serialized"))
+                                    }
+                                }
                             }).map_err(|_| -> &str {
                                 // There shouldn't be a real-life case for
                                 // this.  But in case session-pool has been
@@ -128,6 +202,10 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
                             });
                             chat::CONNECTS.incr(1);
                             chat::CONNECTIONS.incr(1);
+                            r1.chat_reload.add(cid, &s1, &r1.config.get(),
+                                &tx);
+                            let rate_limiter = s1.message_rate_limit.as_ref()
+                                .map(|x| RateLimiter::new(x, Instant::now()));
                             websocket::Loop::server(out, inp, rx,
                                 chat::Dispatcher {
                                     cid: cid,
@@ -140,6 +218,8 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
                                     runtime: r1,
                                     settings: s1,
                                     channel: tx,
+                                    rate_limiter: rate_limiter,
+                                    pending_requests: PendingRequests::new(),
                                 }, &cfg, &h2)
                             .map_err(|e| debug!("websocket closed: {}", e))
                         }))
@@ -171,21 +251,20 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
                 Ok((msg, _)) => {
                     panic!("Received {:?} instead of Hello", msg);
                 }
-                Err(_) => {
-                    error!("Aborted handshake because pool closed");
+                Err((error_kind, code, reason)) => {
                     Either::B(Either::B(
                         // TODO(tailhook) optimize json
                         out.send(Packet::Text(json_encode(&Json::Array(vec![
                             "fatal_error".into(),
                             json!({
-                                "error_kind": "pool_closed",
+                                "error_kind": error_kind,
                             }),
                             Json::Null,
                         ])).expect("can always serialize")))
                         .map_err(log_err_io)
                         .and_then(move |out| {
                             websocket::Loop::<_, _, _>::closing(out, inp,
-                                    1011, "", //
+                                    code, reason,
                                     &cfg, &h2)
                             .map_err(log_err_sock)
                         })))
@@ -194,6 +273,20 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
     }
 }
 
+fn log_error(connection_id: &str, meta: &chat::Meta,
+    err: &chat::MessageError)
+{
+    match get_request_id(meta) {
+        Some(request_id) => {
+            info!("Error on connection {} for request_id {}: {}",
+                connection_id, request_id, err);
+        }
+        None => {
+            info!("Error on connection {}: {}", connection_id, err);
+        }
+    }
+}
+
 fn choose_proto(h: &http::WebsocketHandshake, settings: &Arc<Chat>)
     -> Result<Option<&'static str>, ()>
 {
@@ -203,8 +296,8 @@ fn choose_proto(h: &http::WebsocketHandshake, settings: &Arc<Chat>)
         } else {
             Err(())
         }
-    } else if h.protocols.iter().any(|x| &x[..] == "v1.swindon-lattice+json") {
-        return Ok(Some("v1.swindon-lattice+json"));
+    } else if h.protocols.iter().any(|x| &x[..] == SUBPROTOCOL) {
+        return Ok(Some(SUBPROTOCOL));
     } else {
         return Ok(None);
     }
@@ -215,7 +308,12 @@ pub fn serve<S: Transport>(settings: &Arc<Chat>, inp: Input)
 {
     match inp.headers.get_websocket_upgrade() {
         Ok(Some(ws)) => {
-            if let Ok(proto) = choose_proto(&ws, settings) {
+            // Only the client side of the handshake is limited here,
+            // authorization backend has its own timeout
+            if inp.request_start.elapsed() > settings.handshake_timeout {
+                debug!("Upgrade request took longer than handshake-timeout");
+                Ok(serve_error_page(Status::RequestTimeout, inp))
+            } else if let Ok(proto) = choose_proto(&ws, settings) {
                 let (tx, rx) = ConnectionSender::new();
                 let cid = Cid::new();
                 chat::start_authorize(&inp, cid, settings, tx.clone());
@@ -224,6 +322,7 @@ pub fn serve<S: Transport>(settings: &Arc<Chat>, inp: Input)
                     handle: inp.handle.clone(),
                     settings: settings.clone(),
                     runtime: inp.runtime.clone(),
+                    deadline: inp.connection_deadline,
                     reply_data: Some(ReplyData {
                         context: inp.into_context(),
                         accept: ws.accept,
@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use futures::{Async, Future};
 use futures::stream::{Stream};
 use tk_http::Status;
@@ -11,13 +9,12 @@ use futures::future::{ok};
 use tokio_core::reactor::Handle;
 use tokio_io::{AsyncRead, AsyncWrite};
 
-use crate::config::Config;
-use crate::incoming::{Request, Input, Debug, Reply, Encoder};
+use crate::incoming::{Request, Input, Context, IntoContext, Reply, Encoder};
 use crate::default_error_page::serve_error_page;
 
 
 struct WebsockReply {
-    rdata: Option<(Arc<Config>, Debug, Accept)>,
+    rdata: Option<(Context, Accept)>,
     handle: Handle,
 }
 
@@ -35,9 +32,9 @@ impl<S: AsyncRead + AsyncWrite + 'static> Codec<S> for WebsockReply {
         Ok(Async::Ready(0))
     }
     fn start_response(&mut self, e: http::Encoder<S>) -> Reply<S> {
-        let (config, debug, accept) = self.rdata.take()
+        let (context, accept) = self.rdata.take()
             .expect("start response called once");
-        let mut e = Encoder::new(e, (config, debug));
+        let mut e = Encoder::new(e, context);
         e.status(Status::SwitchingProtocol);
         e.add_header("Connection", "upgrade");
         e.add_header("Upgrade", "websocket");
@@ -60,8 +57,8 @@ pub fn serve<S: AsyncRead + AsyncWrite + 'static>(inp: Input) -> Request<S> {
     match inp.headers.get_websocket_upgrade() {
         Ok(Some(ws)) => {
             Box::new(WebsockReply {
-                rdata: Some((inp.config.clone(), inp.debug, ws.accept)),
                 handle: inp.handle.clone(),
+                rdata: Some((inp.into_context(), ws.accept)),
             })
         }
         Ok(None) => {
@@ -1,5 +1,6 @@
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 
 use ns_router::{Router};
@@ -11,10 +12,14 @@ use tokio_core::net::TcpStream;
 use tokio_core::reactor::Handle;
 use tk_pool::queue::Pool;
 use tk_pool::pool_for;
-use futures::future::FutureResult;
+use futures::{Future, Sink, Async, Poll, StartSend};
+use futures::future::{FutureResult, Either, ok};
+use futures::task::{self, Task};
 use libcantal::{Collection, Visitor};
 
 use crate::intern::Upstream;
+use crate::config::Config;
+use crate::config::handlers::Handler;
 use crate::config::http_destinations::Destination;
 use crate::metrics::{Counter, List, Metric, Integer};
 
@@ -40,8 +45,19 @@ lazy_static! {
     pub static ref POOLS: Integer = Integer::new();
     pub static ref POOLS_STARTED: Counter = Counter::new();
     pub static ref POOLS_STOPPED: Counter = Counter::new();
+
+    pub static ref LIMITED_CONNECTIONS: Integer = Integer::new();
+    pub static ref CONNECTIONS_DELAYED: Counter = Counter::new();
+
+    /// Connection attempts waiting for a slot of `max-upstream-connections`
+    static ref WAITING: Mutex<Vec<Task>> = Mutex::new(Vec::new());
 }
 
+/// Upstream connections counted by `max-upstream-connections`
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+/// Value of `max-upstream-connections`, zero means no limit
+static MAX_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
 /// Future that is used for sending a client request
 ///
 /// While we only support fully buffered requests it's fine to use
@@ -56,22 +72,43 @@ pub struct HttpPool {
     metrics: PoolMetrics,
 }
 
+type PoolMap = HashMap<Upstream, HttpPool>;
+
 pub struct UpstreamRef<'a> {
-    pools: &'a HttpPools,
+    pools: &'a RwLock<PoolMap>,
     upstream: &'a Upstream,
 }
 
 pub struct UpstreamGuard<'a> {
-    guard: RwLockWriteGuard<'a, HashMap<Upstream, HttpPool>>,
+    guard: RwLockWriteGuard<'a, PoolMap>,
     upstream: &'a Upstream,
 }
 
 #[derive(Clone)]
 pub struct HttpPools {
-    plain: Arc<RwLock<HashMap<Upstream, HttpPool>>>,
+    plain: Arc<RwLock<PoolMap>>,
+    /// Pools for `shadow-upstream` of proxy handlers, these are separate
+    /// and not limited by `max-upstream-connections`, so mirrored traffic
+    /// never takes connections from real requests
+    shadow: Arc<RwLock<PoolMap>>,
     // TODO(tailhook) https pools
 }
 
+/// A slot of `max-upstream-connections`, released on drop
+struct ConnectionSlot(());
+
+/// Waits until there are less than `max-upstream-connections` connections
+struct WaitSlot {
+    delayed: bool,
+}
+
+/// Upstream connection which holds a slot of `max-upstream-connections`
+/// (unless it belongs to a shadow pool) until it's closed
+struct LimitedConnection<S> {
+    sink: S,
+    _slot: Option<ConnectionSlot>,
+}
+
 #[derive(Clone, Debug)]
 pub struct PoolMetrics(Arc<Metrics>);
 
@@ -80,7 +117,7 @@ pub struct PoolLog(Upstream);
 
 #[derive(Debug)]
 struct Metrics {
-    name: Upstream,
+    group: String,
 
     connecting: Integer,
     connected: Integer,
@@ -99,11 +136,11 @@ struct Metrics {
 }
 
 impl Metrics {
-    fn new(name: &Upstream) -> Metrics {
+    fn new(group: String) -> Metrics {
         POOLS.incr(1);
         POOLS_STARTED.incr(1);
         Metrics {
-            name: name.clone(),
+            group: group,
 
             connecting: Integer::new(),
             connected: Integer::new(),
@@ -127,28 +164,28 @@ impl Collection for PoolMetrics {
     fn visit<'x>(&'x self, v: &mut dyn Visitor<'x>) {
         use crate::metrics::Metric as M;
         let ref s = self.0;
-        let g = format!("http.pools.{}", s.name);
-        v.metric(&M(&g, "connecting"), &s.connecting);
-        v.metric(&M(&g, "connecting"), &s.connecting);
-        v.metric(&M(&g, "connected"), &s.connected);
-        v.metric(&M(&g, "blacklisted"), &s.blacklisted);
-        v.metric(&M(&g, "request_queue"), &s.request_queue);
-
-        v.metric(&M(&g, "connection_attempted"), &s.connection_attempted);
-        v.metric(&M(&g, "connection_aborted"), &s.connection_aborted);
-        v.metric(&M(&g, "connection_errored"), &s.connection_errored);
-        v.metric(&M(&g, "connection_established"), &s.connection_established);
-        v.metric(&M(&g, "connection_dropped"), &s.connection_dropped);
-        v.metric(&M(&g, "blacklist_added"), &s.blacklist_added);
-        v.metric(&M(&g, "blacklist_removed"), &s.blacklist_removed);
-        v.metric(&M(&g, "requests_queued"), &s.requests_queued);
-        v.metric(&M(&g, "requests_forwarded"), &s.requests_forwarded);
+        let ref g = s.group;
+        v.metric(&M(g, "connecting"), &s.connecting);
+        v.metric(&M(g, "connecting"), &s.connecting);
+        v.metric(&M(g, "connected"), &s.connected);
+        v.metric(&M(g, "blacklisted"), &s.blacklisted);
+        v.metric(&M(g, "request_queue"), &s.request_queue);
+
+        v.metric(&M(g, "connection_attempted"), &s.connection_attempted);
+        v.metric(&M(g, "connection_aborted"), &s.connection_aborted);
+        v.metric(&M(g, "connection_errored"), &s.connection_errored);
+        v.metric(&M(g, "connection_established"), &s.connection_established);
+        v.metric(&M(g, "connection_dropped"), &s.connection_dropped);
+        v.metric(&M(g, "blacklist_added"), &s.blacklist_added);
+        v.metric(&M(g, "blacklist_removed"), &s.blacklist_removed);
+        v.metric(&M(g, "requests_queued"), &s.requests_queued);
+        v.metric(&M(g, "requests_forwarded"), &s.requests_forwarded);
     }
 }
 
 impl PoolMetrics {
-    fn new(name: &Upstream) -> PoolMetrics {
-        PoolMetrics(Arc::new(Metrics::new(name)))
+    fn new(group: String) -> PoolMetrics {
+        PoolMetrics(Arc::new(Metrics::new(group)))
     }
 }
 
@@ -220,60 +257,188 @@ impl HttpPools {
     pub fn new() -> HttpPools {
         HttpPools {
             plain: Arc::new(RwLock::new(HashMap::new())),
+            shadow: Arc::new(RwLock::new(HashMap::new())),
         }
     }
     pub fn upstream<'x>(&'x self, dest: &'x Upstream) -> UpstreamRef<'x> {
         UpstreamRef {
-            pools: self,
+            pools: &self.plain,
             upstream: &dest,
         }
     }
-    pub fn update(&self, cfg: &HashMap<Upstream, Arc<Destination>>,
-        resolver: &Router, handle: &Handle)
+    /// Pool for the `shadow-upstream` of a proxy handler
+    pub fn shadow_upstream<'x>(&'x self, dest: &'x Upstream)
+        -> UpstreamRef<'x>
     {
-        let mut plain = self.plain.write().expect("pools not poisoned");
-        let mut to_delete = Vec::new();
-        for k in plain.keys() {
-            if !cfg.contains_key(k) {
-                to_delete.push(k.clone());
-            }
-        }
-        for k in to_delete {
-            plain.remove(&k);
+        UpstreamRef {
+            pools: &self.shadow,
+            upstream: &dest,
         }
-        for (k, dest) in cfg {
-            // TODO(tailhook) compare destinations
-            if !plain.contains_key(k) {
-                let h2 = handle.clone();
-                let conn_config = HConfig::new()
-                    .inflight_request_limit(
-                        dest.in_flight_requests_per_backend_connection)
-                    .keep_alive_timeout(dest.keep_alive_timeout)
-                    .safe_pipeline_timeout(dest.safe_pipeline_timeout)
-                    .max_request_timeout(dest.max_request_timeout)
-                    .done();
-                let metrics = PoolMetrics::new(k);
-                let pool = pool_for(move |addr| {
-                        Proto::connect_tcp(addr, &conn_config, &h2)
+    }
+    pub fn update(&self, cfg: &Config, resolver: &Router, handle: &Handle) {
+        set_max_connections(cfg.max_upstream_connections);
+        let shadow = cfg.handlers.values()
+            .filter_map(|h| match *h {
+                Handler::Proxy(ref p) => p.shadow_upstream.as_ref(),
+                _ => None,
+            })
+            .map(|d| &d.upstream)
+            .collect::<HashSet<_>>();
+        update_pools(&mut self.plain.write().expect("pools not poisoned"),
+            cfg.http_destinations.iter(), "http.pools", true,
+            resolver, handle);
+        let shadow_destinations = cfg.http_destinations.iter()
+            .filter(|&(k, _)| shadow.contains(&k));
+        update_pools(&mut self.shadow.write().expect("pools not poisoned"),
+            shadow_destinations, "http.shadow_pools", false,
+            resolver, handle);
+    }
+}
+
+fn update_pools<'x, I>(pools: &mut PoolMap, cfg: I, group: &str,
+    limited: bool, resolver: &Router, handle: &Handle)
+    where I: Iterator<Item=(&'x Upstream, &'x Arc<Destination>)> + Clone,
+{
+    pools.retain(|k, _| cfg.clone().any(|(name, _)| name == k));
+    for (k, dest) in cfg {
+        // TODO(tailhook) compare destinations
+        if !pools.contains_key(k) {
+            let h2 = handle.clone();
+            let conn_config = HConfig::new()
+                .inflight_request_limit(
+                    dest.in_flight_requests_per_backend_connection)
+                .keep_alive_timeout(dest.keep_alive_timeout)
+                .safe_pipeline_timeout(dest.safe_pipeline_timeout)
+                .max_request_timeout(dest.max_request_timeout)
+                .done();
+            let metrics = PoolMetrics::new(format!("{}.{}", group, k));
+            let pool = pool_for(move |addr| {
+                    let conn_config = conn_config.clone();
+                    let h3 = h2.clone();
+                    let slot = if limited {
+                        Either::A(WaitSlot { delayed: false }.map(Some))
+                    } else {
+                        Either::B(ok(None))
+                    };
+                    slot.and_then(move |slot| {
+                        Proto::connect_tcp(addr, &conn_config, &h3)
+                        .map(move |sink| LimitedConnection {
+                            sink: sink,
+                            _slot: slot,
+                        })
                     })
-                    .connect_to(resolver.subscribe_many(&dest.addresses, 80))
-                    .lazy_uniform_connections(
-                        dest.backend_connections_per_ip_port as u32)
-                    .with_queue_size(
-                        dest.queue_size_for_503)
-                    .metrics(metrics.clone())
-                    .errors(PoolLog(k.clone()))
-                    .spawn_on(handle);
-                plain.insert(k.clone(), HttpPool { pool, metrics });
-            }
+                })
+                .connect_to(resolver.subscribe_many(&dest.addresses, 80))
+                .lazy_uniform_connections(
+                    dest.backend_connections_per_ip_port as u32)
+                .with_queue_size(
+                    dest.queue_size_for_503)
+                .metrics(metrics.clone())
+                .errors(PoolLog(k.clone()))
+                .spawn_on(handle);
+            pools.insert(k.clone(), HttpPool { pool, metrics });
         }
-     }
+    }
+}
+
+fn set_max_connections(max: Option<usize>) {
+    let old = MAX_CONNECTIONS.swap(max.unwrap_or(0), Ordering::SeqCst);
+    if old != max.unwrap_or(0) {
+        // limit might be raised, so waiting connections may proceed
+        wake_waiting();
+    }
+}
+
+fn wake_waiting() {
+    let tasks = {
+        let mut waiting = WAITING.lock().expect("waiting not poisoned");
+        waiting.drain(..).collect::<Vec<_>>()
+    };
+    for task in tasks {
+        task.notify();
+    }
+}
+
+impl ConnectionSlot {
+    fn acquire() -> Option<ConnectionSlot> {
+        let max = MAX_CONNECTIONS.load(Ordering::SeqCst);
+        let prev = ACTIVE.fetch_add(1, Ordering::SeqCst);
+        if max != 0 && prev >= max {
+            ACTIVE.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        LIMITED_CONNECTIONS.incr(1);
+        Some(ConnectionSlot(()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::SeqCst);
+        LIMITED_CONNECTIONS.decr(1);
+        wake_waiting();
+    }
+}
+
+impl Future for WaitSlot {
+    type Item = ConnectionSlot;
+    type Error = Error;
+    fn poll(&mut self) -> Poll<ConnectionSlot, Error> {
+        if let Some(slot) = ConnectionSlot::acquire() {
+            return Ok(Async::Ready(slot));
+        }
+        WAITING.lock().expect("waiting not poisoned").push(task::current());
+        // slot might have been released before task is registered
+        if let Some(slot) = ConnectionSlot::acquire() {
+            return Ok(Async::Ready(slot));
+        }
+        if !self.delayed {
+            self.delayed = true;
+            CONNECTIONS_DELAYED.incr(1);
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+impl<S: Sink> Sink for LimitedConnection<S> {
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+    fn start_send(&mut self, item: S::SinkItem)
+        -> StartSend<S::SinkItem, S::SinkError>
+    {
+        self.sink.start_send(item)
+    }
+    fn poll_complete(&mut self) -> Poll<(), S::SinkError> {
+        self.sink.poll_complete()
+    }
+    fn close(&mut self) -> Poll<(), S::SinkError> {
+        self.sink.close()
+    }
+}
+
+impl HttpPools {
+    /// Returns `true` if there is at least one pool and no pool has
+    /// established connections, while every pool either has blacklisted
+    /// addresses or has tried to connect and never succeeded
+    ///
+    /// Pools which haven't tried to connect yet (no requests so far) are
+    /// considered healthy.
+    pub fn all_unhealthy(&self) -> bool {
+        let plain = self.plain.read().expect("pools not poisoned");
+        !plain.is_empty() && plain.values().all(|p| {
+            let ref m = p.metrics.0;
+            let never_connected = m.connection_attempted.get() > 0 &&
+                m.connection_established.get() == 0;
+            m.connected.get() == 0 &&
+                (m.blacklisted.get() > 0 || never_connected)
+        })
+    }
 }
 
 impl<'a> UpstreamRef<'a> {
     pub fn get_mut(&mut self) -> UpstreamGuard<'a> {
         UpstreamGuard {
-            guard: self.pools.plain.write().expect("pools not poisoned"),
+            guard: self.pools.write().expect("pools not poisoned"),
             upstream: self.upstream,
         }
     }
@@ -310,12 +475,16 @@ pub fn metrics() -> List {
         (Metric(base, "pools"), &*POOLS),
         (Metric(base, "pools_started"), &*POOLS_STARTED),
         (Metric(base, "pools_stopped"), &*POOLS_STOPPED),
+
+        (Metric(base, "limited_connections"), &*LIMITED_CONNECTIONS),
+        (Metric(base, "connections_delayed"), &*CONNECTIONS_DELAYED),
     ]
 }
 
 pub fn pool_metrics(h: &HttpPools) -> Vec<PoolMetrics> {
-    h.plain.read().expect("http pools are okay")
-        .values()
+    let plain = h.plain.read().expect("http pools are okay");
+    let shadow = h.shadow.read().expect("http pools are okay");
+    plain.values().chain(shadow.values())
         .map(|p| p.metrics.clone())
         .collect()
 }
@@ -1,7 +1,9 @@
+use std::net::IpAddr;
+
 use tk_http::server::{Error};
 
 use crate::incoming::{Input};
-use crate::config::{Authorizer};
+use crate::config::{Authorizer, Config};
 use crate::authorizers;
 
 // TODO(tailhook) this should eventually be a virtual method on Authorizer
@@ -15,4 +17,17 @@ impl Authorizer {
             Authorizer::Ldap(_) => unimplemented!(),
         }
     }
+    /// Returns `true` if peer is in `accept-forwarded-headers-from`
+    /// network of the `!SourceIp` authorizer, i.e. it's a trusted proxy
+    pub fn trusts_peer(&self, addr: IpAddr, config: &Config) -> bool {
+        match *self {
+            Authorizer::SourceIp(ref cfg) => {
+                cfg.accept_forwarded_headers_from.as_ref()
+                    .and_then(|netw| config.networks.get(netw))
+                    .map(|netw| netw.get_subnet(addr).is_some())
+                    .unwrap_or(false)
+            }
+            Authorizer::AllowAll | Authorizer::Ldap(_) => false,
+        }
+    }
 }
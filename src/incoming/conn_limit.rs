@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::metrics::{Counter, Integer};
+
+
+lazy_static! {
+    pub static ref REFUSED: Counter = Counter::new();
+    pub static ref TRACKED_IPS: Integer = Integer::new();
+}
+
+/// Tracks number of open connections for every client IP
+/// (`max-connections-per-ip` setting)
+#[derive(Clone)]
+pub struct ConnectionLimit {
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+/// Holds a slot for the connection, the slot is released on drop
+pub struct ConnectionGuard {
+    ip: IpAddr,
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl ConnectionLimit {
+    pub fn new() -> ConnectionLimit {
+        ConnectionLimit {
+            counts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    /// Returns a guard if there are less than `max` connections from `ip`
+    pub fn acquire(&self, ip: IpAddr, max: usize) -> Option<ConnectionGuard> {
+        let mut counts = self.counts.lock()
+            .expect("connection counters are not poisoned");
+        let cur = counts.entry(ip).or_insert(0);
+        if *cur >= max {
+            REFUSED.incr(1);
+            return None;
+        }
+        *cur += 1;
+        TRACKED_IPS.set(counts.len() as i64);
+        Some(ConnectionGuard {
+            ip: ip,
+            counts: self.counts.clone(),
+        })
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock()
+            .expect("connection counters are not poisoned");
+        let left = match counts.get_mut(&self.ip) {
+            Some(cur) => {
+                *cur -= 1;
+                *cur
+            }
+            None => {
+                debug_assert!(false, "no counter for {}", self.ip);
+                return;
+            }
+        };
+        if left == 0 {
+            counts.remove(&self.ip);
+        }
+        TRACKED_IPS.set(counts.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use super::ConnectionLimit;
+
+    #[test]
+    fn limit() {
+        let lim = ConnectionLimit::new();
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        let g1 = lim.acquire(a, 2).unwrap();
+        let g2 = lim.acquire(a, 2).unwrap();
+        assert!(lim.acquire(a, 2).is_none());
+        let g3 = lim.acquire(b, 2).unwrap();
+        drop(g1);
+        let g4 = lim.acquire(a, 2).unwrap();
+        drop((g2, g3, g4));
+        assert!(lim.counts.lock().unwrap().is_empty());
+    }
+}
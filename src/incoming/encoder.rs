@@ -1,9 +1,15 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::io;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
 use std::sync::Arc;
+use std::thread;
+use std::time::UNIX_EPOCH;
 
 use futures::{Future, Async};
+use httpdate::HttpDate;
 use tk_http::Status;
 use tk_http::server as http;
 use tk_http::server::{EncoderDone};
@@ -11,20 +17,44 @@ use tokio_io::AsyncWrite;
 
 
 use crate::config::Config;
-use crate::incoming::Debug;
+use crate::incoming::{Debug, RequestState};
 
-pub type Context = (Arc<Config>, Debug);
+pub type Context = (Arc<Config>, Debug, RequestState);
 
 
+/// Response encoder used by handlers
+///
+/// Responses with status that forbids a body (`1xx`, `204`, `304`) are
+/// encoded without `Content-Length`, `Transfer-Encoding` and body
+/// regardless of what handler writes, so handlers don't need to special
+/// case them.
 pub struct Encoder<S> {
-    enc: http::Encoder<S>,
+    enc: Guarded<http::Encoder<S>>,
     config: Arc<Config>,
-    debug: Debug,
+    debug: Guarded<Debug>,
+    state: Guarded<RequestState>,
+    bodiless: bool,
+}
+
+type Slot<T> = Rc<RefCell<Option<T>>>;
+
+/// Parts of the encoder that are given back if handler panics before
+/// it has started the response, so that `500` can still be sent
+pub struct Rescue<S> {
+    enc: Slot<http::Encoder<S>>,
+    debug: Slot<Debug>,
+    state: Slot<RequestState>,
+}
+
+/// Value that is put into the rescue slot when dropped by a panic
+struct Guarded<T> {
+    value: Option<T>,
+    slot: Option<Slot<T>>,
 }
 
 pub struct WaitFlush<S> {
     fut: http::WaitFlush<S>,
-    data: Option<(Arc<Config>, Debug)>,
+    data: Option<(Context, bool)>,
 }
 
 /// Represents object that can be used for getting enough context for encoder
@@ -33,7 +63,7 @@ pub trait IntoContext: Sized {
     fn into_context(self) -> Context;
 }
 
-impl IntoContext for (Arc<Config>, Debug) {
+impl IntoContext for (Arc<Config>, Debug, RequestState) {
     fn into_context(self) -> Context {
         self
     }
@@ -45,12 +75,14 @@ impl<S: AsyncWrite> Future for WaitFlush<S> {
     fn poll(&mut self) -> Result<Async<Encoder<S>>, io::Error> {
         match self.fut.poll()? {
             Async::Ready(x) => {
-                let (config, debug) = self.data.take()
+                let ((config, debug, state), bodiless) = self.data.take()
                     .expect("future polled twice");
                 Ok(Async::Ready(Encoder {
-                    enc: x,
+                    enc: Guarded::new(x, None),
                     config: config,
-                    debug: debug,
+                    debug: Guarded::new(debug, None),
+                    state: Guarded::new(state, None),
+                    bodiless: bodiless,
                 }))
             }
             Async::NotReady => Ok(Async::NotReady),
@@ -62,26 +94,123 @@ impl<S> Encoder<S> {
     pub fn new(enc: http::Encoder<S>, context: Context)
         -> Encoder<S>
     {
-        let (config, debug) = context;
+        Encoder::create(enc, context, None)
+    }
+    /// Creates encoder which parts are put into `rescue` if it's dropped
+    /// by a panic before response status is written
+    pub fn rescuable(enc: http::Encoder<S>, context: Context,
+        rescue: &Rescue<S>)
+        -> Encoder<S>
+    {
+        Encoder::create(enc, context, Some(rescue))
+    }
+    fn create(enc: http::Encoder<S>, context: Context,
+        rescue: Option<&Rescue<S>>)
+        -> Encoder<S>
+    {
+        let (config, debug, state) = context;
+        state.trace("response-start", "");
         Encoder {
-            enc: enc,
+            enc: Guarded::new(enc, rescue.map(|r| r.enc.clone())),
             config: config,
-            debug: debug,
+            debug: Guarded::new(debug, rescue.map(|r| r.debug.clone())),
+            state: Guarded::new(state, rescue.map(|r| r.state.clone())),
+            bodiless: false,
         }
     }
+    /// Response is started, so nothing can be rescued anymore
+    fn disarm(&mut self) {
+        self.enc.slot = None;
+        self.debug.slot = None;
+        self.state.slot = None;
+    }
+}
+
+impl<S> Rescue<S> {
+    pub fn new() -> Rescue<S> {
+        Rescue {
+            enc: Rc::new(RefCell::new(None)),
+            debug: Rc::new(RefCell::new(None)),
+            state: Rc::new(RefCell::new(None)),
+        }
+    }
+    /// Returns encoder parts if encoder was dropped by a panic before
+    /// the response was started
+    pub fn take(&self) -> Option<(http::Encoder<S>, Debug, RequestState)> {
+        let enc = self.enc.borrow_mut().take();
+        let debug = self.debug.borrow_mut().take();
+        let state = self.state.borrow_mut().take();
+        match (enc, debug, state) {
+            (Some(enc), Some(debug), Some(state)) => Some((enc, debug, state)),
+            _ => None,
+        }
+    }
+}
+
+impl<T> Guarded<T> {
+    fn new(value: T, slot: Option<Slot<T>>) -> Guarded<T> {
+        Guarded {
+            value: Some(value),
+            slot: slot,
+        }
+    }
+    fn into_inner(mut self) -> T {
+        self.value.take().expect("value is present")
+    }
+}
+
+impl<T> Deref for Guarded<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value.as_ref().expect("value is present")
+    }
+}
+
+impl<T> DerefMut for Guarded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().expect("value is present")
+    }
+}
+
+impl<T> Drop for Guarded<T> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            if thread::panicking() {
+                *slot.borrow_mut() = self.value.take();
+            }
+        }
+    }
+}
+
+fn bodiless_status(code: u16) -> bool {
+    code / 100 == 1 || code == 204 || code == 304
 }
 
 impl<S> Encoder<S> {
     pub fn status(&mut self, status: Status) {
+        self.disarm();
+        self.bodiless = bodiless_status(status.code());
+        self.state.set_status(status.code());
         self.enc.status(status);
     }
     pub fn custom_status(&mut self, code: u16, reason: &str) {
+        self.disarm();
+        self.bodiless = bodiless_status(code);
+        self.state.set_status(code);
         self.enc.custom_status(code, reason);
     }
+    /// Adds `Content-Length`, unless status forbids a body
     pub fn add_length(&mut self, n: u64) {
+        if self.bodiless {
+            return;
+        }
         self.enc.add_length(n).unwrap();
     }
+    /// Enables chunked encoding, unless status forbids a body
     pub fn add_chunked(&mut self) {
+        if self.bodiless {
+            return;
+        }
         self.enc.add_chunked().unwrap();
     }
     pub fn add_header<V: AsRef<[u8]>>(&mut self, name: &str, value: V) {
@@ -106,12 +235,18 @@ impl<S> Encoder<S> {
             }
         }
     }
+    /// Returns `false` if body must not be written
     pub fn done_headers(&mut self) -> bool {
+        let bodiless = self.bodiless;
         let ref mut enc = self.enc;
         self.config.server_name.as_ref().map(|name| {
             enc.add_header("Server", name).unwrap();
         });
         enc.add_date();
+        if self.state.connection_expired() {
+            enc.add_header("Connection", "close")
+                .expect("connection is a valid header");
+        }
         if let Some(route) = self.debug.get_route() {
             enc.add_header("X-Swindon-Route", route)
                 .expect("route is a valid header");
@@ -137,28 +272,70 @@ impl<S> Encoder<S> {
             enc.format_header("X-Swindon-Deny", value)
                 .expect("deny debug info is a valid header");
         }
+        if let Some(dep) = self.state.get_deprecation() {
+            match dep.deprecated_since {
+                Some(since) => {
+                    let ts = since.duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs()).unwrap_or(0);
+                    enc.format_header("Deprecation", format_args!("@{}", ts))
+                        .expect("timestamp is a valid header");
+                }
+                None => {
+                    enc.add_header("Deprecation", "true")
+                        .expect("deprecation is a valid header");
+                }
+            }
+            if let Some(sunset) = dep.sunset {
+                enc.format_header("Sunset", HttpDate::from(sunset))
+                    .expect("date is a valid header");
+            }
+            if let Some(ref link) = dep.link {
+                enc.format_header("Link",
+                    format_args!("<{}>; rel=\"deprecation\"", link))
+                    .expect("link is validated in config");
+            }
+        }
 
-        enc.done_headers().unwrap()
+        enc.done_headers().unwrap() && !bodiless
     }
     pub fn write_body<T: AsRef<[u8]>>(&mut self, val: T) {
-        self.enc.write_body(val.as_ref())
+        if self.bodiless {
+            return;
+        }
+        let val = val.as_ref();
+        if let Some(stats) = self.state.get_route_stats() {
+            stats.add_response_bytes(val.len());
+        }
+        self.enc.write_body(val)
     }
-    pub fn done(self) -> EncoderDone<S> {
-        self.enc.done()
+    pub fn done(mut self) -> EncoderDone<S> {
+        self.state.trace("completed", "");
+        self.state.finish();
+        self.enc.into_inner().done()
     }
     pub fn wait_flush(self, n: usize) -> WaitFlush<S> {
         WaitFlush {
-            fut: self.enc.wait_flush(n),
-            data: Some((self.config, self.debug)),
+            fut: self.enc.into_inner().wait_flush(n),
+            data: Some(((self.config, self.debug.into_inner(),
+                         self.state.into_inner()),
+                        self.bodiless)),
         }
     }
 }
 
 impl<S> io::Write for Encoder<S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.enc.write(buf)
+        if self.bodiless {
+            // pretend it's written, like tk-http does for HEAD requests
+            return Ok(buf.len());
+        }
+        let n = self.enc.write(buf)?;
+        if let Some(stats) = self.state.get_route_stats() {
+            stats.add_response_bytes(n);
+        }
+        Ok(n)
     }
     fn flush(&mut self) -> io::Result<()> {
-        io::Write::flush(&mut self.enc)
+        io::Write::flush(&mut *self.enc)
     }
 }
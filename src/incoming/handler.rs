@@ -19,6 +19,9 @@ impl Handler {
             Handler::EmptyGif(ref h) => {
                 Ok(handlers::empty_gif::serve(h, input))
             }
+            Handler::RobotsTxt(ref h) => {
+                Ok(handlers::robots_txt::serve(h, input))
+            }
             Handler::NotFound => {
                 Ok(serve_error_page(Status::NotFound, input))
             }
@@ -40,6 +43,13 @@ impl Handler {
             Handler::WebsocketEcho => {
                 Ok(handlers::websocket_echo::serve(input))
             }
+            Handler::QueryEcho => {
+                Ok(handlers::query_echo::serve(input))
+            }
+            #[cfg(feature="test-handlers")]
+            Handler::Panic => {
+                Ok(handlers::panic::serve(input))
+            }
             Handler::Proxy(ref settings) => {
                 Ok(handlers::proxy::serve(settings, input))
             }
@@ -52,9 +62,21 @@ impl Handler {
             Handler::StripWWWRedirect => {
                 Ok(handlers::redirect::strip_www_redirect(input))
             }
+            Handler::CanonicalRedirect(ref settings) => {
+                Ok(handlers::redirect::canonical_redirect(settings, input))
+            }
             Handler::SelfStatus(ref settings) => {
                 Ok(handlers::self_status::serve(settings, input))
             }
+            Handler::ByExtension(ref settings) => {
+                handlers::by_extension::serve(settings, input)
+            }
+            Handler::Discovery(ref settings) => {
+                Ok(handlers::discovery::serve(settings, input))
+            }
+            Handler::Health(ref settings) => {
+                Ok(handlers::health::serve(settings, input))
+            }
         }
     }
 }
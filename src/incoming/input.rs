@@ -1,29 +1,77 @@
 use std::sync::Arc;
 use std::net::SocketAddr;
+use std::time::Instant;
 
 use tk_http::server::Head;
 use tokio_core::reactor::Handle;
 
 use crate::config::Config;
 use crate::runtime::Runtime;
-use crate::incoming::{Debug, IntoContext};
+use crate::incoming::{Debug, RequestState, IntoContext, Context};
+use crate::incoming::query::{QueryParams, QueryError};
 use crate::request_id::RequestId;
 
 
 pub struct Input<'a> {
     pub addr: SocketAddr,
+    /// Port of the listening socket request is received on
+    pub local_port: Option<u16>,
     pub runtime: &'a Arc<Runtime>,
     pub config: &'a Arc<Config>,
     pub debug: Debug,
+    pub state: RequestState,
     pub headers: &'a Head<'a>,
+    /// Value of the `Host` header, or `default-host` for HTTP/1.0 requests
+    /// which have no `Host` header
+    pub host: &'a str,
     pub prefix: &'a str,
     pub suffix: &'a str,
     pub handle: &'a Handle,
     pub request_id: RequestId,
+    /// Request headers without the ones listed in `ingress-remove-headers`
+    /// (these are kept for trusted proxies)
+    pub ingress: &'a [(&'a str, &'a [u8])],
+    /// Peer is a proxy trusted by the authorizer of the route
+    /// (`accept-forwarded-headers-from` of `!SourceIp`)
+    pub trusted_peer: bool,
+    /// Time when connection exceeds `max-connection-age` (if enabled)
+    pub connection_deadline: Option<Instant>,
+    /// Time when the first bytes of the request were read
+    pub request_start: Instant,
+    /// Parsed query, filled on the first call of `query_params`
+    pub query: Option<Result<QueryParams, QueryError>>,
+}
+
+impl<'a> Input<'a> {
+    /// Request headers without the ones listed in `ingress-remove-headers`
+    ///
+    /// Handlers must use this instead of `headers.headers()`.
+    pub fn ingress_headers(&self)
+        -> impl Iterator<Item=(&'a str, &'a [u8])> + 'a
+    {
+        let ingress: &'a [(&'a str, &'a [u8])] = self.ingress;
+        ingress.iter().cloned()
+    }
+    /// Query parameters of the request
+    ///
+    /// Query is parsed on the first call and cached, so handlers which
+    /// don't need it don't pay for parsing.
+    pub fn query_params(&mut self) -> Result<&QueryParams, &QueryError> {
+        if self.query.is_none() {
+            let policy = self.config.duplicate_query_params;
+            self.query = Some(QueryParams::from_path(
+                self.headers.path().unwrap_or("/"))
+                .map(|mut params| {
+                    params.dedup(policy);
+                    params
+                }));
+        }
+        self.query.as_ref().expect("query is just parsed").as_ref()
+    }
 }
 
 impl<'a> IntoContext for Input<'a> {
-    fn into_context(self) -> (Arc<Config>, Debug) {
-        (self.config.clone(), self.debug)
+    fn into_context(self) -> Context {
+        (self.config.clone(), self.debug, self.state)
     }
 }
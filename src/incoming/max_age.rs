@@ -0,0 +1,306 @@
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::{Future, Async, Poll};
+use tokio_core::reactor::{Handle, Timeout};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use crate::metrics::{Counter};
+
+
+lazy_static! {
+    pub static ref EXPIRED: Counter = Counter::new();
+}
+
+/// Tracks age and requests in flight of a single connection
+/// (`max-connection-age` setting), and routes with `keep-alive=false`
+/// which close the connection after the response
+#[derive(Clone)]
+pub struct ConnectionAge(Arc<State>);
+
+struct State {
+    deadline: Option<Instant>,
+    expired: AtomicBool,
+    inflight: AtomicUsize,
+    /// Last write to the socket was not complete, so there is still data
+    /// in the output buffer of the connection
+    unflushed: AtomicBool,
+    /// Time when first bytes of the next request are read
+    request_start: Mutex<Option<Instant>>,
+}
+
+/// Marks request as being in flight, released on drop
+pub struct InflightGuard(Arc<State>);
+
+/// Socket wrapper which tracks whether output buffer is flushed
+/// and when the next request starts
+///
+/// Response is done (and request is not in flight any more) as soon as it
+/// is put into the output buffer, so connection must not be closed until
+/// the buffer is written to the socket. Buffer is always written until
+/// it's empty or socket would block, so incomplete last write means that
+/// some data is still there.
+pub struct AgedSocket<S> {
+    sock: S,
+    age: ConnectionAge,
+}
+
+/// Wraps connection future and resolves it when connection is older
+/// than `max-connection-age` (or has to be closed for `keep-alive=false`),
+/// has no requests in flight and the last response is flushed
+///
+/// Socket of the connection must be wrapped by `ConnectionAge::wrap`.
+pub struct MaxAge<F> {
+    inner: F,
+    timeout: Option<Timeout>,
+    age: ConnectionAge,
+}
+
+impl ConnectionAge {
+    pub fn new(max_age: Duration) -> ConnectionAge {
+        ConnectionAge::with_deadline(Some(Instant::now() + max_age))
+    }
+    /// Connection is never expired by age, but may still be closed by
+    /// `close_after_response`
+    pub fn unlimited() -> ConnectionAge {
+        ConnectionAge::with_deadline(None)
+    }
+    fn with_deadline(deadline: Option<Instant>) -> ConnectionAge {
+        ConnectionAge(Arc::new(State {
+            deadline: deadline,
+            expired: AtomicBool::new(false),
+            inflight: AtomicUsize::new(0),
+            unflushed: AtomicBool::new(false),
+            request_start: Mutex::new(None),
+        }))
+    }
+    /// Wraps connection socket to find out when the response is flushed
+    pub fn wrap<S>(&self, sock: S) -> AgedSocket<S> {
+        AgedSocket {
+            sock: sock,
+            age: self.clone(),
+        }
+    }
+    pub fn deadline(&self) -> Option<Instant> {
+        self.0.deadline
+    }
+    /// Closes connection when requests in flight are done (route has
+    /// `keep-alive=false`)
+    pub fn close_after_response(&self) {
+        self.0.expired.store(true, Ordering::SeqCst);
+    }
+    /// Time when the first bytes of the current request were read
+    ///
+    /// Returns `None` if request bytes were read while the previous
+    /// request was still in flight (i.e. pipelined requests)
+    pub fn take_request_start(&self) -> Option<Instant> {
+        self.0.request_start.lock().expect("request start lock").take()
+    }
+    pub fn request(&self) -> InflightGuard {
+        self.0.inflight.fetch_add(1, Ordering::SeqCst);
+        InflightGuard(self.0.clone())
+    }
+    fn expire(&self) {
+        EXPIRED.incr(1);
+        self.0.expired.store(true, Ordering::SeqCst);
+    }
+    fn idle_and_expired(&self) -> bool {
+        self.0.expired.load(Ordering::SeqCst) &&
+            self.0.inflight.load(Ordering::SeqCst) == 0
+    }
+    /// Connection is expired, idle and its last response is fully written
+    fn can_close(&self) -> bool {
+        self.idle_and_expired() && !self.0.unflushed.load(Ordering::SeqCst)
+    }
+}
+
+impl<S: Read> Read for AgedSocket<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let res = self.sock.read(buf);
+        if let Ok(bytes) = res {
+            if bytes > 0 && self.age.0.inflight.load(Ordering::SeqCst) == 0 {
+                let mut start = self.age.0.request_start.lock()
+                    .expect("request start lock");
+                if start.is_none() {
+                    *start = Some(Instant::now());
+                }
+            }
+        }
+        res
+    }
+}
+
+impl<S: Write> Write for AgedSocket<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let res = self.sock.write(buf);
+        let unflushed = match res {
+            Ok(bytes) => bytes < buf.len(),
+            Err(ref e) => e.kind() == io::ErrorKind::WouldBlock,
+        };
+        self.age.0.unflushed.store(unflushed, Ordering::SeqCst);
+        res
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.sock.flush()
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for AgedSocket<S> {}
+
+impl<S: AsyncWrite> AsyncWrite for AgedSocket<S> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.sock.shutdown()
+    }
+}
+
+impl InflightGuard {
+    /// Returns `true` if response must be sent with `Connection: close`
+    pub fn expired(&self) -> bool {
+        self.0.expired.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.0.inflight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<F> MaxAge<F> {
+    pub fn new(inner: F, age: &ConnectionAge, handle: &Handle) -> MaxAge<F> {
+        MaxAge {
+            inner: inner,
+            timeout: age.deadline().map(|deadline| {
+                Timeout::new_at(deadline, handle)
+                    .expect("can always add a timeout")
+            }),
+            age: age.clone(),
+        }
+    }
+}
+
+impl<F: Future<Item=()>> Future for MaxAge<F> {
+    type Item = ();
+    type Error = F::Error;
+    fn poll(&mut self) -> Poll<(), F::Error> {
+        if self.inner.poll()?.is_ready() {
+            return Ok(Async::Ready(()));
+        }
+        let fired = match self.timeout.as_mut().map(|t| t.poll()) {
+            Some(Ok(Async::Ready(()))) => true,
+            Some(Ok(Async::NotReady)) | None => false,
+            Some(Err(e)) => {
+                error!("Connection age timer error: {}", e);
+                true
+            }
+        };
+        if fired {
+            self.timeout = None;
+            self.age.expire();
+        }
+        // Request in flight wakes up this task when it's done, as the
+        // response future is polled by the connection itself, and so does
+        // the socket when the rest of the response can be written
+        if self.age.can_close() {
+            debug!("Closing expired connection");
+            return Ok(Async::Ready(()));
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Read, Write};
+    use std::time::Duration;
+    use super::ConnectionAge;
+
+    /// Accepts (or returns) at most that many bytes per write (read),
+    /// blocks if it's zero
+    struct Limited(usize);
+
+    impl Read for Limited {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            Ok(buf.len().min(self.0))
+        }
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            Ok(buf.len().min(self.0))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn inflight() {
+        let age = ConnectionAge::new(Duration::new(10, 0));
+        let g1 = age.request();
+        assert!(!g1.expired());
+        age.expire();
+        assert!(g1.expired());
+        assert!(!age.idle_and_expired());
+        drop(g1);
+        assert!(age.idle_and_expired());
+    }
+
+    #[test]
+    fn close_after_response() {
+        let age = ConnectionAge::unlimited();
+        assert!(age.deadline().is_none());
+        let g1 = age.request();
+        age.close_after_response();
+        assert!(g1.expired());
+        assert!(!age.idle_and_expired());
+        drop(g1);
+        assert!(age.idle_and_expired());
+    }
+
+    #[test]
+    fn unflushed() {
+        let age = ConnectionAge::unlimited();
+        let g1 = age.request();
+        age.close_after_response();
+        let mut sock = age.wrap(Limited(4));
+        // partial write
+        assert_eq!(sock.write(b"hello").unwrap(), 4);
+        drop(g1);
+        assert!(age.idle_and_expired());
+        assert!(!age.can_close());
+        sock.sock.0 = 0;
+        sock.write(b"o").unwrap_err();
+        assert!(!age.can_close());
+        sock.sock.0 = 4;
+        sock.write(b"o").unwrap();
+        assert!(age.can_close());
+    }
+
+    #[test]
+    fn request_start() {
+        let age = ConnectionAge::unlimited();
+        let mut sock = age.wrap(Limited(0));
+        let mut buf = [0u8; 16];
+        sock.read(&mut buf).unwrap_err();
+        assert!(age.take_request_start().is_none());
+        sock.sock.0 = 4;
+        sock.read(&mut buf).unwrap();
+        let start = age.take_request_start().expect("request started");
+        let g1 = age.request();
+        // pipelined request
+        sock.read(&mut buf).unwrap();
+        assert!(age.take_request_start().is_none());
+        drop(g1);
+        sock.read(&mut buf).unwrap();
+        assert!(age.take_request_start().expect("next request") >= start);
+    }
+}
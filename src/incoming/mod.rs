@@ -11,14 +11,26 @@ mod encoder;
 mod quick_reply;
 mod handler;
 mod authorizer;
+mod query;
+mod conn_limit;
+mod max_age;
+mod panic;
+mod route_stats;
+mod state;
 
 pub type Request<S> = Box<dyn Codec<S, ResponseFuture=Reply<S>>>;
 pub type Reply<S> = Box<dyn Future<Item=EncoderDone<S>, Error=Error>>;
 
 pub use self::debug::Debug;
+pub use self::state::RequestState;
 pub use tk_http::server::EncoderDone;
 pub use self::encoder::{Encoder, IntoContext, Context};
 pub use self::input::{Input};
+pub use self::conn_limit::ConnectionLimit;
+pub use self::max_age::{ConnectionAge, MaxAge};
+pub use self::panic::catch_connection_panic;
+pub use self::route_stats::{RouteStatsMap, route_metrics};
+pub use self::query::{QueryParams, QueryError, dedup_target};
 pub use self::quick_reply::reply;
 pub use self::router::Router;
 
@@ -32,5 +44,12 @@ pub fn metrics() -> List {
     vec![
         // obeys cantal-py.RequestTracker
         (Metric("frontend.incoming", "requests"), &*router::REQUESTS),
+        (Metric("frontend.incoming", "refused_connections_per_ip"),
+            &*conn_limit::REFUSED),
+        (Metric("frontend.incoming", "tracked_client_ips"),
+            &*conn_limit::TRACKED_IPS),
+        (Metric("frontend.incoming", "expired_connections"),
+            &*max_age::EXPIRED),
+        (Metric("frontend.incoming", "panics"), &*panic::PANICS),
     ]
 }
@@ -0,0 +1,93 @@
+use std::net::SocketAddr;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+use futures::Future;
+use tk_http::Status;
+use tk_http::server as http;
+
+use crate::default_error_page::error_page;
+use crate::incoming::{Encoder, Context, Reply};
+use crate::incoming::encoder::Rescue;
+use crate::metrics::{Counter};
+use crate::request_id::RequestId;
+
+
+lazy_static! {
+    pub static ref PANICS: Counter = Counter::new();
+}
+
+/// Runs handler code, returns `None` if it panics
+///
+/// Panic message is printed by the panic hook, here we only log which
+/// request it belongs to.
+pub fn catch_panic<T, F>(request_id: RequestId, f: F) -> Option<T>
+    where F: FnOnce() -> T,
+{
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            PANICS.incr(1);
+            error!("Handler panicked while serving request {}", request_id);
+            None
+        }
+    }
+}
+
+/// Runs the closure of `incoming::reply`, sends `500` if it panics
+///
+/// If the closure has already started the response, there is no way to
+/// send an error page, so the panic is passed to `catch_connection_panic`.
+pub fn catch_reply_panic<S: 'static, F>(e: http::Encoder<S>, ctx: Context,
+    f: F)
+    -> Reply<S>
+    where F: FnOnce(Encoder<S>) -> Reply<S>,
+{
+    let config = ctx.0.clone();
+    let rescue = Rescue::new();
+    let enc = Encoder::rescuable(e, ctx, &rescue);
+    match catch_unwind(AssertUnwindSafe(move || f(enc))) {
+        Ok(reply) => reply,
+        Err(panic) => match rescue.take() {
+            Some((e, debug, state)) => {
+                PANICS.incr(1);
+                error!("Handler panicked while serving request {}",
+                    state.request_id());
+                let enc = Encoder::new(e, (config, debug, state));
+                Box::new(error_page(Status::InternalServerError, enc))
+            }
+            None => resume_unwind(panic),
+        }
+    }
+}
+
+/// Closes the connection if anything panics while it's polled
+///
+/// Response is already being written at this point, so there is no way
+/// to send an error page, but other connections are not affected.
+pub fn catch_connection_panic<F>(conn: F, addr: SocketAddr)
+    -> impl Future<Item=(), Error=()>
+    where F: Future<Item=(), Error=()>,
+{
+    AssertUnwindSafe(conn).catch_unwind().then(move |res| match res {
+        Ok(res) => res,
+        Err(_) => {
+            PANICS.incr(1);
+            error!("Panic while serving connection from {}, closing", addr);
+            Err(())
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::request_id;
+    use super::catch_panic;
+
+    #[test]
+    fn panic() {
+        assert_eq!(catch_panic(request_id::new(), || 1), Some(1));
+        assert_eq!(catch_panic(request_id::new(), || -> u32 {
+            panic!("test panic")
+        }), None);
+    }
+}
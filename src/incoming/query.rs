@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::config::DuplicateQueryParams;
+
+
+quick_error! {
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum QueryError {
+        BadEscape(part: String) {
+            description("invalid percent-encoding in query")
+            display("invalid percent-encoding in query: {:?}", part)
+        }
+        InvalidUtf8(part: String) {
+            description("query parameter is not valid utf-8")
+            display("query parameter is not valid utf-8: {:?}", part)
+        }
+    }
+}
+
+/// Percent-decoded query parameters
+///
+/// Every key maps to all its values in the order they were present in the
+/// query string.
+#[derive(Debug, Default)]
+pub struct QueryParams {
+    map: HashMap<String, Vec<String>>,
+}
+
+impl QueryParams {
+    /// Parses query string (the part after `?` without fragment)
+    pub fn parse(query: &str) -> Result<QueryParams, QueryError> {
+        let mut map = HashMap::<_, Vec<_>>::new();
+        for pair in query.split('&') {
+            if pair.is_empty() {
+                continue;
+            }
+            let mut kv = pair.splitn(2, '=');
+            let key = decode(kv.next().unwrap_or(""))?;
+            let value = decode(kv.next().unwrap_or(""))?;
+            map.entry(key).or_default().push(value);
+        }
+        Ok(QueryParams { map })
+    }
+    /// Parses query from the request target, i.e. `/path?query#fragment`
+    pub fn from_path(path: &str) -> Result<QueryParams, QueryError> {
+        let path = match path.find('#') {
+            Some(idx) => &path[..idx],
+            None => path,
+        };
+        match path.find('?') {
+            Some(idx) => QueryParams::parse(&path[idx+1..]),
+            None => Ok(QueryParams::default()),
+        }
+    }
+    /// Returns first value of the parameter
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.map.get(key)
+            .and_then(|x| x.first())
+            .map(|x| &x[..])
+    }
+    /// Returns all values of the parameter
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.map.get(key).map(|x| &x[..]).unwrap_or(&[])
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// All parameters, values are in the order of the query string
+    pub fn as_map(&self) -> &HashMap<String, Vec<String>> {
+        &self.map
+    }
+    /// Keeps single value of every parameter according to the policy
+    pub fn dedup(&mut self, policy: DuplicateQueryParams) {
+        use crate::config::DuplicateQueryParams::*;
+        for values in self.map.values_mut() {
+            match policy {
+                all => {}
+                first => values.truncate(1),
+                last => {
+                    let start = values.len().saturating_sub(1);
+                    values.drain(..start);
+                }
+            }
+        }
+    }
+}
+
+/// Removes repeated parameters from the request target, keeping either
+/// first or last occurence of every key, in place
+///
+/// Keys are compared after percent-decoding, values are forwarded as is.
+pub fn dedup_target(path: &str, policy: DuplicateQueryParams) -> String {
+    let (path, query) = match path.find('?') {
+        Some(idx) if policy != DuplicateQueryParams::all => {
+            (&path[..idx], &path[idx+1..])
+        }
+        _ => return path.to_string(),
+    };
+    let key = |pair: &str| {
+        let raw = pair.splitn(2, '=').next().unwrap_or("");
+        decode(raw).unwrap_or_else(|_| raw.to_string())
+    };
+    let pairs = query.split('&').filter(|p| !p.is_empty())
+        .collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    let mut keep = vec![false; pairs.len()];
+    // for the `last` policy walk backwards, so last occurence is seen first
+    let indices: Box<dyn Iterator<Item=usize>> = match policy {
+        DuplicateQueryParams::last => Box::new((0..pairs.len()).rev()),
+        _ => Box::new(0..pairs.len()),
+    };
+    for idx in indices {
+        keep[idx] = seen.insert(key(pairs[idx]));
+    }
+    let mut result = String::with_capacity(path.len() + query.len() + 1);
+    result.push_str(path);
+    result.push('?');
+    let mut first = true;
+    for (pair, keep) in pairs.iter().zip(keep) {
+        if keep {
+            if !first {
+                result.push('&');
+            }
+            result.push_str(pair);
+            first = false;
+        }
+    }
+    return result;
+}
+
+fn decode(part: &str) -> Result<String, QueryError> {
+    let mut buf = Vec::with_capacity(part.len());
+    let mut chariter = part.as_bytes().iter();
+    while let Some(c) = chariter.next() {
+        match *c {
+            b'%' => {
+                let h = chariter.next().and_then(|&x| from_hex(x));
+                let l = chariter.next().and_then(|&x| from_hex(x));
+                match (h, l) {
+                    (Some(h), Some(l)) => buf.push((h << 4) | l),
+                    _ => return Err(QueryError::BadEscape(part.to_string())),
+                }
+            }
+            b'+' => buf.push(b' '),
+            c => buf.push(c),
+        }
+    }
+    String::from_utf8(buf)
+        .map_err(|_| QueryError::InvalidUtf8(part.to_string()))
+}
+
+fn from_hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b & 0x0f),
+        b'a'..=b'f' | b'A'..=b'F' => Some((b & 0x0f) + 9),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::DuplicateQueryParams::*;
+    use super::{QueryParams, QueryError, dedup_target};
+
+    #[test]
+    fn simple() {
+        let q = QueryParams::from_path("/list?page=2").unwrap();
+        assert_eq!(q.get("page"), Some("2"));
+        assert_eq!(q.get("size"), None);
+        assert!(!q.contains_key("size"));
+    }
+
+    #[test]
+    fn repeated_keys() {
+        let q = QueryParams::parse("tag=a&page=2&tag=b").unwrap();
+        assert_eq!(q.get("tag"), Some("a"));
+        assert_eq!(q.get_all("tag"), &["a".to_string(), "b".to_string()]);
+        assert_eq!(q.get_all("page"), &["2".to_string()]);
+        assert!(q.get_all("none").is_empty());
+    }
+
+    #[test]
+    fn dedup_params() {
+        let mut q = QueryParams::parse("a=1&b=2&a=3&a=4").unwrap();
+        q.dedup(all);
+        assert_eq!(q.get_all("a").len(), 3);
+        q.dedup(last);
+        assert_eq!(q.get_all("a"), &["4".to_string()]);
+        let mut q = QueryParams::parse("a=1&b=2&a=3&a=4").unwrap();
+        q.dedup(first);
+        assert_eq!(q.get_all("a"), &["1".to_string()]);
+        assert_eq!(q.get_all("b"), &["2".to_string()]);
+    }
+
+    #[test]
+    fn dedup_targets() {
+        assert_eq!(dedup_target("/x?a=1&b=2&a=3", all), "/x?a=1&b=2&a=3");
+        assert_eq!(dedup_target("/x?a=1&b=2&a=3", first), "/x?a=1&b=2");
+        assert_eq!(dedup_target("/x?a=1&b=2&a=3", last), "/x?b=2&a=3");
+        assert_eq!(dedup_target("/x?a=1&%61=2", first), "/x?a=1");
+        assert_eq!(dedup_target("/x?a&&a=2", last), "/x?a=2");
+        assert_eq!(dedup_target("/x", last), "/x");
+        assert_eq!(dedup_target("/x?", first), "/x?");
+    }
+
+    #[test]
+    fn decoding() {
+        let q = QueryParams::parse("q=hello+world%21&%6B=%D1%8F&flag")
+            .unwrap();
+        assert_eq!(q.get("q"), Some("hello world!"));
+        assert_eq!(q.get("k"), Some("я"));
+        assert_eq!(q.get("flag"), Some(""));
+    }
+
+    #[test]
+    fn no_query() {
+        assert!(QueryParams::from_path("/").unwrap().is_empty());
+        assert!(QueryParams::from_path("/x?").unwrap().is_empty());
+        assert!(QueryParams::from_path("/x#?a=b").unwrap().is_empty());
+        let q = QueryParams::from_path("/x?a=b#c=d").unwrap();
+        assert_eq!(q.get("a"), Some("b"));
+        assert!(!q.contains_key("c"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(QueryParams::parse("a=%zz").unwrap_err(),
+            QueryError::BadEscape("%zz".to_string()));
+        assert_eq!(QueryParams::parse("a=%2").unwrap_err(),
+            QueryError::BadEscape("%2".to_string()));
+        assert_eq!(QueryParams::parse("a=%ff").unwrap_err(),
+            QueryError::InvalidUtf8("%ff".to_string()));
+    }
+}
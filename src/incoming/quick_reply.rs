@@ -1,15 +1,13 @@
-use std::sync::Arc;
-
 use futures::Async;
 use tk_http::server::{Error, Codec, RecvMode};
 use tk_http::server as http;
 
-use crate::config::Config;
-use crate::incoming::{Request, Reply, Encoder, IntoContext, Debug};
+use crate::incoming::{Request, Reply, Encoder, IntoContext, Context};
+use crate::incoming::panic::catch_reply_panic;
 
 
 pub struct QuickReply<F> {
-    inner: Option<(F, Arc<Config>, Debug)>,
+    inner: Option<(F, Context)>,
 }
 
 
@@ -18,13 +16,12 @@ pub fn reply<F, C, S: 'static>(ctx: C, f: F)
     where F: FnOnce(Encoder<S>) -> Reply<S> + 'static,
           C: IntoContext,
 {
-    let (cfg, debug) = ctx.into_context();
     Box::new(QuickReply {
-        inner: Some((f, cfg, debug)),
+        inner: Some((f, ctx.into_context())),
     })
 }
 
-impl<F, S> Codec<S> for QuickReply<F>
+impl<F, S: 'static> Codec<S> for QuickReply<F>
     where F: FnOnce(Encoder<S>) -> Reply<S>,
 {
     type ResponseFuture = Reply<S>;
@@ -39,8 +36,8 @@ impl<F, S> Codec<S> for QuickReply<F>
         Ok(Async::Ready(0))
     }
     fn start_response(&mut self, e: http::Encoder<S>) -> Reply<S> {
-        let (func, config, debug) = self.inner.take()
+        let (func, context) = self.inner.take()
             .expect("start response called once");
-        func(Encoder::new(e, (config, debug)))
+        catch_reply_panic(e, context, func)
     }
 }
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use futures::Async;
+use libcantal::{Collection, Visitor};
+use tk_bufstream::{ReadBuf, WriteBuf};
+use tk_http::server::{Codec, Error, RecvMode};
+use tk_http::server as http;
+
+use crate::config::handlers::Handler;
+use crate::incoming::{Request, Reply, Transport};
+use crate::intern::HandlerName;
+use crate::metrics::Counter;
+
+
+/// Request counters of a single route, labeled by handler name
+///
+/// Only bodies are counted, headers and websocket frames are not.
+#[derive(Clone, Debug)]
+pub struct RouteStats(Arc<Stats>);
+
+#[derive(Debug)]
+struct Stats {
+    name: HandlerName,
+    requests: Counter,
+    request_bytes: Counter,
+    response_bytes: Counter,
+}
+
+#[derive(Clone)]
+pub struct RouteStatsMap {
+    routes: Arc<RwLock<HashMap<HandlerName, RouteStats>>>,
+}
+
+/// Wraps a request codec to count request body bytes
+pub struct Counted<S> {
+    inner: Request<S>,
+    stats: RouteStats,
+}
+
+impl RouteStats {
+    fn new(name: &HandlerName) -> RouteStats {
+        RouteStats(Arc::new(Stats {
+            name: name.clone(),
+            requests: Counter::new(),
+            request_bytes: Counter::new(),
+            response_bytes: Counter::new(),
+        }))
+    }
+    pub fn add_request(&self) {
+        self.0.requests.incr(1);
+    }
+    pub fn add_request_bytes(&self, n: usize) {
+        self.0.request_bytes.incr(n as u64);
+    }
+    pub fn add_response_bytes(&self, n: usize) {
+        self.0.response_bytes.incr(n as u64);
+    }
+}
+
+impl Collection for RouteStats {
+    fn visit<'x>(&'x self, v: &mut dyn Visitor<'x>) {
+        use crate::metrics::Metric as M;
+        let ref s = self.0;
+        let g = format!("frontend.routes.{}", s.name);
+        v.metric(&M(&g, "requests"), &s.requests);
+        v.metric(&M(&g, "request_bytes"), &s.request_bytes);
+        v.metric(&M(&g, "response_bytes"), &s.response_bytes);
+    }
+}
+
+impl RouteStatsMap {
+    pub fn new() -> RouteStatsMap {
+        RouteStatsMap {
+            routes: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+    /// Adds counters for new handlers and drops ones for removed handlers
+    ///
+    /// Counters of handlers that are kept in config are preserved.
+    pub fn update(&self, handlers: &HashMap<HandlerName, Handler>) {
+        let mut routes = self.routes.write()
+            .expect("route stats not poisoned");
+        routes.retain(|name, _| handlers.contains_key(name));
+        for name in handlers.keys() {
+            if !routes.contains_key(name) {
+                routes.insert(name.clone(), RouteStats::new(name));
+            }
+        }
+    }
+    pub fn get(&self, name: &HandlerName) -> Option<RouteStats> {
+        self.routes.read().expect("route stats not poisoned")
+            .get(name).cloned()
+    }
+}
+
+impl<S> Counted<S> {
+    pub fn new(inner: Request<S>, stats: RouteStats) -> Counted<S> {
+        stats.add_request();
+        Counted { inner, stats }
+    }
+}
+
+impl<S: Transport> Codec<S> for Counted<S> {
+    type ResponseFuture = Reply<S>;
+    fn recv_mode(&mut self) -> RecvMode {
+        self.inner.recv_mode()
+    }
+    fn data_received(&mut self, data: &[u8], end: bool)
+        -> Result<Async<usize>, Error>
+    {
+        let res = self.inner.data_received(data, end);
+        if let Ok(Async::Ready(n)) = res {
+            self.stats.add_request_bytes(n);
+        }
+        res
+    }
+    fn start_response(&mut self, e: http::Encoder<S>) -> Reply<S> {
+        self.inner.start_response(e)
+    }
+    fn hijack(&mut self, write_buf: WriteBuf<S>, read_buf: ReadBuf<S>) {
+        self.inner.hijack(write_buf, read_buf)
+    }
+}
+
+pub fn route_metrics(map: &RouteStatsMap) -> Vec<RouteStats> {
+    map.routes.read().expect("route stats not poisoned")
+        .values()
+        .cloned()
+        .collect()
+}
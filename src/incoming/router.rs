@@ -1,14 +1,22 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 
+use futures::future::ok;
 use tokio_core::reactor::Handle;
-use tk_http::Status;
+use tk_http::{Status, Version};
 use tk_http::server::{Dispatcher, Error as ServerError, Head};
 
+use crate::config::Config;
 use crate::runtime::Runtime;
-use crate::incoming::{Request, Debug, Input, Transport};
-use crate::routing::{parse_host, route};
-use crate::default_error_page::serve_error_page;
+use crate::incoming::{Request, Debug, RequestState, Input, Transport};
+use crate::incoming::ConnectionAge;
+use crate::routing::{parse_host, route, known_method};
+use crate::default_error_page::{serve_error_page, error_page_with_headers};
+use crate::incoming::reply;
+use crate::incoming::route_stats::Counted;
+use crate::incoming::panic::catch_panic;
 use crate::request_id;
 
 use crate::metrics::{Counter};
@@ -20,24 +28,35 @@ lazy_static! {
     pub static ref REQUESTS: Counter = Counter::new();
 }
 
+/// Reported in `Allow` for `OPTIONS *`, actual set of methods depends on
+/// the handler
+const SERVER_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+
 
 pub struct Router {
     addr: SocketAddr,
+    local_port: Option<u16>,
+    age: ConnectionAge,
     runtime: Arc<Runtime>,
     handle: Handle,
 }
 
 pub enum Error {
-    Page(Status, Debug),
+    Page(Status, Debug, RequestState),
+    WarmingUp(Debug, RequestState),
+    ServerOptions(Debug, RequestState),
     Fallback(ServerError),
 }
 
 impl Router {
-    pub fn new(addr: SocketAddr, runtime: Arc<Runtime>, handle: Handle)
+    pub fn new(addr: SocketAddr, local_port: Option<u16>,
+        age: ConnectionAge, runtime: Arc<Runtime>, handle: Handle)
         -> Router
     {
         Router {
             addr: addr,
+            local_port: local_port,
+            age: age,
             runtime: runtime,
             handle: handle,
         }
@@ -46,6 +65,18 @@ impl Router {
 
 impl Router {
 
+    fn request_state(&self, headers: &Head, request_id: RequestId,
+        cfg: &Arc<Config>)
+        -> RequestState
+    {
+        let mut state = RequestState::new(request_id, cfg);
+        if cfg.debug_logging {
+            state.set_log_record(logging::Record::new(&self.runtime,
+                self.addr, headers, request_id));
+        }
+        return state;
+    }
+
     fn start_request<S: Transport>(&mut self, headers: &Head,
         request_id: RequestId)
         -> Result<Request<S>, Error>
@@ -56,14 +87,55 @@ impl Router {
         // Keep config same while processing a single request
         let cfg = self.runtime.config.get();
         let mut debug = Debug::new(headers, request_id, &cfg);
+        let mut state = self.request_state(headers, request_id, &cfg);
+        let request_start = self.age.take_request_start()
+            .unwrap_or_else(Instant::now);
+        state.set_inflight(self.age.request());
+        state.trace("accepted", format_args!("{} {} from {}",
+            headers.method(), headers.path().unwrap_or("*"), self.addr));
 
-        // No path means either CONNECT host, or OPTIONS *
-        // in both cases we use root route for the domain to make decision
+        // health checks are served during warmup, so request is routed
+        let warming_up = !self.runtime.ready.load(Ordering::SeqCst);
+
+        if !valid_transfer_encoding(headers) {
+            return Err(Page(Status::NotImplemented, debug, state));
+        }
+
+        if let Some(limit) = cfg.max_header_value_size {
+            if headers.all_headers().iter().any(|h| h.value.len() > limit) {
+                return Err(Page(Status::RequestHeaderFieldsTooLarge,
+                    debug, state));
+            }
+        }
+
+        // No path means either CONNECT host, OPTIONS * or some other
+        // method with authority or asterisk form. Handlers rely on the path,
+        // so server-wide OPTIONS is answered right here and everything else
+        // is a bad request.
         //
         // TODO(tailhook) strip ?, #, ; from path
-        let path = headers.path().unwrap_or("/");
+        let path = match headers.path() {
+            Some(path) => path,
+            None if headers.method() == "OPTIONS" => {
+                return Err(ServerOptions(debug, state));
+            }
+            None if !known_method(headers.method(), &cfg.extension_methods)
+            => {
+                return Err(Page(Status::NotImplemented, debug, state));
+            }
+            None => return Err(Page(Status::BadRequest, debug, state)),
+        };
 
-        let parsed_host = headers.host().map(parse_host);
+        // HTTP/1.1 requires Host header (RFC 7230, section 5.4), but
+        // HTTP/1.0 clients might not send it, those are routed as
+        // `default-host` (if configured)
+        let host = match headers.host() {
+            Some(host) => Some(host),
+            None if headers.version() == Version::Http10 => {
+                cfg.default_host.as_ref().map(|x| &x[..])
+            }
+            None => return Err(Page(Status::BadRequest, debug, state)),
+        };
 
         /*
         if let Some((auth, pref, suf)) = authorization_route {
@@ -82,37 +154,116 @@ impl Router {
         };
         */
 
-        let matched_route = parsed_host
-            .and_then(|host| route(host, &path, &cfg.routing));
+        let matched_route = host
+            .and_then(|host| route(parse_host(host), &path, &cfg.routing)
+                .map(|(route, p, s)| (host, route, p, s)));
 
-        let (route, pref, suf) = if let Some((route, p, s)) = matched_route {
-            (route, p, s)
+        let (host, route, pref, suf) = if let Some(m) = matched_route {
+            m
+        } else if warming_up {
+            return Err(WarmingUp(debug, state));
         } else {
-            return Err(Page(Status::NotFound, debug));
+            return Err(Page(Status::NotFound, debug, state));
         };
+        if !route.serves_port(self.local_port) {
+            return Err(Page(Status::NotFound, debug, state));
+        }
+        if !known_method(headers.method(), &cfg.extension_methods) &&
+            !route.handler.passes_any_method()
+        {
+            return Err(Page(Status::NotImplemented, debug, state));
+        }
+        if warming_up && !route.handler.serves_during_warmup() {
+            return Err(WarmingUp(debug, state));
+        }
+        // headers from `ingress-remove-headers` are stripped here, so
+        // handlers never see them, unless the peer is a proxy trusted by
+        // the authorizer of the route
+        let trusted_peer = route.authorizer.trusts_peer(self.addr.ip(), &cfg);
+        let remove = if trusted_peer {
+            &[][..]
+        } else {
+            &cfg.ingress_remove_headers[..]
+        };
+        let ingress = headers.headers()
+            .filter(|&(name, _)| {
+                !remove.iter().any(|h| h.eq_ignore_ascii_case(name))
+            })
+            .collect::<Vec<_>>();
         debug.set_route(route);
+        state.set_log_format(route.log_format.clone());
+        state.trace("routed", &route.handler_name);
+        let stats = self.runtime.route_stats.get(&route.handler_name);
+        if let Some(ref stats) = stats {
+            state.set_route_stats(stats.clone());
+        }
+        if let Some(ref deprecation) = route.deprecation {
+            state.set_deprecation(deprecation.clone());
+        }
+        if !route.keep_alive {
+            self.age.close_after_response();
+        }
 
         let mut inp = Input {
             addr: self.addr,
+            local_port: self.local_port,
             runtime: &self.runtime,
             config: &cfg,
             debug: debug,
+            state: state,
             headers: headers,
+            host: host,
             prefix: pref,
             suffix: suf,
             handle: &self.handle,
             request_id: request_id,
+            ingress: &ingress,
+            trusted_peer: trusted_peer,
+            connection_deadline: self.age.deadline(),
+            request_start: request_start,
+            query: None,
         };
 
         match route.authorizer.check(&mut inp) {
             Ok(true) => {}
             Ok(false) => {
-                return Err(Page(Status::Forbidden, inp.debug));
+                return Err(Page(Status::Forbidden, inp.debug, inp.state));
             }
             Err(e) => return Err(Fallback(e)),
         }
 
-        route.handler.serve(inp).map_err(Fallback)
+        inp.state.trace("handler-start", "");
+        let handler = &route.handler;
+        let codec = match catch_panic(request_id, || handler.serve(inp)) {
+            Some(codec) => codec.map_err(Fallback)?,
+            None => {
+                // debug info and request state are lost with the input
+                let debug = Debug::new(headers, request_id, &cfg);
+                let state = self.request_state(headers, request_id, &cfg);
+                return Err(Page(Status::InternalServerError, debug, state));
+            }
+        };
+        match stats {
+            Some(stats) => Ok(Box::new(Counted::new(codec, stats))),
+            None => Ok(codec),
+        }
+    }
+}
+
+/// Checks that request body uses no transfer codings other than `chunked`
+///
+/// We don't decode any other codings, and if `chunked` isn't the final one
+/// there is no way to know where the body ends (RFC 7230, section 3.3.3).
+fn valid_transfer_encoding(headers: &Head) -> bool {
+    let mut codings = headers.all_headers().iter()
+        .filter(|h| h.name.eq_ignore_ascii_case("Transfer-Encoding"))
+        .flat_map(|h| h.value.split(|&c| c == b','))
+        .map(|c| std::str::from_utf8(c).unwrap_or("").trim())
+        .filter(|c| !c.is_empty());
+    match (codings.next(), codings.next()) {
+        (None, _) => true,
+        (Some(c), None) => c.eq_ignore_ascii_case("chunked"),
+        (Some(_), Some(_)) => false,
     }
 }
 
@@ -123,34 +274,26 @@ impl<S: Transport> Dispatcher<S> for Router {
     {
         let request_id = request_id::new();
         match self.start_request(headers, request_id) {
-            Ok(x) => {
-                // TODO(tailhook) request is not done yet, just a fake
-                logging::log(&self.runtime,
-                    logging::http::FakePage {
-                        request: logging::http::EarlyRequest {
-                            addr: self.addr,
-                            head: headers,
-                            request_id: request_id,
-                        },
-                        response: logging::http::FakeResponse {
-                        },
-                    });
-                Ok(x)
-            }
-            Err(Error::Page(status, debug)) => {
-                logging::log(&self.runtime,
-                    logging::http::EarlyError {
-                        request: logging::http::EarlyRequest {
-                            addr: self.addr,
-                            head: headers,
-                            request_id: request_id,
-                        },
-                        response: logging::http::EarlyResponse {
-                            status: status.into(),
-                        }
-                    });
+            Ok(x) => Ok(x),
+            Err(Error::Page(status, debug, state)) => {
                 Ok(serve_error_page(status,
-                    (self.runtime.config.get(), debug)))
+                    (self.runtime.config.get(), debug, state)))
+            }
+            Err(Error::WarmingUp(debug, state)) => {
+                Ok(reply((self.runtime.config.get(), debug, state), |e| {
+                    Box::new(error_page_with_headers(
+                        Status::ServiceUnavailable,
+                        &[("Retry-After", "1")], e))
+                }))
+            }
+            Err(Error::ServerOptions(debug, state)) => {
+                Ok(reply((self.runtime.config.get(), debug, state), |mut e| {
+                    e.status(Status::Ok);
+                    e.add_header("Allow", SERVER_METHODS);
+                    e.add_length(0);
+                    e.done_headers();
+                    Box::new(ok(e.done()))
+                }))
             }
             // Maybe return bad request?
             Err(Error::Fallback(e)) => Err(e),
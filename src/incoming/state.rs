@@ -0,0 +1,99 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::config::deprecation::Deprecation;
+use crate::incoming::max_age::InflightGuard;
+use crate::incoming::route_stats::RouteStats;
+use crate::intern::LogFormatName;
+use crate::logging::{Record, trace};
+use crate::request_id::RequestId;
+
+/// Per-request state that travels from router to the response encoder
+///
+/// Unlike `Debug`, which is only filled when `debug-routing` is enabled,
+/// this is used for every request.
+pub struct RequestState {
+    request_id: RequestId,
+    route_stats: Option<RouteStats>,
+    deprecation: Option<Arc<Deprecation>>,
+    inflight: Option<InflightGuard>,
+    /// Set when `debug-tracing` is enabled
+    trace: Option<RequestId>,
+    /// Set when `debug-logging` is enabled, written when response is done
+    log: Option<Box<Record>>,
+    status: u16,
+}
+
+impl RequestState {
+    pub fn new(request_id: RequestId, cfg: &Arc<Config>) -> RequestState {
+        RequestState {
+            request_id: request_id,
+            route_stats: None,
+            deprecation: None,
+            inflight: None,
+            trace: if cfg.debug_tracing { Some(request_id) } else { None },
+            log: None,
+            status: 0,
+        }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    pub fn set_log_record(&mut self, record: Record) {
+        self.log = Some(Box::new(record));
+    }
+
+    /// Sets log format of the route (if overridden)
+    pub fn set_log_format(&mut self, format: Option<LogFormatName>) {
+        if let Some(ref mut record) = self.log {
+            record.set_format(format);
+        }
+    }
+
+    pub fn set_status(&mut self, status: u16) {
+        self.status = status;
+    }
+
+    /// Writes access log record, called when response is done
+    pub fn finish(&mut self) {
+        if let Some(record) = self.log.take() {
+            record.finish(self.status);
+        }
+    }
+
+    /// Logs request lifecycle event if `debug-tracing` is enabled
+    pub fn trace<D: Display>(&self, event: &str, details: D) {
+        if let Some(request_id) = self.trace {
+            trace::event(request_id, event, details);
+        }
+    }
+
+    pub fn set_route_stats(&mut self, stats: RouteStats) {
+        self.route_stats = Some(stats);
+    }
+
+    pub fn get_route_stats(&self) -> Option<&RouteStats> {
+        self.route_stats.as_ref()
+    }
+
+    pub fn set_deprecation(&mut self, deprecation: Arc<Deprecation>) {
+        self.deprecation = Some(deprecation);
+    }
+
+    pub fn get_deprecation(&self) -> Option<&Deprecation> {
+        self.deprecation.as_ref().map(|x| &**x)
+    }
+
+    pub fn set_inflight(&mut self, guard: InflightGuard) {
+        self.inflight = Some(guard);
+    }
+
+    /// Connection is older than `max-connection-age` (or route has
+    /// `keep-alive=false`) and must be closed after this response
+    pub fn connection_expired(&self) -> bool {
+        self.inflight.as_ref().map(|g| g.expired()).unwrap_or(false)
+    }
+}
@@ -16,6 +16,7 @@ mod private {
     pub struct AuthorizerValidator;
     pub struct NetworkValidator;
     pub struct LogFormatValidator;
+    pub struct DeprecationValidator;
 }
 use self::private::*;
 
@@ -36,6 +37,7 @@ pub type LdapUpstream = Symbol<LdapValidator>;
 pub type Authorizer = Symbol<AuthorizerValidator>;
 pub type Network = Symbol<NetworkValidator>;
 pub type LogFormatName = Symbol<LogFormatValidator>;
+pub type DeprecationName = Symbol<DeprecationValidator>;
 
 quick_error! {
     #[derive(Debug)]
@@ -109,6 +111,19 @@ impl Validator for LogFormatValidator {
     }
 }
 
+impl Validator for DeprecationValidator {
+    type Err = BadIdent;
+    fn validate_symbol(val: &str) -> Result<(), Self::Err> {
+        if !valid_ident(val) {
+            return Err(BadIdent::InvalidChar);
+        }
+        Ok(())
+    }
+    fn display(value: &Symbol<Self>, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "depr{:?}", value.as_ref())
+    }
+}
+
 impl Validator for AuthorizerValidator {
     type Err = BadIdent;
     fn validate_symbol(val: &str) -> Result<(), Self::Err> {
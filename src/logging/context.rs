@@ -1,5 +1,21 @@
+use std::time::Duration;
+
 pub use trimmer::Context;
 
+use crate::request_id::RequestId;
+
 pub trait AsContext {
     fn as_context(&self) -> Context;
+    /// Request id used for sampling, records without one are never skipped
+    fn request_id(&self) -> Option<RequestId> {
+        None
+    }
+    /// Status code of the response, if it's already known
+    fn status_code(&self) -> Option<u16> {
+        None
+    }
+    /// Time spent serving the request, if it's already done
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
 }
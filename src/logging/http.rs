@@ -1,115 +1,149 @@
 use std::fmt;
 use std::net::SocketAddr;
+use std::time::Duration;
 
-use tk_http::Status;
+use tk_http::Version;
 use tk_http::server::Head;
-use trimmer::{Variable, Var, DataError, Output};
+use trimmer::{Variable, Var, DataError, Output, Template};
 
 use crate::request_id::RequestId;
 use crate::logging::context::{Context, AsContext};
 
 
-pub struct EarlyRequest<'a> {
+/// Attributes of `request` available in log formats
+const REQUEST_ATTRS: &[&str] = &[
+    "client_ip", "host", "method", "path", "version", "request_id"];
+/// Attributes of `response` available in log formats
+const RESPONSE_ATTRS: &[&str] = &["status_code"];
+
+
+/// Request attributes kept until the response is done
+pub struct Request {
     pub addr: SocketAddr,
-    pub head: &'a Head<'a>,
+    pub host: String,
+    pub method: String,
+    pub path: String,
+    pub version: Version,
     pub request_id: RequestId,
 }
 
 #[derive(Debug)]
-pub struct EarlyResponse {
-    pub status: Status,
-}
-
-#[derive(Debug)]
-pub struct FakeResponse {
+pub struct Response {
+    pub status: u16,
+    pub duration: Duration,
 }
 
-// Temporary page object
-pub struct FakePage<'a> {
-    pub request: EarlyRequest<'a>,
-    pub response: FakeResponse,
-}
-
-pub struct EarlyError<'a> {
-    pub request: EarlyRequest<'a>,
-    pub response: EarlyResponse,
+pub struct Page<'a> {
+    pub request: &'a Request,
+    pub response: Response,
 }
 
 #[derive(Debug)]
 pub struct Display<D: fmt::Display + fmt::Debug>(D);
 
-impl<'a> AsContext for EarlyError<'a> {
-    fn as_context(&self) -> Context {
-        let mut ctx = Context::new();
-        ctx.set("request", &self.request);
-        ctx.set("response", &self.response);
-        ctx
+/// Placeholder having every attribute from the list, used to check
+/// log formats for unknown variables
+#[derive(Debug)]
+struct Sample(&'static [&'static str]);
+
+/// Renders template with placeholder values, so formats that refer to
+/// unknown variables are rejected when config is read, rather than
+/// failing on every request
+pub fn check_format(template: &Template) -> Result<(), String> {
+    let request = Sample(REQUEST_ATTRS);
+    let response = Sample(RESPONSE_ATTRS);
+    let mut ctx = Context::new();
+    ctx.set("request", &request);
+    ctx.set("response", &response);
+    template.render(&ctx).map(|_| ()).map_err(|e| format!("{:?}", e))
+}
+
+impl Request {
+    pub fn new(addr: SocketAddr, head: &Head, request_id: RequestId)
+        -> Request
+    {
+        Request {
+            addr: addr,
+            host: head.host().unwrap_or("-").to_string(),
+            method: head.method().to_string(),
+            path: head.path().unwrap_or("").to_string(),
+            version: head.version(),
+            request_id: request_id,
+        }
     }
 }
 
-impl<'a> AsContext for FakePage<'a> {
+impl<'a> AsContext for Page<'a> {
     fn as_context(&self) -> Context {
         let mut ctx = Context::new();
-        ctx.set("request", &self.request);
+        ctx.set("request", self.request);
         ctx.set("response", &self.response);
         ctx
     }
+    fn request_id(&self) -> Option<RequestId> {
+        Some(self.request.request_id)
+    }
+    fn status_code(&self) -> Option<u16> {
+        Some(self.response.status)
+    }
+    fn duration(&self) -> Option<Duration> {
+        Some(self.response.duration)
+    }
 }
 
-impl<'a> Variable<'a> for EarlyRequest<'a> {
+impl<'a> Variable<'a> for Request {
     fn attr<'x>(&'x self, attr: &str) -> Result<Var<'x, 'a>, DataError>
         where 'a: 'x
     {
         match attr {
             // TODO(tailhook) return just IP when trimmer is updated
             "client_ip" => Ok(Var::owned(self.addr.ip())),
-            "host" => Ok(Var::owned(
-                self.head.host()
-                .unwrap_or("-")
-                .to_string())),
-            "method" => Ok(Var::owned(self.head.method())),
-            "path" => Ok(Var::owned(self.head.path())),
-            "version" => Ok(Var::owned(Display(self.head.version()))),
+            "host" => Ok(Var::borrow(&self.host)),
+            "method" => Ok(Var::borrow(&self.method)),
+            "path" => Ok(Var::borrow(&self.path)),
+            "version" => Ok(Var::owned(Display(self.version))),
+            "request_id" => Ok(Var::owned(Display(self.request_id))),
             _ => Err(DataError::AttrNotFound),
         }
     }
     fn typename(&self) -> &'static str {
-        "EarlyRequest"
+        "Request"
     }
 }
 
-impl<'a> fmt::Debug for EarlyRequest<'a> {
+impl fmt::Debug for Request {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("EarlyRequest")
+        f.debug_struct("Request")
          .finish()
     }
 }
 
-impl<'a> Variable<'a> for EarlyResponse {
+impl<'a> Variable<'a> for Response {
     fn attr<'x>(&'x self, attr: &str) -> Result<Var<'x, 'a>, DataError>
         where 'a: 'x
     {
         match attr {
-            "status_code" => Ok(Var::owned(self.status.code())),
+            "status_code" => Ok(Var::owned(self.status)),
             _ => Err(DataError::AttrNotFound),
         }
     }
     fn typename(&self) -> &'static str {
-        "EarlyResponse"
+        "Response"
     }
 }
 
-impl<'a> Variable<'a> for FakeResponse {
+impl<'a> Variable<'a> for Sample {
     fn attr<'x>(&'x self, attr: &str) -> Result<Var<'x, 'a>, DataError>
         where 'a: 'x
     {
-        match attr {
-            "status_code" => Ok(Var::str("non-404")),
-            _ => Err(DataError::AttrNotFound),
+        if self.0.iter().any(|a| *a == attr) {
+            Ok(Var::str("-"))
+        } else {
+            Err(DataError::AttrNotFound)
         }
     }
     fn typename(&self) -> &'static str {
-        "FakeResponse"
+        "Sample"
     }
 }
 
@@ -1,20 +1,88 @@
 
 mod context;
 pub mod http;
+pub mod trace;
 
 pub use self::context::AsContext;
 
 
 use std::io::{stdout, Write};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 
+use tk_http::server::Head;
+
+use crate::config::Config;
+use crate::intern::LogFormatName;
+use crate::request_id::RequestId;
 use crate::runtime::Runtime;
 
 
-pub fn log<C: AsContext>(runtime: &Arc<Runtime>, ctx: C) {
+/// Access log record of the request that is being served
+///
+/// Record is written when the response is done, so sampling can take
+/// final status and duration of the request into account.
+pub struct Record {
+    runtime: Arc<Runtime>,
+    format: Option<LogFormatName>,
+    request: http::Request,
+    started: Instant,
+}
+
+impl Record {
+    pub fn new(runtime: &Arc<Runtime>, addr: SocketAddr, head: &Head,
+        request_id: RequestId)
+        -> Record
+    {
+        Record {
+            runtime: runtime.clone(),
+            format: None,
+            request: http::Request::new(addr, head, request_id),
+            started: Instant::now(),
+        }
+    }
+    /// Sets log format of the route
+    pub fn set_format(&mut self, format: Option<LogFormatName>) {
+        self.format = format;
+    }
+    pub fn finish(self, status: u16) {
+        log(&self.runtime, self.format.as_ref(), http::Page {
+            request: &self.request,
+            response: http::Response {
+                status: status,
+                duration: self.started.elapsed(),
+            },
+        });
+    }
+}
+
+/// Returns false if the record should be skipped by access log sampling
+///
+/// Server errors and slow requests are always logged.
+fn sampled<C: AsContext>(ctx: &C, cfg: &Config) -> bool {
+    if ctx.status_code().map(|s| s >= 500).unwrap_or(false) {
+        return true;
+    }
+    if ctx.duration().map(|d| d >= cfg.access_log_slow_request)
+        .unwrap_or(false)
+    {
+        return true;
+    }
+    ctx.request_id()
+        .map(|rid| rid.sampled(cfg.access_log_sample_rate))
+        .unwrap_or(true)
+}
+
+/// Logs request with the `debug-log` format, or with the format of the
+/// route if it's specified
+pub fn log<C: AsContext>(runtime: &Arc<Runtime>,
+    format: Option<&LogFormatName>, ctx: C)
+{
     let cfg = runtime.config.get();
-    if cfg.debug_logging {
-        if let Some(ref fmt) = cfg.log_formats.get("debug-log") {
+    if cfg.debug_logging && sampled(&ctx, &cfg) {
+        let name = format.map(|x| &x[..]).unwrap_or("debug-log");
+        if let Some(ref fmt) = cfg.log_formats.get(name) {
             let ctx = ctx.as_context();
             match fmt.template.render(&ctx) {
                 Ok(mut line) => {
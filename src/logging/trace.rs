@@ -0,0 +1,16 @@
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::request_id::RequestId;
+
+
+/// Logs a request lifecycle event (`debug-tracing` setting)
+///
+/// Events are logged with `swindon::trace` target, so they can be enabled
+/// separately with `RUST_LOG=swindon::trace=info`.
+pub fn event<D: Display>(request_id: RequestId, event: &str, details: D) {
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    info!(target: "swindon::trace", "{}.{:06} {} {} {}",
+        ts.as_secs(), ts.subsec_micros(), request_id, event, details);
+}
@@ -23,6 +23,7 @@ mod proxy;
 mod request_id;
 mod routing;
 mod runtime;
+mod shutdown;
 mod startup;
 mod template;
 
@@ -21,6 +21,7 @@ mod proxy;
 mod request_id;
 mod routing;
 mod runtime;
+mod shutdown;
 mod startup;
 mod template;
 mod updater;
@@ -30,7 +31,7 @@ use std::io::{self, Write};
 use std::process::exit;
 
 use futures::stream::Stream;
-use argparse::{ArgumentParser, Parse, StoreTrue, Print};
+use argparse::{ArgumentParser, Parse, ParseOption, StoreTrue, Print};
 use tokio_core::reactor::Core;
 
 
@@ -43,6 +44,11 @@ pub fn main() {
     let mut config = String::from("/etc/swindon/main.yaml");
     let mut check = false;
     let mut verbose = false;
+    let mut test_route = false;
+    let mut test_host = String::new();
+    let mut test_path = String::from("/");
+    let mut test_method = String::from("GET");
+    let mut test_port = None::<u16>;
     {
         let mut ap = ArgumentParser::new();
         ap.set_description("Runs a web server");
@@ -60,6 +66,29 @@ pub fn main() {
             .add_option(&["--verbose"], StoreTrue,
             "Print some user-friendly startup messages. \
              With --check-config prints config fingerprint.");
+        ap.refer(&mut test_route)
+            .add_option(&["--test-route"], StoreTrue,
+            "Print which handler serves request specified by --host, \
+             --path, --method and --port and exit");
+        ap.refer(&mut test_host)
+            .add_option(&["--host"], Parse,
+            "Host of the request for --test-route")
+            .metavar("HOST");
+        ap.refer(&mut test_path)
+            .add_option(&["--path"], Parse,
+            "Path of the request for --test-route (default `/`)")
+            .metavar("PATH");
+        ap.refer(&mut test_method)
+            .add_option(&["--method"], Parse,
+            "Method of the request for --test-route (default `GET`). \
+             Methods which are neither standard nor listed in \
+             `extension-methods` are not routed")
+            .metavar("METHOD");
+        ap.refer(&mut test_port)
+            .add_option(&["--port"], ParseOption,
+            "Port of the listening socket request is received on, for \
+             --test-route. Routes with `listen-port` don't match without it")
+            .metavar("PORT");
         ap.parse_args_or_exit();
     }
 
@@ -72,6 +101,45 @@ pub fn main() {
     };
     let cfg = configurator.config();
 
+    if test_route {
+        if test_host.len() == 0 {
+            writeln!(&mut io::stderr(),
+                "Option --host is required for --test-route").ok();
+            exit(1);
+        }
+        let data = cfg.get();
+        let host = routing::parse_host(&test_host);
+        println!("{} {}{}", test_method, host, test_path);
+        // same checks as in `Router::start_request`
+        match routing::route(host, &test_path, &data.routing) {
+            Some((route, _, _)) if !route.serves_port(test_port) => {
+                println!("handler: {}", route.handler_name);
+                println!("listen-port: {}", route.listen_port
+                    .expect("only routes with listen-port are filtered"));
+                println!("no route on this port (404 Not Found)");
+                exit(1);
+            }
+            Some((route, _, _)) if !routing::known_method(&test_method,
+                &data.extension_methods) && !route.handler.passes_any_method()
+            => {
+                println!("handler: {}", route.handler_name);
+                println!("unknown method (501 Not Implemented)");
+                exit(1);
+            }
+            Some((route, prefix, suffix)) => {
+                println!("handler: {}", route.handler_name);
+                println!("authorizer: {}", route.authorizer_name);
+                println!("prefix: {:?}", prefix);
+                println!("suffix: {:?}", suffix);
+                exit(0);
+            }
+            None => {
+                println!("no route (404 Not Found)");
+                exit(1);
+            }
+        }
+    }
+
     if check {
         if verbose {
             println!("Config fingerprint: {}", cfg.fingerprint());
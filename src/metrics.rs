@@ -40,7 +40,9 @@ pub fn all(runtime: &Arc<Runtime>) -> Box<Vec<Box<dyn Collection>>> {
         Box::new(crate::incoming::metrics()),
         Box::new(crate::chat::metrics()),
         Box::new(crate::http_pools::metrics()),
+        Box::new(crate::proxy::metrics()),
         Box::new(crate::http_pools::pool_metrics(&runtime.http_pools)),
+        Box::new(crate::incoming::route_metrics(&runtime.route_stats)),
     ])
 }
 
@@ -6,9 +6,14 @@ use futures::future::{FutureResult, ok};
 use futures::sync::oneshot;
 use tk_http::client as http;
 
+use crate::config::Destination as Route;
 use crate::config::http_destinations::Destination;
+use crate::intern::Upstream;
 use crate::proxy::{RepReq, HalfResp, Response};
 
+/// Response buffer size used when `max-response-size` is not set
+const DEFAULT_MAX_RESPONSE_SIZE: usize = 10_485_760;
+
 enum State {
     Init(RepReq),
     Wait,
@@ -21,18 +26,25 @@ enum State {
 
 pub struct Codec {
     state: State,
+    upstream: Upstream,
+    path_prefix: String,
     destination: Arc<Destination>,
+    max_response_size: usize,
     sender: Option<oneshot::Sender<Response>>,
 }
 
 impl Codec {
-    pub fn new(req: RepReq, destination: &Arc<Destination>,
-        tx: oneshot::Sender<Response>)
+    pub fn new(req: RepReq, route: &Route, destination: &Arc<Destination>,
+        max_response_size: Option<usize>, tx: oneshot::Sender<Response>)
         -> Codec
     {
         Codec {
             state: State::Init(req),
+            upstream: route.upstream.clone(),
+            path_prefix: route.path.clone(),
             destination: destination.clone(),
+            max_response_size: max_response_size
+                .unwrap_or(DEFAULT_MAX_RESPONSE_SIZE),
             sender: Some(tx),
         }
     }
@@ -44,7 +56,8 @@ impl<S> http::Codec<S> for Codec {
     fn start_write(&mut self, e: http::Encoder<S>) -> Self::Future {
         if let State::Init(req) = mem::replace(&mut self.state, State::Void) {
             self.state = State::Wait;
-            ok(req.encode(e, &self.destination))
+            req.trace("upstream-connect", &self.upstream);
+            ok(req.encode(e, &self.path_prefix, &self.destination))
         } else {
             panic!("wrong state");
         }
@@ -53,9 +66,24 @@ impl<S> http::Codec<S> for Codec {
         -> Result<http::RecvMode, http::Error>
     {
         if let State::Wait = mem::replace(&mut self.state, State::Void) {
+            let length = headers.headers()
+                .find(|&(k, _)| k.eq_ignore_ascii_case("Content-Length"))
+                .and_then(|(_, v)| std::str::from_utf8(v).ok())
+                .and_then(|v| v.trim().parse::<u64>().ok());
+            if let Some(length) = length {
+                if length > self.max_response_size as u64 {
+                    warn!("Upstream response of {} bytes exceeds \
+                        max-response-size of {}",
+                        length, self.max_response_size);
+                    // sender is dropped, so client receives 502
+                    return Err(http::Error::custom("response is too large"));
+                }
+            }
             self.state = State::Headers(HalfResp::from_headers(headers));
-            // TODO(tailhook) limit and streaming
-            Ok(http::RecvMode::buffered(10_485_760))
+            // TODO(tailhook) streaming
+            // bodies without Content-Length are aborted by tk-http when
+            // they exceed the buffer size
+            Ok(http::RecvMode::buffered(self.max_response_size))
         } else {
             panic!("wrong state");
         }
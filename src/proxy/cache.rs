@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::proxy::Response;
+use crate::metrics::{Counter, Integer};
+
+
+/// Maximum number of responses kept for serving stale
+const MAX_ENTRIES: usize = 10_000;
+/// Maximum total size of response bodies kept for serving stale
+const MAX_TOTAL_SIZE: usize = 64 << 20;
+/// Bigger responses are not cached
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+lazy_static! {
+    pub static ref STALE_SERVED: Counter = Counter::new();
+    pub static ref ENTRIES: Integer = Integer::new();
+    pub static ref TOTAL_SIZE: Integer = Integer::new();
+}
+
+struct Entry {
+    response: Arc<Response>,
+    fresh_until: Instant,
+    /// Entry is useless after this time even for serving stale
+    expires: Instant,
+    stored_at: Instant,
+    /// Value of the `Age` header sent by upstream
+    initial_age: Duration,
+    /// Position in `Entries::lru`
+    last_used: u64,
+}
+
+struct Entries {
+    map: HashMap<String, Entry>,
+    /// Keys ordered by last use, least recently used first
+    lru: BTreeMap<u64, String>,
+    counter: u64,
+    total_size: usize,
+}
+
+/// A small cache of proxied responses used to serve stale content when
+/// upstream fails (`serve-stale` setting of a proxy handler)
+///
+/// Cache is limited both by number of entries and total size of bodies,
+/// least recently used entries are evicted first.
+#[derive(Clone)]
+pub struct StaleCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl StaleCache {
+    pub fn new() -> StaleCache {
+        StaleCache {
+            entries: Arc::new(Mutex::new(Entries {
+                map: HashMap::new(),
+                lru: BTreeMap::new(),
+                counter: 0,
+                total_size: 0,
+            })),
+        }
+    }
+    /// Stores response if it's cacheable
+    pub fn store(&self, key: &str, response: &Arc<Response>,
+        max_stale: Duration, now: Instant)
+    {
+        let max_age = match freshness(response) {
+            Some(max_age) => max_age,
+            None => return,
+        };
+        let initial_age = response.header("Age")
+            .and_then(|v| String::from_utf8_lossy(v).trim().parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::new(0, 0));
+        // age counts towards freshness lifetime (RFC 7234, section 4.2)
+        let fresh_until = now + max_age.checked_sub(initial_age)
+            .unwrap_or(Duration::new(0, 0));
+        let mut entries = self.entries.lock()
+            .expect("stale cache is not poisoned");
+        entries.remove(key);
+        let size = response.body().len();
+        if entries.map.len() >= MAX_ENTRIES ||
+            entries.total_size + size > MAX_TOTAL_SIZE
+        {
+            entries.remove_expired(now);
+        }
+        while entries.map.len() >= MAX_ENTRIES ||
+            entries.total_size + size > MAX_TOTAL_SIZE
+        {
+            if !entries.remove_lru() {
+                break;
+            }
+        }
+        let last_used = entries.touch(key);
+        entries.total_size += size;
+        entries.map.insert(key.to_string(), Entry {
+            response: response.clone(),
+            fresh_until: fresh_until,
+            expires: fresh_until + max_stale,
+            stored_at: now,
+            initial_age: initial_age,
+            last_used: last_used,
+        });
+        entries.update_metrics();
+    }
+    /// Returns cached response, whether it's stale, and its current age
+    pub fn get(&self, key: &str, max_stale: Duration, now: Instant)
+        -> Option<(Arc<Response>, bool, Duration)>
+    {
+        let mut entries = self.entries.lock()
+            .expect("stale cache is not poisoned");
+        let (stale, expired) = match entries.map.get(key) {
+            Some(e) => (
+                e.fresh_until < now,
+                e.expires < now || e.fresh_until + max_stale < now,
+            ),
+            None => return None,
+        };
+        if expired {
+            entries.remove(key);
+            entries.update_metrics();
+            return None;
+        }
+        let last_used = entries.touch(key);
+        let e = entries.map.get_mut(key).expect("entry exists");
+        e.last_used = last_used;
+        Some((e.response.clone(), stale, e.age(now)))
+    }
+}
+
+impl Entries {
+    /// Moves key to the end of LRU list and returns its new position
+    fn touch(&mut self, key: &str) -> u64 {
+        if let Some(e) = self.map.get(key) {
+            self.lru.remove(&e.last_used);
+        }
+        self.counter += 1;
+        self.lru.insert(self.counter, key.to_string());
+        return self.counter;
+    }
+    fn remove(&mut self, key: &str) {
+        if let Some(e) = self.map.remove(key) {
+            self.lru.remove(&e.last_used);
+            self.total_size -= e.response.body().len();
+        }
+    }
+    fn remove_expired(&mut self, now: Instant) {
+        let expired = self.map.iter()
+            .filter(|&(_, e)| e.expires < now)
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+        for key in expired {
+            self.remove(&key);
+        }
+    }
+    /// Removes least recently used entry, returns false if cache is empty
+    fn remove_lru(&mut self) -> bool {
+        let key = match self.lru.values().next() {
+            Some(key) => key.clone(),
+            None => return false,
+        };
+        debug!("Evicting {:?} from stale cache", key);
+        self.remove(&key);
+        return true;
+    }
+    fn update_metrics(&self) {
+        ENTRIES.set(self.map.len() as i64);
+        TOTAL_SIZE.set(self.total_size as i64);
+    }
+}
+
+impl Entry {
+    /// Current age of the response (RFC 7234, section 4.2.3), we don't
+    /// use `Date` header as clocks of the upstream might be off
+    fn age(&self, now: Instant) -> Duration {
+        let resident_time = if now > self.stored_at {
+            now - self.stored_at
+        } else {
+            Duration::new(0, 0)
+        };
+        self.initial_age + resident_time
+    }
+}
+
+/// Returns freshness lifetime for a response, or `None` if response
+/// must not be cached
+fn freshness(response: &Response) -> Option<Duration> {
+    if response.status_code() != 200 || response.body().len() > MAX_BODY_SIZE {
+        return None;
+    }
+    // responses that depend on anything other than URL are never cached
+    if response.header("Set-Cookie").is_some() ||
+       response.header("Vary").is_some()
+    {
+        return None;
+    }
+    let mut max_age = None;
+    if let Some(value) = response.header("Cache-Control") {
+        let value = String::from_utf8_lossy(value);
+        for item in value.split(',') {
+            let item = item.trim().to_lowercase();
+            match &item[..] {
+                "no-store" | "no-cache" | "private" => return None,
+                _ if item.starts_with("max-age=") => {
+                    max_age = item["max-age=".len()..].parse()
+                        .map(Duration::from_secs)
+                        .ok();
+                }
+                _ => {}
+            }
+        }
+    }
+    // responses without explicit freshness are not cached, we don't
+    // guess freshness lifetime heuristically
+    max_age
+}
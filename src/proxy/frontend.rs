@@ -1,10 +1,12 @@
 use std::sync::Arc;
 use std::mem;
+use std::time::{Duration, Instant};
 
 use futures::{Async, Future, AsyncSink};
 use futures::future::{ok};
 use futures::sink::{Sink};
 use futures::sync::oneshot;
+use tokio_core::reactor::Handle;
 use tk_http::Status;
 use tk_http::server::{Error, RecvMode};
 use tk_http::server as http;
@@ -13,7 +15,10 @@ use crate::config::proxy::Proxy;
 use crate::incoming::{Input, Reply, Encoder, Context, IntoContext};
 use crate::default_error_page::error_page;
 use crate::http_pools::{HttpPools, REQUESTS, FAILED_503};
-use crate::proxy:: {RepReq, HalfReq, Response, backend};
+use crate::proxy:: {RepReq, HalfReq, Response, StaleCache, backend};
+use crate::proxy::cache::STALE_SERVED;
+use crate::proxy::{SHADOW_REQUESTS, SHADOW_DROPPED};
+use crate::proxy::response::accepts_gzip;
 
 
 enum State {
@@ -29,11 +34,24 @@ enum State {
 }
 
 
+/// Stale cache entry that request might be served from
+struct Stale {
+    cache: StaleCache,
+    key: String,
+    max_stale: Duration,
+    age_header: bool,
+}
+
+
 pub struct Codec {
     settings: Arc<Proxy>,
     pools: HttpPools,
+    handle: Handle,
     state: State,
     context: Option<Context>,
+    stale: Option<Stale>,
+    /// Client accepts gzip, only checked with `negotiate-encoding`
+    accept_gzip: bool,
 }
 
 impl<S: 'static> http::Codec<S> for Codec {
@@ -51,11 +69,15 @@ impl<S: 'static> http::Codec<S> for Codec {
         if self.settings.stream_requests {
             unimplemented!();
         }
+        let mut shadow = None;
         self.state = match mem::replace(&mut self.state, State::Void) {
             State::Error(e) => State::Error(e),
             State::Headers(r) => {
                 assert!(end);
                 let r = r.upgrade(data.to_vec());
+                if self.settings.shadow_upstream.is_some() {
+                    shadow = Some(r.clone());
+                }
                 let dest_name = &self.settings.destination.upstream;
                 let mut up = self.pools.upstream(dest_name);
                 let (tx, rx) = oneshot::channel();
@@ -63,7 +85,8 @@ impl<S: 'static> http::Codec<S> for Codec {
                 let opt_dest = cfg.http_destinations.get(dest_name);
                 if let Some(dest_settings) = opt_dest {
                     let codec = Box::new(backend::Codec::new(r.clone(),
-                        dest_settings, tx));
+                        &self.settings.destination, dest_settings,
+                        self.settings.max_response_size, tx));
                     match up.get_mut().get_mut() {
                         Some(pool) => {
                             match pool.start_send(codec) {
@@ -101,6 +124,11 @@ impl<S: 'static> http::Codec<S> for Codec {
             State::Sent { .. } => unimplemented!(),
             State::Void => unreachable!(),
         };
+        // sent after the primary request, and after the pool lock is
+        // released, as both might use the same upstream
+        if let Some(r) = shadow {
+            self.send_shadow(r);
+        }
         return Ok(Async::Ready(data.len()));
     }
     fn start_response(&mut self, e: http::Encoder<S>) -> Reply<S> {
@@ -108,21 +136,50 @@ impl<S: 'static> http::Codec<S> for Codec {
             unimplemented!();
         } else {
             let ctx = self.context.take().unwrap();
+            let stale = self.stale.take();
+            let settings = self.settings.clone();
+            let gzip = self.accept_gzip;
             match mem::replace(&mut self.state, State::Void) {
                 State::Sent { response, .. } => {
                     Box::new(response.then(move |result| {
                         let e = Encoder::new(e, ctx);
                         match result {
+                            Ok(ref resp) if resp.status_code() >= 500 => {
+                                match stale.and_then(|s| s.lookup()) {
+                                    Some(cached) => {
+                                        ok(cached.encode_to(e, &settings, gzip))
+                                    }
+                                    None => ok(resp.encode(e, &settings, gzip)),
+                                }
+                            }
                             Ok(resp) => {
-                                ok(resp.encode(e))
+                                let resp = Arc::new(resp);
+                                if let Some(ref stale) = stale {
+                                    stale.store(&resp);
+                                }
+                                ok(resp.encode(e, &settings, gzip))
                             }
                             Err(err) => {
                                 debug!("Proxy request error: {:?}", err);
-                                error_page(Status::BadGateway, e)
+                                match stale.and_then(|s| s.lookup()) {
+                                    Some(cached) => {
+                                        ok(cached.encode_to(e, &settings, gzip))
+                                    }
+                                    None => error_page(Status::BadGateway, e),
+                                }
                             }
                         }
                     }))
                 }
+                State::Error(status @ Status::ServiceUnavailable) => {
+                    let e = Encoder::new(e, ctx);
+                    match stale.and_then(|s| s.lookup()) {
+                        Some(cached) => {
+                            Box::new(ok(cached.encode_to(e, &settings, gzip)))
+                        }
+                        None => Box::new(error_page(status, e)),
+                    }
+                }
                 State::Error(status) => {
                     Box::new(error_page(status, Encoder::new(e, ctx)))
                 }
@@ -137,8 +194,135 @@ impl Codec {
         Codec {
             state: State::Headers(HalfReq::from_input(&inp, &settings)),
             pools: inp.runtime.http_pools.clone(),
+            handle: inp.handle.clone(),
+            stale: Stale::from_input(&inp, &settings),
+            accept_gzip: settings.negotiate_encoding && accepts_gzip(&inp),
             settings: settings.clone(),
             context: Some(inp.into_context()),
         }
     }
+    /// Sends a copy of the request to the `shadow-upstream`
+    ///
+    /// Response is discarded, and any failure only affects metrics and logs.
+    fn send_shadow(&self, r: RepReq) {
+        let route = match self.settings.shadow_upstream {
+            Some(ref route) => route,
+            None => return,
+        };
+        let ref cfg = self.context.as_ref().unwrap().0;
+        let dest_settings = match cfg.http_destinations.get(&route.upstream) {
+            Some(dest_settings) => dest_settings,
+            None => {
+                error!("No such destination {:?}", route.upstream);
+                return;
+            }
+        };
+        let (tx, rx) = oneshot::channel();
+        let codec = Box::new(backend::Codec::new(r, route, dest_settings,
+            self.settings.max_response_size, tx));
+        let mut up = self.pools.shadow_upstream(&route.upstream);
+        match up.get_mut().get_mut() {
+            Some(pool) => {
+                match pool.start_send(codec) {
+                    Ok(AsyncSink::Ready) => {
+                        SHADOW_REQUESTS.incr(1);
+                        let upstream = route.upstream.clone();
+                        self.handle.spawn(rx.then(move |result| {
+                            match result {
+                                Ok(resp) => {
+                                    debug!("Shadow upstream {:?} responded \
+                                        with {}", upstream, resp.status_code());
+                                }
+                                Err(_) => {
+                                    SHADOW_DROPPED.incr(1);
+                                    debug!("Shadow upstream {:?} failed",
+                                        upstream);
+                                }
+                            }
+                            Ok(())
+                        }));
+                    }
+                    Ok(AsyncSink::NotReady(_)) => {
+                        SHADOW_DROPPED.incr(1);
+                        debug!("Shadow upstream {:?} is busy",
+                            route.upstream);
+                    }
+                    Err(e) => {
+                        SHADOW_DROPPED.incr(1);
+                        error!("Error sending to pool {:?}: {}",
+                            route.upstream, e);
+                    }
+                }
+            }
+            None => {
+                error!("No such pool {:?}", route.upstream);
+            }
+        }
+    }
+}
+
+/// Cached response found on upstream failure
+struct Cached {
+    response: Arc<Response>,
+    is_stale: bool,
+    /// Value for the `Age` header, unless disabled by `age-header`
+    age: Option<Duration>,
+}
+
+impl Cached {
+    fn encode_to<S>(&self, e: Encoder<S>, settings: &Proxy,
+        accept_gzip: bool)
+        -> http::EncoderDone<S>
+    {
+        if self.is_stale {
+            STALE_SERVED.incr(1);
+            self.response.encode_stale(e, settings, accept_gzip, self.age)
+        } else {
+            self.response.encode_cached(e, settings, accept_gzip, self.age)
+        }
+    }
+}
+
+impl Stale {
+    fn from_input(inp: &Input, settings: &Proxy) -> Option<Stale> {
+        let cfg = match settings.serve_stale {
+            Some(ref cfg) if cfg.on_error => cfg,
+            _ => return None,
+        };
+        if inp.headers.method() != "GET" {
+            return None;
+        }
+        // responses to authorized requests may be different for every user
+        let personal = inp.ingress_headers().any(|(name, _)| {
+            name.eq_ignore_ascii_case("Authorization") ||
+            name.eq_ignore_ascii_case("Cookie")
+        });
+        if personal {
+            return None;
+        }
+        let host = inp.host;
+        let path = inp.headers.path().unwrap_or("/");
+        Some(Stale {
+            cache: inp.runtime.proxy_cache.clone(),
+            key: format!("{}{}\n{}{}",
+                settings.destination.upstream, settings.destination.path,
+                host, path),
+            max_stale: cfg.max_stale,
+            age_header: cfg.age_header,
+        })
+    }
+    fn store(&self, response: &Arc<Response>) {
+        self.cache.store(&self.key, response, self.max_stale, Instant::now());
+    }
+    fn lookup(&self) -> Option<Cached> {
+        self.cache.get(&self.key, self.max_stale, Instant::now())
+            .map(|(response, is_stale, age)| {
+                debug!("Serving {:?} from stale cache", self.key);
+                Cached {
+                    response,
+                    is_stale,
+                    age: if self.age_header { Some(age) } else { None },
+                }
+            })
+    }
 }
@@ -1,7 +1,26 @@
 pub mod frontend;
 pub mod backend;
+mod cache;
 mod response;
 mod request;
 
+pub use self::cache::StaleCache;
 pub use self::response::{HalfResp, Response};
 pub use self::request::{HalfReq, RepReq};
+
+use crate::metrics::{List, Metric, Counter};
+
+lazy_static! {
+    pub static ref SHADOW_REQUESTS: Counter = Counter::new();
+    pub static ref SHADOW_DROPPED: Counter = Counter::new();
+}
+
+pub fn metrics() -> List {
+    vec![
+        (Metric("proxy.stale_cache", "entries"), &*cache::ENTRIES),
+        (Metric("proxy.stale_cache", "total_size"), &*cache::TOTAL_SIZE),
+        (Metric("proxy.stale_cache", "served"), &*cache::STALE_SERVED),
+        (Metric("proxy.shadow", "requests"), &*SHADOW_REQUESTS),
+        (Metric("proxy.shadow", "dropped"), &*SHADOW_DROPPED),
+    ]
+}
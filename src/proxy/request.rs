@@ -1,3 +1,4 @@
+use std::fmt::Display;
 use std::sync::Arc;
 use std::net::SocketAddr;
 
@@ -6,7 +7,8 @@ use tk_http::client::{Encoder, EncoderDone};
 
 use crate::config::http_destinations::Destination;
 use crate::config::proxy::Proxy;
-use crate::incoming::{Input};
+use crate::incoming::{Input, dedup_target};
+use crate::logging::trace;
 use crate::request_id::RequestId;
 
 
@@ -22,6 +24,7 @@ pub struct HalfReq {
     headers: Vec<(String, Vec<u8>)>,
     addr: SocketAddr,
     request_id: RequestId,
+    trace: bool,
 }
 
 #[derive(Debug)]
@@ -33,6 +36,7 @@ struct ReqData {
     headers: Vec<(String, Vec<u8>)>,
     addr: SocketAddr,
     request_id: RequestId,
+    trace: bool,
     body: Vec<u8>,
 }
 
@@ -41,22 +45,28 @@ impl HalfReq {
     pub fn from_input(inp: &Input, settings: &Arc<Proxy>) -> HalfReq {
         use tk_http::server::RequestTarget::*;
         let path = match *inp.headers.request_target() {
+            Origin(x) if settings.merge_slashes => merge_slashes(x),
             Origin(x) => x.to_string(),
+            Absolute { path, ..} if settings.merge_slashes => {
+                merge_slashes(path)
+            }
             Absolute { path, ..} => path.to_string(),
             Authority(..) => unreachable!(),
             Asterisk => String::from("*"),
         };
+        let path = dedup_target(&path, inp.config.duplicate_query_params);
 
         HalfReq {
             settings: settings.clone(),
             method: inp.headers.method().to_string(),
             path: path,
-            host: inp.headers.host().expect("host exists").to_string(),
-            headers: inp.headers.headers().map(|(k, v)| {
+            host: inp.host.to_string(),
+            headers: inp.ingress_headers().map(|(k, v)| {
                 (k.to_string(), v.to_vec())
             }).collect(),
             addr: inp.addr,
             request_id: inp.request_id,
+            trace: inp.config.debug_tracing,
         }
     }
     pub fn upgrade(self, body: Vec<u8>) -> RepReq {
@@ -68,20 +78,30 @@ impl HalfReq {
             headers: self.headers,
             addr: self.addr,
             request_id: self.request_id,
+            trace: self.trace,
             body: body,
         }))
     }
 }
 impl RepReq {
-    pub fn encode<S>(&self, mut e: Encoder<S>, dest: &Arc<Destination>)
+    /// Logs request lifecycle event if `debug-tracing` is enabled
+    pub fn trace<D: Display>(&self, event: &str, details: D) {
+        if self.0.trace {
+            trace::event(self.0.request_id, event, details);
+        }
+    }
+    /// Encodes request to the backend, `prefix` is a path of the
+    /// destination (i.e. `/path` in `upstream/path`)
+    pub fn encode<S>(&self, mut e: Encoder<S>, prefix: &str,
+        dest: &Arc<Destination>)
         -> EncoderDone<S>
     {
         let ref r = *self.0;
-        if r.settings.destination.path == "/" {
+        if prefix == "/" {
             e.request_line(&r.method, &r.path, Version::Http11);
         } else {
             e.request_line(&r.method,
-                &format!("{}{}", r.settings.destination.path, r.path),
+                &format!("{}{}", prefix, r.path),
                 Version::Http11);
         }
 
@@ -111,3 +131,33 @@ impl RepReq {
         return e.done();
     }
 }
+
+/// Collapses consecutive slashes in the path part, query is kept intact
+fn merge_slashes(path: &str) -> String {
+    let (path, query) = match path.find('?') {
+        Some(idx) => path.split_at(idx),
+        None => (path, ""),
+    };
+    let mut result = String::with_capacity(path.len() + query.len());
+    for c in path.chars() {
+        if c == '/' && result.ends_with('/') {
+            continue;
+        }
+        result.push(c);
+    }
+    result.push_str(query);
+    return result;
+}
+
+#[cfg(test)]
+mod test {
+    use super::merge_slashes;
+
+    #[test]
+    fn merge() {
+        assert_eq!(merge_slashes("/"), "/");
+        assert_eq!(merge_slashes("//a//b"), "/a/b");
+        assert_eq!(merge_slashes("/a///b/"), "/a/b/");
+        assert_eq!(merge_slashes("//a//b?x=//y"), "/a/b?x=//y");
+    }
+}
@@ -1,8 +1,15 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use tk_http::{Status};
 use tk_http::client::Head;
 use tk_http::server::{EncoderDone};
 
-use crate::incoming::Encoder;
+use crate::config::proxy::{self, Proxy};
+use crate::incoming::{Encoder, Input};
 
 
 #[derive(Debug)]
@@ -47,7 +54,107 @@ impl HalfResp {
 }
 
 impl Response {
-    pub fn encode<S>(&self, mut e: Encoder<S>) -> EncoderDone<S>{
+    pub fn status_code(&self) -> u16 {
+        match self.status {
+            RespStatus::Normal(s) => s.code(),
+            RespStatus::Custom(c, _) => c,
+        }
+    }
+    pub fn header(&self, name: &str) -> Option<&[u8]> {
+        self.headers.iter()
+            .find(|&&(ref k, _)| k.eq_ignore_ascii_case(name))
+            .map(|&(_, ref v)| &v[..])
+    }
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+    /// Encodes response, `accept_gzip` is only used with
+    /// `negotiate-encoding`
+    pub fn encode<S>(&self, e: Encoder<S>, settings: &Proxy,
+        accept_gzip: bool)
+        -> EncoderDone<S>
+    {
+        self.encode_with_warning(e, settings, accept_gzip, None, None)
+    }
+    /// Encodes response from the cache that is still fresh, `age` replaces
+    /// the `Age` header sent by upstream
+    pub fn encode_cached<S>(&self, e: Encoder<S>, settings: &Proxy,
+        accept_gzip: bool, age: Option<Duration>)
+        -> EncoderDone<S>
+    {
+        self.encode_with_warning(e, settings, accept_gzip, age, None)
+    }
+    /// Encodes response from the stale cache
+    pub fn encode_stale<S>(&self, e: Encoder<S>, settings: &Proxy,
+        accept_gzip: bool, age: Option<Duration>)
+        -> EncoderDone<S>
+    {
+        self.encode_with_warning(e, settings, accept_gzip, age,
+            Some(r#"110 - "Response is Stale""#))
+    }
+    /// Returns the body recoded for the client and whether it's gzipped,
+    /// or `None` if the body must be sent as is (`negotiate-encoding`
+    /// and `proxy-compression`)
+    fn recode(&self, settings: &Proxy, accept_gzip: bool)
+        -> Option<(Vec<u8>, bool)>
+    {
+        let gzipped = match self.header("Content-Encoding")
+            .map(|v| String::from_utf8_lossy(v).trim().to_lowercase())
+        {
+            None => false,
+            Some(ref enc) if enc == "identity" => false,
+            Some(ref enc) if enc == "gzip" || enc == "x-gzip" => true,
+            // unknown encodings and multiple encodings are passed as is
+            Some(_) => return None,
+        };
+        if gzipped && !accept_gzip && settings.negotiate_encoding {
+            let max = settings.negotiate_encoding_max_size;
+            let mut body = Vec::new();
+            let res = GzDecoder::new(&self.body[..])
+                .take(max as u64 + 1)
+                .read_to_end(&mut body);
+            match res {
+                Ok(_) if body.len() <= max => Some((body, false)),
+                Ok(_) => {
+                    debug!("Decompressed response is larger than {}, \
+                        sending as is", max);
+                    None
+                }
+                Err(e) => {
+                    debug!("Error decompressing response: {}", e);
+                    None
+                }
+            }
+        } else if !gzipped && accept_gzip {
+            let ctype = self.header("Content-Type");
+            let (max, allowed) = match settings.proxy_compression {
+                Some(ref comp) => {
+                    (comp.max_size,
+                     ctype.map(|t| compression_allowed(comp, t))
+                        .unwrap_or(false))
+                }
+                None => {
+                    (settings.negotiate_encoding_max_size,
+                     ctype.map(compressible).unwrap_or(false))
+                }
+            };
+            if !allowed || self.body.len() > max {
+                return None;
+            }
+            let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+            enc.write_all(&self.body)
+                .and_then(|()| enc.finish())
+                .map(|body| (body, true))
+                .map_err(|e| error!("Error compressing response: {}", e))
+                .ok()
+        } else {
+            None
+        }
+    }
+    fn encode_with_warning<S>(&self, mut e: Encoder<S>, settings: &Proxy,
+        accept_gzip: bool, age: Option<Duration>, warning: Option<&str>)
+        -> EncoderDone<S>
+    {
         let body = match self.status {
             RespStatus::Normal(s) => {
                 e.status(s);
@@ -58,13 +165,68 @@ impl Response {
                 true
             }
         };
+        let recodes = settings.negotiate_encoding ||
+            settings.proxy_compression.is_some();
+        let recoded = if body && recodes {
+            self.recode(settings, accept_gzip)
+        } else {
+            None
+        };
         for &(ref k, ref v) in &self.headers {
+            if age.is_some() && k.eq_ignore_ascii_case("Age") {
+                continue;
+            }
+            if recodes &&
+                k.eq_ignore_ascii_case("Vary") && !varies_on_encoding(v)
+            {
+                e.format_header(k, format_args!("{}, Accept-Encoding",
+                    String::from_utf8_lossy(v)));
+                continue;
+            }
+            if recoded.is_some() {
+                if k.eq_ignore_ascii_case("Content-Encoding") {
+                    continue;
+                }
+                // body is different, so it's not byte-for-byte equal
+                if k.eq_ignore_ascii_case("ETag") && !v.starts_with(b"W/") {
+                    e.format_header(k,
+                        format_args!("W/{}", String::from_utf8_lossy(v)));
+                    continue;
+                }
+            }
             e.add_header(k, v);
         }
+        if let Some((_, true)) = recoded {
+            e.add_header("Content-Encoding", "gzip");
+        }
+        if recodes && self.header("Vary").is_none() {
+            e.add_header("Vary", "Accept-Encoding");
+        }
+        let body_data = recoded.as_ref()
+            .map(|&(ref data, _)| &data[..])
+            .unwrap_or(&self.body[..]);
+        if body && self.header("Content-Type").is_none() {
+            if let Some(ref ctype) = settings.default_content_type {
+                e.add_header("Content-Type", ctype);
+            }
+        }
+        if settings.nosniff &&
+            self.header("X-Content-Type-Options").is_none()
+        {
+            e.add_header("X-Content-Type-Options", "nosniff");
+        }
+        if let Some(age) = age {
+            e.format_header("Age", age.as_secs());
+        }
+        if let Some(warning) = warning {
+            e.add_header("Warning", warning);
+        }
         if body {
-            e.add_length(self.body.len() as u64);
+            // body is buffered, so length is known even if upstream
+            // delimited it by closing the connection
+            e.add_length(body_data.len() as u64);
             if e.done_headers() {
-                e.write_body(&self.body);
+                e.write_body(body_data);
             }
         } else {
             let res = e.done_headers();
@@ -73,3 +235,108 @@ impl Response {
         return e.done();
     }
 }
+
+/// Returns true if client accepts `gzip` content coding
+pub fn accepts_gzip(inp: &Input) -> bool {
+    let values = inp.ingress_headers()
+        .filter(|&(name, _)| name.eq_ignore_ascii_case("Accept-Encoding"))
+        .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+        .collect::<Vec<_>>();
+    accept_encoding_has_gzip(&values.join(","))
+}
+
+fn accept_encoding_has_gzip(value: &str) -> bool {
+    let mut gzip = None;
+    let mut star = None;
+    for item in value.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim().to_lowercase();
+        let allowed = parts.all(|p| {
+            let p = p.trim();
+            !(p.starts_with("q=") || p.starts_with("Q=")) ||
+                p[2..].trim().parse::<f32>().map(|q| q > 0.).unwrap_or(false)
+        });
+        match &coding[..] {
+            "gzip" | "x-gzip" => gzip = Some(allowed),
+            "*" => star = Some(allowed),
+            _ => {}
+        }
+    }
+    gzip.or(star).unwrap_or(false)
+}
+
+fn varies_on_encoding(vary: &[u8]) -> bool {
+    String::from_utf8_lossy(vary).split(',').any(|x| {
+        let x = x.trim();
+        x == "*" || x.eq_ignore_ascii_case("Accept-Encoding")
+    })
+}
+
+/// Content types that are worth compressing
+fn compressible(content_type: &[u8]) -> bool {
+    let ctype = String::from_utf8_lossy(content_type).to_lowercase();
+    let mime = ctype.split(';').next().unwrap_or("").trim();
+    mime.starts_with("text/") ||
+        mime.ends_with("+json") || mime.ends_with("+xml") ||
+        mime == "application/json" ||
+        mime == "application/javascript" ||
+        mime == "application/xml" ||
+        mime == "image/svg+xml"
+}
+
+/// Checks content type against `proxy-compression` settings
+fn compression_allowed(settings: &proxy::Compression, content_type: &[u8])
+    -> bool
+{
+    if settings.content_types.is_empty() {
+        return compressible(content_type);
+    }
+    let ctype = String::from_utf8_lossy(content_type);
+    let mime = ctype.split(';').next().unwrap_or("").trim();
+    settings.content_types.iter().any(|x| x.eq_ignore_ascii_case(mime))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::proxy::Compression;
+    use super::{accept_encoding_has_gzip, compressible, compression_allowed};
+
+    #[test]
+    fn accept_encoding() {
+        assert!(accept_encoding_has_gzip("gzip"));
+        assert!(accept_encoding_has_gzip("deflate, gzip;q=0.5"));
+        assert!(accept_encoding_has_gzip("*"));
+        assert!(accept_encoding_has_gzip("X-GZIP"));
+        assert!(!accept_encoding_has_gzip(""));
+        assert!(!accept_encoding_has_gzip("identity"));
+        assert!(!accept_encoding_has_gzip("gzip;q=0"));
+        assert!(!accept_encoding_has_gzip("*, gzip;q=0.0"));
+        assert!(!accept_encoding_has_gzip("br, *;q=0"));
+    }
+
+    #[test]
+    fn content_types() {
+        assert!(compressible(b"text/html; charset=utf-8"));
+        assert!(compressible(b"application/json"));
+        assert!(compressible(b"application/ld+json"));
+        assert!(!compressible(b"image/png"));
+        assert!(!compressible(b"application/octet-stream"));
+    }
+
+    #[test]
+    fn compression_content_types() {
+        let builtin = Compression {
+            content_types: vec![],
+            max_size: 1 << 20,
+        };
+        assert!(compression_allowed(&builtin, b"text/plain"));
+        assert!(!compression_allowed(&builtin, b"image/png"));
+        let custom = Compression {
+            content_types: vec!["application/json".into()],
+            max_size: 1 << 20,
+        };
+        assert!(compression_allowed(&custom,
+            b"Application/JSON; charset=utf-8"));
+        assert!(!compression_allowed(&custom, b"text/plain"));
+    }
+}
@@ -96,6 +96,23 @@ impl RequestId {
             str::from_utf8_unchecked(&self.0[..])
         }
     }
+    /// Returns true for approximately one in `rate` request ids
+    ///
+    /// Decision only depends on the request id itself, so every place
+    /// that samples by request id makes the same choice.
+    pub fn sampled(&self, rate: u32) -> bool {
+        if rate <= 1 {
+            return true;
+        }
+        // FNV-1a, timestamp and thread id are hashed too, so it's uniform
+        // even if a single thread serves all requests
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for &b in &self.0[..] {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash % (rate as u64) == 0
+    }
 }
 
 impl fmt::Debug for RequestId {
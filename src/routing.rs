@@ -2,11 +2,15 @@ use std::collections::{HashMap};
 
 use regex::{self, RegexSet};
 
+use std::sync::Arc;
+
 use crate::intern::{HandlerName, Authorizer as AuthorizerName};
+use crate::intern::{DeprecationName, LogFormatName};
 use crate::config::{ConfigSource, Error};
 use crate::config::routing::{Host, HostPath, RouteDef};
 use crate::config::handlers::Handler::{self, StripWWWRedirect};
 use crate::config::authorizers::Authorizer;
+use crate::config::deprecation::Deprecation;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Route {
@@ -14,20 +18,54 @@ pub struct Route {
     pub handler: Handler,
     pub authorizer_name: AuthorizerName,
     pub authorizer: Authorizer,
+    pub listen_port: Option<u16>,
+    pub deprecation: Option<Arc<Deprecation>>,
+    pub log_format: Option<LogFormatName>,
+    pub keep_alive: bool,
+}
+
+impl Route {
+    /// Returns false if route is bound by `listen-port` to another
+    /// listening socket than the one request is received on
+    pub fn serves_port(&self, local_port: Option<u16>) -> bool {
+        match self.listen_port {
+            Some(port) => local_port == Some(port),
+            None => true,
+        }
+    }
 }
 
+/// Tables bigger than this are matched using hash lookups of every
+/// prefix (suffix for hosts) instead of a regex set, because regex set
+/// of thousands of entries is slow to compile and uses a lot of memory
+const LARGE_TABLE: usize = 256;
+
 #[derive(Debug)]
 pub struct RoutingTable {
-    set: RegexSet,
+    matcher: HostMatcher,
     table: Vec<(String, PathTable)>,
 }
 
 #[derive(Debug)]
 pub struct PathTable {
-    set: RegexSet,
+    matcher: PathMatcher,
     table: Vec<(String, Route)>,
 }
 
+#[derive(Debug)]
+enum HostMatcher {
+    Regex(RegexSet),
+    /// Maps host name to indexes of the exact and the star entry, they are
+    /// equal if there is no exact entry for the host
+    Index(HashMap<String, (usize, usize)>),
+}
+
+#[derive(Debug)]
+enum PathMatcher {
+    Regex(RegexSet),
+    Index(HashMap<String, usize>),
+}
+
 impl PartialEq for RoutingTable {
     fn eq(&self, other: &RoutingTable) -> bool {
         return self.table == other.table;
@@ -54,14 +92,18 @@ fn inherit(to: &mut RouteDef, from: &RouteDef) {
 }
 fn is_done(item: &RouteDef) -> bool {
     matches!(*item, RouteDef {
-        handler: _,
         authorizer: Some(_),
+        ..
     })
 }
 fn default() -> RouteDef {
     RouteDef {
         handler: HandlerName::from("default"),
         authorizer: None,
+        listen_port: None,
+        deprecation: None,
+        log_format: None,
+        keep_alive: true,
     }
 }
 
@@ -71,23 +113,62 @@ impl PathTable {
             // sort by longest first, but then keep order reproducible
             b.len().cmp(&a.len()).then(a.cmp(b))
         });
-        let rset = RegexSet::new(
-            table.iter().map(|&(ref path, _)| {
-                String::from("^") + &regex::escape(&path) + r"(?:$|/|\?|#)"
-            }))?;
+        let matcher = if table.len() > LARGE_TABLE {
+            PathMatcher::Index(table.iter().enumerate()
+                .map(|(idx, &(ref path, _))| (path.clone(), idx))
+                .collect())
+        } else {
+            PathMatcher::Regex(RegexSet::new(
+                table.iter().map(|&(ref path, _)| {
+                    String::from("^") + &regex::escape(&path) +
+                        r"(?:$|/|\?|#)"
+                }))?)
+        };
         Ok(PathTable {
-            set: rset,
+            matcher: matcher,
             table: table,
         })
     }
+    fn find(&self, path: &str) -> Option<usize> {
+        match self.matcher {
+            PathMatcher::Regex(ref set) => set.matches(path).iter().next(),
+            PathMatcher::Index(ref map) => {
+                if let Some(&idx) = map.get(path) {
+                    return Some(idx);
+                }
+                // every prefix that ends at a component boundary,
+                // longest first
+                for (pos, c) in path.char_indices().rev() {
+                    if c == '/' || c == '?' || c == '#' {
+                        if let Some(&idx) = map.get(&path[..pos]) {
+                            return Some(idx);
+                        }
+                    }
+                }
+                None
+            }
+        }
+    }
 }
 
 trait Resolver {
     fn handler(&self, _: &HandlerName) -> Option<Handler>;
     fn authorizer(&self, _: &AuthorizerName) -> Option<Authorizer>;
+    fn deprecation(&self, _: &DeprecationName) -> Option<Arc<Deprecation>>;
+    fn has_log_format(&self, _: &LogFormatName) -> bool;
     fn route(&self, route: &RouteDef) -> Result<Route, Error> {
         let auth = route.authorizer.clone()
             .unwrap_or(AuthorizerName::from("default"));
+        let deprecation = match route.deprecation {
+            Some(ref name) => Some(self.deprecation(name)
+                .ok_or_else(|| Error::NoDeprecation(name.clone()))?),
+            None => None,
+        };
+        if let Some(ref name) = route.log_format {
+            if !self.has_log_format(name) {
+                return Err(Error::NoLogFormat(name.clone()));
+            }
+        }
         Ok(Route {
             handler: self.handler(&route.handler)
                 .ok_or_else(|| Error::NoHandler(route.handler.clone()))?,
@@ -95,6 +176,10 @@ trait Resolver {
             authorizer: self.authorizer(&auth)
                 .ok_or_else(|| Error::NoAuthorizer(auth.clone()))?,
             authorizer_name: auth,
+            listen_port: route.listen_port,
+            deprecation: deprecation,
+            log_format: route.log_format.clone(),
+            keep_alive: route.keep_alive,
         })
     }
 }
@@ -106,6 +191,12 @@ impl<'a> Resolver for &'a ConfigSource {
     fn authorizer(&self, n: &AuthorizerName) -> Option<Authorizer> {
         self.authorizers.get(n).cloned()
     }
+    fn deprecation(&self, n: &DeprecationName) -> Option<Arc<Deprecation>> {
+        self.deprecations.get(n).cloned()
+    }
+    fn has_log_format(&self, n: &LogFormatName) -> bool {
+        self.log_formats.contains_key(n)
+    }
 }
 
 impl RoutingTable {
@@ -284,20 +375,31 @@ impl RoutingTable {
         hosts_table.sort_by(|&(ref a, _, _), &(ref b, _, _)| {
             b.len().cmp(&a.len()).then(a.cmp(b))
         });
+        let large = hosts_table.len() > LARGE_TABLE;
         let mut real_table = Vec::new();
         let mut regex_table = Vec::new();
+        let mut index = HashMap::new();
         for (name, star, exact) in hosts_table.into_iter() {
             match exact {
                 Some(exact) => {
-                    regex_table.push(
-                        String::from("^") + &regex::escape(&name) + "$");
+                    if large {
+                        index.insert(name.clone(),
+                            (real_table.len(), real_table.len() + 1));
+                    } else {
+                        regex_table.push(
+                            String::from("^") + &regex::escape(&name) + "$");
+                        regex_table.push(
+                            String::from(r"^.*\.") +
+                                &regex::escape(&name) + "$");
+                    }
                     real_table.push((name.clone(), exact));
-                    regex_table.push(
-                        String::from(r"^.*\.") + &regex::escape(&name) + "$");
                     real_table.push((name, star));
                 }
                 None => {
-                    if name == "" {
+                    if large {
+                        index.insert(name.clone(),
+                            (real_table.len(), real_table.len()));
+                    } else if name == "" {
                         regex_table.push(String::from(r"^.*$"));
                     } else {
                         regex_table.push(
@@ -308,14 +410,56 @@ impl RoutingTable {
                 }
             }
         }
-        let rset = RegexSet::new(regex_table.into_iter())?;
+        let matcher = if large {
+            HostMatcher::Index(index)
+        } else {
+            HostMatcher::Regex(RegexSet::new(regex_table.into_iter())?)
+        };
         let table = RoutingTable {
-            set: rset,
+            matcher: matcher,
             table: real_table,
         };
         Ok(table)
     }
 
+    fn find(&self, host: &str) -> Option<usize> {
+        match self.matcher {
+            HostMatcher::Regex(ref set) => set.matches(host).iter().next(),
+            HostMatcher::Index(ref map) => {
+                if let Some(&(exact, _)) = map.get(host) {
+                    return Some(exact);
+                }
+                // parent domains, longest first
+                for (pos, _) in host.match_indices('.') {
+                    if let Some(&(_, star)) = map.get(&host[pos+1..]) {
+                        return Some(star);
+                    }
+                }
+                // catch-all `*` entry
+                match map.get("") {
+                    Some(&(exact, star)) if exact == star => Some(star),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Returns path prefix of the first route of the host which is served
+    /// by the handler and is reachable on the `port`
+    pub fn handler_path(&self, host: &str, handler: &HandlerName,
+        port: Option<u16>)
+        -> Option<&str>
+    {
+        let idx = self.find(host)?;
+        let (_, ref sub_table) = self.table[idx];
+        sub_table.table.iter()
+            .find(|&&(_, ref route)| {
+                route.handler_name == *handler &&
+                route.listen_port.map(|p| Some(p) == port).unwrap_or(true)
+            })
+            .map(|&(ref path, _)| &path[..])
+    }
+
     #[allow(dead_code)]
     pub fn num_hosts(&self) -> usize {
         self.table.len()
@@ -329,22 +473,28 @@ pub fn route<'x>(host: &str, path: &'x str,
     table: &'x RoutingTable)
     -> Option<(&'x Route, &'x str, &'x str)>
 {
-    let set = table.set.matches(host);
-    if !set.matched_any() {
-        return None;
-    }
-    let idx = set.iter().next().unwrap();
+    let idx = table.find(host)?;
     let (_, ref sub_table) = table.table[idx];
 
-    let set = sub_table.set.matches(path);
-    if !set.matched_any() {
-        return None;
-    }
-    let idx = set.iter().next().unwrap();
+    let idx = sub_table.find(path)?;
     let (ref rpath, ref route) = sub_table.table[idx];
     return Some((route, rpath, &path[rpath.len()..]));
 }
 
+/// Returns true if method is a standard one or is allowed in config
+///
+/// Methods are case-sensitive (RFC 7231, section 4.1), so `get` is not
+/// a `GET` but some unknown method. Handlers have no way to serve methods
+/// they don't know, so such requests are rejected with `501 Not
+/// Implemented` (RFC 7231, section 6.6.2) unless the method is listed in
+/// `extension-methods` or handler passes method through (i.e. proxy).
+pub fn known_method(method: &str, extension_methods: &[String]) -> bool {
+    const STANDARD: &[&str] = &["GET", "HEAD", "POST", "PUT", "DELETE",
+        "CONNECT", "OPTIONS", "TRACE", "PATCH"];
+    STANDARD.iter().any(|m| *m == method) ||
+        extension_methods.iter().any(|m| m == method)
+}
+
 /// Returns host with trimmed whitespace and without port number if exists
 pub fn parse_host(host_header: &str) -> &str {
     match host_header.find(':') {
@@ -356,8 +506,11 @@ pub fn parse_host(host_header: &str) -> &str {
 #[cfg(test)]
 mod route_test {
     use std::str::FromStr;
+    use std::sync::Arc;
     use super::{route, RoutingTable, Resolver};
     use crate::intern::{HandlerName, Authorizer as AuthorizerName};
+    use crate::intern::{DeprecationName, LogFormatName};
+    use crate::config::deprecation::Deprecation;
     use crate::config::routing::{HostPath, RouteDef};
     use crate::config::handlers::Handler;
     use crate::config::authorizers::Authorizer;
@@ -371,6 +524,14 @@ mod route_test {
         fn authorizer(&self, _: &AuthorizerName) -> Option<Authorizer> {
             Some(Authorizer::AllowAll)
         }
+        fn deprecation(&self, _: &DeprecationName)
+            -> Option<Arc<Deprecation>>
+        {
+            None
+        }
+        fn has_log_format(&self, _: &LogFormatName) -> bool {
+            true
+        }
     }
 
     fn table(table: Vec<(&'static str, &'static str, &'static str)>)
@@ -380,7 +541,11 @@ mod route_test {
             (HostPath::from_str(r).unwrap(), RouteDef {
                 handler: HandlerName::from(h),
                 authorizer: if a == "" { None }
-                    else { Some(AuthorizerName::from(a)) }
+                    else { Some(AuthorizerName::from(a)) },
+                listen_port: None,
+                deprecation: None,
+                log_format: None,
+                keep_alive: true,
             })
         }).collect::<Vec<_>>();
         RoutingTable::_create(items.iter().map(|&(ref x, ref y)| (x, y)),
@@ -544,4 +709,65 @@ mod route_test {
         assert_eq!(route_h("example.org", "/two", &table), None);
     }
 
+    #[test]
+    fn large_table() {
+        let mut items = vec![
+            ("*.example.com".to_string(), "star".to_string()),
+            ("*.example.com/static".to_string(), "static".to_string()),
+            ("*".to_string(), "any".to_string()),
+            ("big.example.com".to_string(), "big".to_string()),
+        ];
+        for i in 0..1000 {
+            items.push((format!("h{}.example.com", i), format!("h{}", i)));
+            items.push((format!("big.example.com/p{}", i),
+                        format!("p{}", i)));
+        }
+        items.push(("big.example.com/p1/sub".to_string(), "sub".to_string()));
+        let items = items.into_iter().map(|(r, h)| {
+            (HostPath::from_str(&r).unwrap(), RouteDef {
+                handler: HandlerName::from(&h[..]),
+                authorizer: None,
+                listen_port: None,
+                deprecation: None,
+                log_format: None,
+                keep_alive: true,
+            })
+        }).collect::<Vec<_>>();
+        let table = RoutingTable::_create(
+            items.iter().map(|&(ref x, ref y)| (x, y)), Fake).unwrap();
+        assert!(table.num_hosts() > super::LARGE_TABLE);
+
+        assert_eq!(route_h("h0.example.com", "/", &table),
+                   Some(("h0", "", "/")));
+        assert_eq!(route_h("h999.example.com", "/x?y", &table),
+                   Some(("h999", "", "/x?y")));
+        // exact hosts don't inherit paths of the star domain
+        assert_eq!(route_h("h999.example.com", "/static/x", &table),
+                   Some(("h999", "", "/static/x")));
+        assert_eq!(route_h("sub.h5.example.com", "/", &table),
+                   Some(("default", "", "/")));
+        assert_eq!(route_h("unknown.example.com", "/static", &table),
+                   Some(("static", "/static", "")));
+        assert_eq!(route_h("example.com", "/", &table),
+                   Some(("star", "", "/")));
+        assert_eq!(route_h("example.org", "/static", &table),
+                   Some(("any", "", "/static")));
+
+        assert_eq!(route_h("big.example.com", "/", &table),
+                   Some(("big", "", "/")));
+        assert_eq!(route_h("big.example.com", "/p10", &table),
+                   Some(("p10", "/p10", "")));
+        assert_eq!(route_h("big.example.com", "/p10/x/y", &table),
+                   Some(("p10", "/p10", "/x/y")));
+        assert_eq!(route_h("big.example.com", "/p10?q=/p1", &table),
+                   Some(("p10", "/p10", "?q=/p1")));
+        assert_eq!(route_h("big.example.com", "/p1/sub#frag", &table),
+                   Some(("sub", "/p1/sub", "#frag")));
+        assert_eq!(route_h("big.example.com", "/p1/subway", &table),
+                   Some(("p1", "/p1", "/subway")));
+        assert_eq!(route_h("big.example.com", "/p1000", &table),
+                   Some(("big", "", "/p1000")));
+        assert_eq!(route_h("big.example.com", "/static/x", &table),
+                   Some(("big", "", "/static/x")));
+    }
 }
@@ -1,9 +1,13 @@
+use std::sync::atomic::AtomicBool;
+
 use tokio_core::reactor::Handle;
 
 use crate::chat;
 use crate::config::ConfigCell;
 use crate::handlers::files;
 use crate::http_pools::HttpPools;
+use crate::incoming::{ConnectionLimit, RouteStatsMap};
+use crate::proxy::StaleCache;
 use self_meter_http::Meter;
 use crate::request_id::RequestId;
 use ns_router::Router;
@@ -13,11 +17,21 @@ pub struct Runtime {
     pub config: ConfigCell,
     pub handle: Handle,
     pub http_pools: HttpPools,
+    pub proxy_cache: StaleCache,
     pub session_pools: chat::SessionPools,
     pub disk_pools: files::DiskPools,
     pub meter: Meter,
     pub server_id: ServerId,
     pub resolver: Router,
+    /// Set when initialization is done (or `warmup-period` is over), until
+    /// then all requests are answered with `503 Service Unavailable`
+    pub ready: AtomicBool,
+    /// Set on `SIGTERM` when `shutdown-drain-period` is enabled, requests
+    /// are still served but readiness check fails
+    pub draining: AtomicBool,
+    pub connection_limit: ConnectionLimit,
+    pub route_stats: RouteStatsMap,
+    pub chat_reload: chat::ReloadTracker,
 }
 
 /// Runtime server identifier.
@@ -0,0 +1,126 @@
+use std::io::{self, Read};
+use std::os::unix::io::RawFd;
+use std::process::exit;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::Duration;
+
+use futures::Future;
+use libc::{self, c_int, c_void, signal, SIGTERM, sighandler_t};
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use mio::unix::EventedFd;
+use tokio_core::reactor::{Handle, PollEvented, Timeout};
+use tokio_io::io::read_exact;
+
+use crate::runtime::Runtime;
+
+
+/// Write end of the self-pipe, `-1` until the hook is installed
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Read end of the self-pipe, signal handler writes a byte there
+struct Pipe(RawFd);
+
+extern "C" fn on_sigterm(_: c_int) {
+    let fd = PIPE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        // write is async-signal-safe, and if pipe is full the signal
+        // is already reported anyway
+        unsafe { libc::write(fd, b"x".as_ptr() as *const c_void, 1) };
+    }
+}
+
+fn nonblocking_pipe() -> io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        for &fd in &fds {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+        }
+    }
+    Ok((fds[0], fds[1]))
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = unsafe {
+            libc::read(self.0, buf.as_mut_ptr() as *mut c_void, buf.len())
+        };
+        if n < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+}
+
+impl Evented for Pipe {
+    fn register(&self, poll: &Poll, token: Token, interest: Ready,
+        opts: PollOpt)
+        -> io::Result<()>
+    {
+        EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+    fn reregister(&self, poll: &Poll, token: Token, interest: Ready,
+        opts: PollOpt)
+        -> io::Result<()>
+    {
+        EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.0).deregister(poll)
+    }
+}
+
+/// Handles `SIGTERM`: marks runtime as draining and exits after
+/// `shutdown-drain-period`
+///
+/// Does nothing if drain period is zero, so process is killed by
+/// `SIGTERM` right away as usual.
+pub fn watch(runtime: &Arc<Runtime>, handle: &Handle) {
+    if runtime.config.get().shutdown_drain_period == Duration::new(0, 0) {
+        return;
+    }
+    let (rd, wr) = match nonblocking_pipe() {
+        Ok(pair) => pair,
+        Err(e) => {
+            error!("Can't create pipe for SIGTERM handler: {}", e);
+            return;
+        }
+    };
+    let pipe = match PollEvented::new(Pipe(rd), handle) {
+        Ok(pipe) => pipe,
+        Err(e) => {
+            error!("Can't watch pipe for SIGTERM handler: {}", e);
+            return;
+        }
+    };
+    PIPE.store(wr, Ordering::SeqCst);
+    unsafe {
+        signal(SIGTERM, on_sigterm as extern "C" fn(c_int) as sighandler_t);
+    }
+    let runtime = runtime.clone();
+    let h1 = handle.clone();
+    handle.spawn(read_exact(pipe, [0u8; 1])
+        .map_err(|e| error!("Error waiting for SIGTERM: {}", e))
+        .and_then(move |_| {
+            // period might be changed by config reload
+            let period = runtime.config.get().shutdown_drain_period;
+            if period == Duration::new(0, 0) {
+                warn!("Received SIGTERM, exiting");
+                exit(0);
+            }
+            warn!("Received SIGTERM, draining for {:?} before exit", period);
+            runtime.draining.store(true, Ordering::SeqCst);
+            Timeout::new(period, &h1)
+                .expect("can always add a timeout")
+                .map_err(|e| error!("Shutdown timer error: {}", e))
+        })
+        .map(|()| {
+            warn!("Drain period is over, exiting");
+            exit(0);
+        }));
+}
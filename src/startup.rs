@@ -1,10 +1,11 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use abstract_ns::HostResolve;
 use async_slot as slot;
 use futures::Stream;
-use futures::future::{Future};
+use futures::future::{Future, Either, ok, join_all};
 use futures_cpupool;
 use ns_router::{self, SubscribeExt};
 use ns_router::future::AddrStream;
@@ -13,17 +14,20 @@ use self_meter_http::Meter;
 use tk_http::server::Proto;
 use tk_http;
 use tk_listen::{BindMany, ListenExt};
-use tokio_core::reactor::{Handle};
+use tokio_core::reactor::{Handle, Timeout};
 use void::Void;
 
 use crate::config::listen::Listen;
 use crate::config::{ConfigCell};
-use crate::incoming::Router;
+use crate::incoming::{Router, ConnectionLimit, RouteStatsMap};
+use crate::incoming::{ConnectionAge, MaxAge, catch_connection_panic};
 use crate::chat;
 use crate::runtime::Runtime;
 use crate::http_pools::{HttpPools};
+use crate::proxy::StaleCache;
 use crate::handlers::files::{DiskPools};
 use crate::request_id;
+use crate::shutdown;
 
 
 pub struct State {
@@ -67,9 +71,36 @@ pub fn spawn_listener(addr_stream: AddrStream, handle: &Handle,
             }), handle)
         .sleep_on_error(r1.config.get().listen_error_timeout, &r1.handle)
         .map(move |(socket, saddr)| {
-             Proto::new(socket, &hcfg,
-                Router::new(saddr, r2.clone(), h1.clone()), &h1)
-             .map_err(|e| debug!("Http protocol error: {}", e))
+            let local_port = socket.local_addr().ok().map(|a| a.port());
+            let guard = match r2.config.get().max_connections_per_ip {
+                Some(max) => {
+                    match r2.connection_limit.acquire(saddr.ip(), max) {
+                        Some(guard) => Some(guard),
+                        None => {
+                            info!("Refusing connection from {}: \
+                                more than {} connections from this address",
+                                saddr, max);
+                            return Either::B(ok(()));
+                        }
+                    }
+                }
+                None => None,
+            };
+            let max_age = r2.config.get().max_connection_age;
+            // also used to close connections on `keep-alive=false` routes
+            let age = if max_age == Duration::new(0, 0) {
+                ConnectionAge::unlimited()
+            } else {
+                ConnectionAge::new(max_age)
+            };
+            let proto = Proto::new(age.wrap(socket), &hcfg,
+                Router::new(saddr, local_port, age.clone(),
+                            r2.clone(), h1.clone()), &h1)
+                .map_err(|e| debug!("Http protocol error: {}", e));
+            let conn = MaxAge::new(proto, &age, &h1);
+            let conn = catch_connection_panic(conn, saddr);
+            // guard is released when connection is closed either way
+            Either::A(conn.then(move |res| { drop(guard); res }))
         })
         .listen(root.max_connections)
         .map(move |()| panic!("Main listener exited"))
@@ -120,11 +151,17 @@ pub fn populate_loop(handle: &Handle, cfg: &ConfigCell, verbose: bool)
         config: cfg.clone(),
         handle: handle.clone(),
         http_pools: http_pools.clone(),
+        proxy_cache: StaleCache::new(),
         session_pools: session_pools.clone(),
         disk_pools: disk_pools.clone(),
         meter: meter,
         server_id: server_id,
         resolver: resolver.clone(),
+        ready: AtomicBool::new(false),
+        draining: AtomicBool::new(false),
+        connection_limit: ConnectionLimit::new(),
+        route_stats: RouteStatsMap::new(),
+        chat_reload: chat::ReloadTracker::new(),
     });
     let root = cfg.get();
 
@@ -139,10 +176,49 @@ pub fn populate_loop(handle: &Handle, cfg: &ConfigCell, verbose: bool)
             listen_rx.map_err(|()| -> Void { unreachable!() }), 80),
         handle, &runtime, verbose);
 
+    runtime.route_stats.update(&root.handlers);
     disk_pools.update(&root.disk_pools);
-    http_pools.update(&root.http_destinations, &resolver, handle);
+    http_pools.update(&root, &resolver, handle);
     session_pools.update(&root.session_pools, handle, &runtime);
     replication_session.update(&cfg.get().replication, handle, &runtime);
+
+    if root.warmup_period == Duration::new(0, 0) {
+        runtime.ready.store(true, Ordering::SeqCst);
+    } else {
+        // ready when every http destination has resolved at least one
+        // address, `warmup-period` limits how long we wait for that
+        let resolved = join_all(root.http_destinations.values()
+            .map(|dest| {
+                resolver.subscribe_many(&dest.addresses, 80)
+                .filter(|addr| addr.addresses_at(0).next().is_some())
+                .into_future()
+                .map(|_| ())
+                .map_err(|_| ())
+            })
+            .collect::<Vec<_>>());
+        let timeout = Timeout::new(root.warmup_period, handle)
+            .expect("can always add a timeout");
+        let r2 = runtime.clone();
+        handle.spawn(resolved.select2(timeout).then(move |res| {
+            match res {
+                Ok(Either::A(..)) => {
+                    info!("Initialization is done, serving requests");
+                }
+                Ok(Either::B(..)) => {
+                    warn!("Initialization is not done in warmup-period, \
+                        serving requests anyway");
+                }
+                Err(Either::A(..)) => unreachable!(),  // AddrStream is Void
+                Err(Either::B((e, _))) => {
+                    error!("Warmup timer error: {}", e);
+                }
+            }
+            r2.ready.store(true, Ordering::SeqCst);
+            Ok(())
+        }));
+    }
+    shutdown::watch(&runtime, handle);
+
     State {
         http_pools: http_pools,
         session_pools: session_pools,
@@ -157,11 +233,12 @@ pub fn populate_loop(handle: &Handle, cfg: &ConfigCell, verbose: bool)
 pub fn update_loop(state: &mut State, cfg: &ConfigCell, handle: &Handle) {
     state.listener_channel.swap(cfg.get().listen.clone())
         .map_err(|_| error!("Can't update listening sockets")).ok();
+    state.runtime.route_stats.update(&cfg.get().handlers);
     state.disk_pools.update(&cfg.get().disk_pools);
-    state.http_pools.update(&cfg.get().http_destinations,
-        &state.runtime.resolver, handle);
+    state.http_pools.update(&cfg.get(), &state.runtime.resolver, handle);
     state.session_pools.update(&cfg.get().session_pools,
         handle, &state.runtime);
+    state.runtime.chat_reload.update(&cfg.get());
     state.replication_session.update(&cfg.get().replication,
         handle, &state.runtime);
 }
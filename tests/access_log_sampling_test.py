@@ -0,0 +1,91 @@
+import asyncio
+import tempfile
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+debug-logging: true
+access-log-sample-rate: 2
+access-log-slow-request: 200ms
+routing:
+  localhost/empty.gif: empty_gif
+  localhost/proxy: proxy
+handlers:
+  empty_gif: !EmptyGif
+  proxy: !Proxy
+    destination: backend/
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def backend(port, loop):
+    """Responds with `500` for `/proxy/error`, and slowly for `/proxy/slow`
+    """
+
+    async def handle(reader, writer):
+        try:
+            head = await reader.readuntil(b'\r\n\r\n')
+        except asyncio.IncompleteReadError:
+            writer.close()
+            return
+        path = head.split(b' ')[1]
+        if path.startswith(b'/proxy/error'):
+            status = b'500 Internal Server Error'
+        else:
+            await asyncio.sleep(0.3, loop=loop)
+            status = b'200 OK'
+        writer.write(b'HTTP/1.1 ' + status + b'\r\n'
+                     b'Content-Length: 0\r\n'
+                     b'Connection: close\r\n'
+                     b'\r\n')
+        await writer.drain()
+        writer.close()
+
+    return await asyncio.start_server(handle, '127.0.0.1', port, loop=loop)
+
+
+async def test_sample_rate(custom_swindon, swindon_ports, raw_request, loop):
+    ports = swindon_ports['access_log_sampling']
+    port = ports['main']
+    server = await backend(ports['proxy'], loop)
+    try:
+        with tempfile.TemporaryFile() as log, \
+                custom_swindon(CONFIG, port, stdout=log,
+                               port=port, proxy_port=ports['proxy']):
+            for _ in range(100):
+                assert await raw_request(port, b'/empty.gif') == 200
+            for _ in range(10):
+                # unsupported transfer coding gives early 501
+                status = await raw_request(port, b'/empty.gif?error',
+                    b'Transfer-Encoding: gzip\r\n')
+                assert status == 501
+            for _ in range(10):
+                status = await raw_request(port, b'/proxy/error')
+                assert status == 500
+            for _ in range(4):
+                status = await raw_request(port, b'/proxy/slow')
+                assert status == 200
+
+            await asyncio.sleep(0.1, loop=loop)
+            log.seek(0)
+            lines = log.read().decode('utf-8').splitlines()
+    finally:
+        server.close()
+        await server.wait_closed()
+
+    ok = [line for line in lines
+          if '/empty.gif ' in line and line.endswith(' 200')]
+    not_implemented = [line for line in lines if line.endswith(' 501')]
+    upstream_errors = [line for line in lines
+                       if '/proxy/error ' in line and line.endswith(' 500')]
+    slow = [line for line in lines if '/proxy/slow ' in line]
+    assert 20 <= len(ok) <= 80
+    # server errors (even if they come from upstream) and slow requests
+    # are always logged
+    assert len(not_implemented) == 10
+    assert len(upstream_errors) == 10
+    assert len(slow) == 4
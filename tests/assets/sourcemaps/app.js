@@ -0,0 +1,2 @@
+console.log("hello");
+//# sourceMappingURL=app.js.map
@@ -0,0 +1,33 @@
+import aiohttp
+
+
+async def test_proxy_no_content(proxy_server, swindon):
+    url = swindon.url / 'proxy/no-content'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        req = await handler.request()
+        assert req.path == '/proxy/no-content'
+
+        resp, body = await handler.response(b'', status=204)
+        assert resp.status == 204
+        assert 'Content-Length' not in resp.headers
+        assert 'Transfer-Encoding' not in resp.headers
+        assert body == b''
+
+
+async def test_static_not_modified(swindon, loop):
+    url = swindon.url / 'static-file'
+    async with aiohttp.ClientSession(loop=loop) as s:
+        async with s.get(url) as resp:
+            assert resp.status == 200
+            if 'ETag' in resp.headers:
+                cond = {'If-None-Match': resp.headers['ETag']}
+            else:
+                cond = {'If-Modified-Since': resp.headers['Last-Modified']}
+            await resp.read()
+
+        async with s.get(url, headers=cond) as resp:
+            assert resp.status == 304
+            assert 'Content-Length' not in resp.headers
+            assert 'Transfer-Encoding' not in resp.headers
+            assert await resp.read() == b''
@@ -0,0 +1,32 @@
+async def test_html_page(swindon, get_request, static_request_method):
+    url = swindon.url / 'by-extension' / 'test.html'
+    resp, data = await get_request(url)
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'text/html'
+    assert resp.headers['X-Handler'] == 'page'
+    if static_request_method == 'GET':
+        assert data == b'Static file test\n'
+
+
+async def test_raw_png(swindon, get_request, static_request_method):
+    url = swindon.url / 'by-extension' / 'pixel.png'
+    resp, data = await get_request(url)
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'image/png'
+    assert resp.headers['X-Handler'] == 'raw'
+    if static_request_method == 'GET':
+        assert data.startswith(b'\x89PNG')
+
+
+async def test_extension_case(swindon, get_request, static_request_method):
+    url = swindon.url / 'by-extension' / 'test.HTML'
+    resp, data = await get_request(url)
+    assert resp.status == 200
+    assert resp.headers['X-Handler'] == 'page'
+
+
+async def test_query_ignored(swindon, get_request, static_request_method):
+    url = (swindon.url / 'by-extension' / 'pixel.png').with_query(x='a.html')
+    resp, data = await get_request(url)
+    assert resp.status == 200
+    assert resp.headers['X-Handler'] == 'raw'
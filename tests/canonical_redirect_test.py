@@ -0,0 +1,59 @@
+import aiohttp
+
+
+async def request(swindon, method, http_version, loop, host, headers={}):
+    url = swindon.url / 'some/path'
+    url = url.with_query(x='1')
+    kw = {"allow_redirects": False,
+          "headers": dict(headers, Host=host)}
+    async with aiohttp.ClientSession(version=http_version, loop=loop) as s:
+        async with s.request(method, url, **kw) as resp:
+            assert await resp.read() == b''
+            return resp
+
+
+async def test_upgrade_scheme(swindon, proxy_request_method, http_version,
+        debug_routing, loop):
+    resp = await request(swindon, proxy_request_method, http_version, loop,
+        'canonical.example.com')
+    assert resp.status == 301
+    assert resp.headers.getall("Location") == [
+        "https://canonical.example.com/some/path?x=1"
+        ]
+    if debug_routing:
+        assert 'X-Swindon-Route' in resp.headers
+    else:
+        assert 'X-Swindon-Route' not in resp.headers
+
+
+async def test_wrong_host(swindon, proxy_request_method, http_version, loop):
+    resp = await request(swindon, proxy_request_method, http_version, loop,
+        'www.canonical.example.com', {'X-Forwarded-Proto': 'https'})
+    assert resp.status == 301
+    assert resp.headers.getall("Location") == [
+        "https://canonical.example.com/some/path?x=1"
+        ]
+
+
+async def test_canonical(swindon, http_version, loop):
+    url = swindon.url / 'some/path'
+    kw = {"allow_redirects": False,
+          "headers": {'Host': 'canonical.example.com',
+                      'X-Forwarded-Proto': 'https'}}
+    async with aiohttp.ClientSession(version=http_version, loop=loop) as s:
+        async with s.get(url, **kw) as resp:
+            assert resp.status == 404
+            assert 'Location' not in resp.headers
+
+
+async def test_untrusted_scheme(swindon, http_version, loop):
+    url = swindon.url / 'untrusted'
+    kw = {"allow_redirects": False,
+          "headers": {'Host': 'canonical.example.com',
+                      'X-Forwarded-Proto': 'https'}}
+    async with aiohttp.ClientSession(version=http_version, loop=loop) as s:
+        async with s.get(url, **kw) as resp:
+            assert resp.status == 301
+            assert resp.headers.getall("Location") == [
+                "https://canonical.example.com/untrusted"
+                ]
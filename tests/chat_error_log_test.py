@@ -0,0 +1,64 @@
+import asyncio
+import tempfile
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/chat: chat
+handlers:
+  chat: !SwindonLattice
+    session-pool: pool
+    connection-id-in-errors: true
+    message-handlers:
+      "*": backend/
+session-pools:
+  pool:
+    listen:
+    - 127.0.0.1:${pool_port}
+    inactivity-handlers: []
+http-destinations:
+  backend:
+    override-host-header: swindon.internal
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def test_request_id_in_error(custom_swindon, swindon_ports,
+                                   proxy_server, user_id, loop):
+    ports = swindon_ports['chat_errors']
+    url = 'http://localhost:{}/chat'.format(ports['main'])
+    with tempfile.TemporaryFile() as log, \
+            custom_swindon(CONFIG, ports['main'],
+                           rust_log='swindon=info', stderr=log,
+                           port=ports['main'],
+                           pool_port=ports['session_pool_1'],
+                           proxy_port=ports['proxy']):
+
+        async with proxy_server(port=ports['proxy']) as proxy:
+            handler = proxy.swindon_lattice(url, timeout=1)
+            req = await handler.request()
+            assert req.path == '/swindon/authorize_connection'
+            meta = (await req.json())[0]
+            connection_id = meta['connection_id']
+            ws = await handler.json_response({"user_id": user_id})
+            hello = await ws.receive_json()
+            assert hello == ['hello', {}, {'user_id': user_id}]
+
+            await ws.send_json(
+                ['swindon.call', {'request_id': 'abc'}, [], {}])
+            msg = await ws.receive_json()
+            assert msg == [
+                'error',
+                {'request_id': 'abc', 'error_kind': 'validation_error',
+                 'connection_id': connection_id},
+                'invalid metod']
+
+        await asyncio.sleep(0.1, loop=loop)
+        log.seek(0)
+        lines = [line for line in log.read().decode('utf-8').splitlines()
+                 if connection_id in line]
+        assert len(lines) == 1
+        assert 'abc' in lines[0]
@@ -0,0 +1,95 @@
+import asyncio
+import string
+
+from aiohttp import WSMsgType
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/chat: chat
+handlers:
+  chat: !SwindonLattice
+    session-pool: pool
+    on-reload: ${policy}
+    message-handlers:
+      "*": backend/${suffix}
+session-pools:
+  pool:
+    listen:
+    - 127.0.0.1:${pool_port}
+    inactivity-handlers: []
+http-destinations:
+  backend:
+    override-host-header: ${host}
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+# Config is checked for updates every 10 seconds
+RELOAD_TIME = 12
+
+
+def write_config(path, **options):
+    with open(path, 'wt') as f:
+        f.write(string.Template(CONFIG).substitute(**options))
+
+
+async def reload_config(custom_swindon, swindon_ports,
+                        proxy_server, user_id, loop, name, policy,
+                        **changes):
+    ports = swindon_ports[name]
+    url = 'http://localhost:{}/chat'.format(ports['main'])
+    options = dict(port=ports['main'], pool_port=ports['session_pool_1'],
+                   proxy_port=ports['proxy'], policy=policy,
+                   suffix='', host='swindon.internal')
+    with custom_swindon(CONFIG, ports['main'], **options) as swindon:
+        async with proxy_server(port=ports['proxy']) as proxy:
+            handler = proxy.swindon_lattice(url, timeout=1)
+            req = await handler.request()
+            assert req.path == '/swindon/authorize_connection'
+            ws = await handler.json_response({"user_id": user_id})
+            hello = await ws.receive_json()
+            assert hello == ['hello', {}, {'user_id': user_id}]
+
+            options.update(changes)
+            write_config(swindon.config, **options)
+            try:
+                msg = await ws.receive(timeout=RELOAD_TIME)
+            except asyncio.TimeoutError:
+                msg = None
+            return msg, ws.closed, ws.close_code
+
+
+async def test_keep(custom_swindon, swindon_ports,
+                    proxy_server, user_id, loop):
+    msg, closed, _ = await reload_config(custom_swindon, swindon_ports,
+        proxy_server, user_id, loop, 'chat_reload_keep', 'keep',
+        suffix='v2')
+    assert msg is None
+    assert not closed
+
+
+async def test_reconnect(custom_swindon, swindon_ports,
+                         proxy_server, user_id, loop):
+    msg, _, close_code = await reload_config(custom_swindon, swindon_ports,
+        proxy_server, user_id, loop, 'chat_reload_reconnect', 'reconnect',
+        suffix='v2')
+    assert msg is not None
+    assert msg.type == WSMsgType.CLOSE
+    assert msg.data == 1001
+    assert msg.extra == 'config_reloaded'
+    assert close_code == 1001
+
+
+async def test_reconnect_destination(custom_swindon, swindon_ports,
+                                     proxy_server, user_id, loop):
+    # handler settings are the same, but http destination is changed
+    msg, _, close_code = await reload_config(custom_swindon, swindon_ports,
+        proxy_server, user_id, loop, 'chat_reload_destination', 'reconnect',
+        host='swindon2.internal')
+    assert msg is not None
+    assert msg.type == WSMsgType.CLOSE
+    assert msg.extra == 'config_reloaded'
+    assert close_code == 1001
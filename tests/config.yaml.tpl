@@ -17,6 +17,9 @@ listen:
 # listen_error_timeout: 100ms
 
 server_name: swindon/func-tests
+default-host: localhost
+# method tests send some non-standard methods to non-proxy handlers too
+extension-methods: [MKCOL, UPDATED, XXX]
 debug-routing: *DEBUG_ROUTING
 
 # Configure all possible routing?
@@ -27,6 +30,12 @@ routing:
   localhost/empty-w-headers.gif: empty_gif_w_headers
   localhost/empty-w-content-length.gif: empty_gif_w_clen
 
+  ### !RobotsTxt routes ###
+  localhost/robots.txt: robots_txt
+
+  ### !Discovery routes ###
+  localhost/chat-config.json: discovery
+
   ### !SingleFile routes ###
   localhost/static-file: single_file
   localhost/missing-file: missing_file
@@ -44,11 +53,17 @@ routing:
   localhost/static-w-index: static_w_index
   localhost/static-wo-index: static_wo_index
   localhost/static-autoindex: static_autoindex
+  localhost/static-root-index: static_root_index
+  localhost/static-dir-redirect: static_dir_redirect
   localhost/static-no-permission: static_no_permission
+  localhost/static-maps-public: static_maps_public
+  localhost/static-maps-local: static_maps_local
+  "*.sites.example.com": static_w_host_root
 
   ### !VersionedStatic routes ###
   localhost/versioned: versioned
   localhost/versioned-fallback: versioned-fallback
+  localhost/versioned-restricted: versioned_restricted
 
   # TODO: add overlapping routes:
   #   /static: !Proxy & /static/file: !SingleFile
@@ -60,6 +75,12 @@ routing:
   localhost/proxy-w-request-id: proxy_w_request_id
   localhost/proxy-w-host: proxy_w_host
   localhost/proxy-w-timeout: proxy_w_timeout
+  localhost/proxy-w-stale: proxy_w_stale
+  localhost/proxy-w-stale-no-age: proxy_w_stale_no_age
+  localhost/proxy-w-short-stale: proxy_w_short_stale
+  localhost/proxy-w-max-response-size: proxy_w_max_response_size
+  localhost/proxy-w-shadow: proxy_w_shadow
+  localhost/proxy-w-merge-slashes: proxy_w_merge_slashes
 
   ### !SwindonLattice compatibility routes ###
   localhost/swindon-chat: swindon_chat
@@ -70,20 +91,48 @@ routing:
   localhost/swindon-lattice: swindon_lattice
   localhost/swindon-lattice-w-timeouts: swindon_lattice_w_timeouts
   localhost/swindon-lattice-w-client-timeout: swindon_lattice_w_client_timeout
+  localhost/swindon-lattice-w-rate-limit: swindon_lattice_w_rate_limit
+  localhost/swindon-lattice-w-rate-limit-close: swindon_lattice_w_rate_limit_close
+  localhost/swindon-lattice-w-handshake-timeout: swindon_lattice_w_handshake_timeout
+  localhost/swindon-lattice-w-auth-limit: swindon_lattice_w_auth_limit
+  localhost/swindon-lattice-w-auth-timeout: swindon_lattice_w_auth_timeout
+  localhost/swindon-lattice-w-subscription-limit: swindon_lattice_w_subscription_limit
+  localhost/swindon-lattice-w-unique-ids: swindon_lattice_w_unique_ids
+  localhost/swindon-lattice-w-json-limits: swindon_lattice_w_json_limits
+  localhost/swindon-lattice-w-orphan-log: swindon_lattice_w_orphan_log
+  localhost/swindon-lattice-w-reserved-reject: swindon_lattice_w_reserved_reject
+  localhost/swindon-lattice-w-reserved-log: swindon_lattice_w_reserved_log
+  localhost/swindon-lattice-w-discovery: swindon_lattice_w_discovery
 
   ### !WebsocketEcho routes ###
   localhost/websocket-echo: websocket_echo
 
+  ### !QueryEcho routes ###
+  localhost/query-echo: query_echo
+
   ### !BaseRedirect routes ###
   example.com: base_redirect
 
   ### !StripWWWRedirect routes ###
   www.example.com: strip_www_redirect
 
+  ### !CanonicalRedirect routes ###
+  canonical.example.com: canonical_redirect @trusted-proxy
+  canonical.example.com/untrusted: canonical_redirect
+  www.canonical.example.com: canonical_redirect @trusted-proxy
+
   ### !Authorized routes ###
   localhost/auth/local: empty_gif @only-127-0-0-1
   localhost/auth/by-header: empty_gif @by-header
 
+  ### !ByExtension routes ###
+  localhost/by-extension: by_extension
+
+  ### Deprecated routes ###
+  localhost/deprecated.gif: empty_gif deprecation=old-gif
+  localhost/deprecated-plain.gif: empty_gif deprecation=plain
+  localhost/no-keep-alive.gif: empty_gif keep-alive=false
+
 # Configure all possible handlers?
 handlers:
   # Allowed handlers are: SwindonLattice, Static, SingleFile, Proxy,
@@ -99,6 +148,13 @@ handlers:
       Content-Type: image/other
       Content-Length: 100500
 
+  ### RobotsTxt handlers ###
+  robots_txt: !RobotsTxt
+    max-age: 1 hour
+    content: |
+      User-agent: *
+      Disallow: /private/
+
   ### SingleFile handlers ###
 
   single_file: !SingleFile
@@ -141,6 +197,18 @@ handlers:
   static_w_hostname: !Static
     mode: with_hostname
     path: ${TESTS_DIR}/assets/
+  static_w_host_root: !Static
+    mode: relative_to_domain_root
+    path: ${TESTS_DIR}/assets/sites/$$host
+  static_maps_public: !Static
+    path: ${TESTS_DIR}/assets/sourcemaps
+    generate-index: true
+    restrict-files:
+      "*.map": goog
+  static_maps_local: !Static
+    path: ${TESTS_DIR}/assets/sourcemaps
+    restrict-files:
+      "*.map": only-127-0-0-1
   static_w_index: !Static
     path: ${TESTS_DIR}/assets/index
     index-files:
@@ -150,6 +218,16 @@ handlers:
   static_autoindex: !Static
     path: ${TESTS_DIR}/assets
     generate-index: true
+  static_root_index: !Static
+    path: ${TESTS_DIR}/assets
+    index-files: [index.html, static_file.html]
+    root-index: index
+    dir-index: listing
+  static_dir_redirect: !Static
+    path: ${TESTS_DIR}/assets
+    index-files: [index.html]
+    root-index: forbidden
+    dir-index: redirect
   static_no_permission: !Static
     path: /tmp
 
@@ -169,6 +247,16 @@ handlers:
     version-chars: lowercase_hex
     fallback-to-plain: always
 
+  versioned_restricted: !VersionedStatic
+    versioned-root: ${TESTS_DIR}/hashed
+    plain-root: ${TESTS_DIR}/assets
+    version-arg: "r"
+    version-split: [2, 6]
+    version-chars: lowercase_hex
+    fallback-to-plain: always
+    restrict-files:
+      "*.html": goog
+
   ### Proxy handlers ###
 
   proxy: !Proxy
@@ -184,6 +272,28 @@ handlers:
     destination: proxy_host
   proxy_w_timeout: !Proxy
     destination: proxy_timeout
+  proxy_w_stale: !Proxy
+    destination: proxy_dest/
+    serve-stale:
+      max-stale: 1 hour
+  proxy_w_stale_no_age: !Proxy
+    destination: proxy_dest/
+    serve-stale:
+      max-stale: 1 hour
+      age-header: false
+  proxy_w_short_stale: !Proxy
+    destination: proxy_dest/
+    serve-stale:
+      max-stale: 1s
+  proxy_w_max_response_size: !Proxy
+    destination: proxy_dest/
+    max-response-size: 100
+  proxy_w_shadow: !Proxy
+    destination: proxy_dest/
+    shadow-upstream: proxy_dest/shadow
+  proxy_w_merge_slashes: !Proxy
+    destination: proxy_dest/
+    merge-slashes: true
   swindon_proxy: !Proxy
     destination: swindon_http_dest
 
@@ -225,10 +335,110 @@ handlers:
     http_route: swindon_proxy
     message_handlers:
       "*": swindon_lattice_w_timeout/
+  swindon_lattice_w_rate_limit: !SwindonLattice
+    session_pool: swindon_pool_new
+    message_handlers:
+      "*": swindon_lattice_dest/
+    message_rate_limit:
+      rate: 1
+      burst: 2
+  swindon_lattice_w_rate_limit_close: !SwindonLattice
+    session_pool: swindon_pool_new
+    message_handlers:
+      "*": swindon_lattice_dest/
+    message_rate_limit:
+      rate: 1
+      burst: 2
+      on_exceed: close
+  swindon_lattice_w_handshake_timeout: !SwindonLattice
+    session_pool: swindon_pool_new
+    handshake_timeout: 1s
+    message_handlers:
+      "*": swindon_lattice_dest/
+  swindon_lattice_w_auth_limit: !SwindonLattice
+    session_pool: swindon_pool_new
+    auth_timeout: 1s
+    max_auth_data_size: 100
+    message_handlers:
+      "*": swindon_lattice_dest/
+  swindon_lattice_w_auth_timeout: !SwindonLattice
+    session_pool: swindon_pool_new
+    auth_timeout: 1s
+    auth_timeout_close_code: 4504
+    message_handlers:
+      "*": swindon_lattice_dest/
+  swindon_lattice_w_subscription_limit: !SwindonLattice
+    session_pool: swindon_pool_new
+    max_subscriptions_per_connection: 2
+    message_handlers:
+      "*": swindon_lattice_dest/
+  swindon_lattice_w_unique_ids: !SwindonLattice
+    session_pool: swindon_pool_new
+    reject_duplicate_request_id: true
+    message_handlers:
+      "*": swindon_lattice_dest/
+
+  swindon_lattice_w_json_limits: !SwindonLattice
+    session_pool: swindon_pool_new
+    max_json_depth: 4
+    max_json_elements: 8
+    message_handlers:
+      "*": swindon_lattice_dest/
+
+  swindon_lattice_w_orphan_log: !SwindonLattice
+    session_pool: swindon_pool_new
+    on_orphaned_response: log
+    message_handlers:
+      "*": swindon_lattice_dest/
+
+  swindon_lattice_w_reserved_reject: !SwindonLattice
+    session_pool: swindon_pool_new
+    reserved_meta_policy: reject
+    reserved_meta_keys: [user_token]
+    message_handlers:
+      "*": swindon_lattice_dest/
+
+  swindon_lattice_w_reserved_log: !SwindonLattice
+    session_pool: swindon_pool_new
+    reserved_meta_policy: log
+    reserved_meta_keys: [user_token]
+    message_handlers:
+      "*": swindon_lattice_dest/
+
+  swindon_lattice_w_discovery: !SwindonLattice
+    session_pool: swindon_pool_new
+    message_rate_limit:
+      rate: 10
+      burst: 20
+    message_handlers:
+      "*": swindon_lattice_dest/
+
+  ### Discovery handlers ###
+  discovery: !Discovery
+    chat: swindon_lattice_w_discovery
+    max-age: 1 min
+
+  ### ByExtension handlers ###
+  by_extension: !ByExtension
+    extensions:
+      html: by_extension_page
+    default: by_extension_raw
+  by_extension_page: !SingleFile
+    path: ${TESTS_DIR}/assets/static_file.html
+    content-type: text/html
+    extra-headers:
+      X-Handler: page
+  by_extension_raw: !Static
+    path: ${TESTS_DIR}/assets/
+    extra-headers:
+      X-Handler: raw
 
   ### WebsocketEcho handlers ###
   websocket_echo: !WebsocketEcho
 
+  ### QueryEcho handlers ###
+  query_echo: !QueryEcho
+
   ### BaseRedirect handler ###
 
   base_redirect: !BaseRedirect
@@ -237,6 +447,11 @@ handlers:
   ### StripWWWRedirect handler
   strip_www_redirect: !StripWWWRedirect
 
+  ### CanonicalRedirect handler
+  canonical_redirect: !CanonicalRedirect
+    host: canonical.example.com
+    scheme: https
+
 session-pools:
   swindon_pool_old:
     listen:
@@ -324,11 +539,24 @@ authorizers:
   only-127-0-0-1: !SourceIp
     allowed-network: only-127-0-0-1
 
+  trusted-proxy: !SourceIp
+    allowed-network: only-127-0-0-1
+    accept-forwarded-headers-from: only-127-0-0-1
+
   by-header: !SourceIp
     allowed-network: goog
     forwarded-ip-header: X-Real-Ip
     accept-forwarded-headers-from: only-127-0-0-1
 
+deprecations:
+
+  old-gif:
+    deprecated-since: 2024-06-01
+    sunset: 2030-01-01T12:00:00Z
+    link: https://example.com/docs/new-gif
+
+  plain: {}
+
 networks:
   only-127-0-0-1:
   - 127.0.0.1
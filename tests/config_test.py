@@ -348,3 +348,147 @@ def test_bad_mixins(check_config):
         '''))
     assert ('app1.yaml" has handler named "handler" without prefix "app1-"'
         ) in err
+
+def test_route_check(route_check):
+    cfg = """
+        routing:
+            localhost: root
+            localhost/static: static
+            localhost/admin: admin @local
+        handlers:
+            root: !EmptyGif
+            static: !EmptyGif
+            admin: !EmptyGif
+        authorizers:
+            local: !SourceIp
+                allowed-network: local
+        networks:
+            local:
+            - 127.0.0.1
+    """
+    out = route_check(cfg, 'localhost', '/static/js/app.js')
+    assert out == (
+        'GET localhost/static/js/app.js\n'
+        'handler: static\n'
+        'authorizer: default\n'
+        'prefix: "/static"\n'
+        'suffix: "/js/app.js"\n')
+
+    out = route_check(cfg, 'localhost:8080', '/admin', method='POST')
+    assert out == (
+        'POST localhost/admin\n'
+        'handler: admin\n'
+        'authorizer: local\n'
+        'prefix: "/admin"\n'
+        'suffix: ""\n')
+
+    out = route_check(cfg, 'example.com', '/static', returncode=1)
+    assert 'no route' in out
+
+
+def test_route_check_like_server(route_check):
+    cfg = """
+        listen:
+        - 127.0.0.1:8080
+        - 127.0.0.1:8081
+        extension-methods: [PROPFIND]
+        routing:
+            localhost: root
+            localhost/internal: internal listen-port=8081
+            localhost/proxy: proxy
+        handlers:
+            root: !EmptyGif
+            internal: !EmptyGif
+            proxy: !Proxy
+                destination: backend/
+        http-destinations:
+            backend:
+                addresses:
+                - 127.0.0.1:8000
+    """
+    out = route_check(cfg, 'localhost', '/internal', port=8081)
+    assert 'handler: internal\n' in out
+
+    out = route_check(cfg, 'localhost', '/internal', port=8080, returncode=1)
+    assert 'no route on this port (404 Not Found)' in out
+
+    out = route_check(cfg, 'localhost', '/internal', returncode=1)
+    assert 'no route on this port (404 Not Found)' in out
+
+    out = route_check(cfg, 'localhost', '/', method='PROPFIND')
+    assert 'handler: root\n' in out
+
+    out = route_check(cfg, 'localhost', '/', method='get', returncode=1)
+    assert out == (
+        'get localhost/\n'
+        'handler: root\n'
+        'unknown method (501 Not Implemented)\n')
+
+    out = route_check(cfg, 'localhost', '/proxy', method='get')
+    assert 'handler: proxy\n' in out
+
+
+def test_route_limits(check_config):
+    cfg = """
+        warn-routes-above: 2
+        routing:
+          localhost: gif
+          localhost/a: gif
+          localhost/b: gif
+        handlers:
+          gif: !EmptyGif
+    """
+    err = check_config(cfg, returncode=0)
+    assert ("3 routes defined, which is more than 2 (`warn-routes-above`)"
+            in err)
+
+    err = check_config(cfg.replace('warn-routes-above', 'max-routes'))
+    assert "3 routes defined, but `max-routes` is 2" in err
+
+    err = check_config("""
+        max-handlers: 1
+        routing:
+          localhost: gif
+        handlers:
+          gif: !EmptyGif
+          other: !EmptyGif
+    """)
+    assert "3 handlers defined, but `max-handlers` is 1" in err
+
+
+def test_route_listen_port(check_config):
+    cfg = """
+        listen:
+        - 127.0.0.1:8080
+        - 127.0.0.1:{port}
+        routing:
+          localhost: gif
+          localhost/ws: gif listen-port=8081
+        handlers:
+          gif: !EmptyGif
+    """
+    assert check_config(cfg.format(port=8081), returncode=0) == ''
+
+    err = check_config(cfg.format(port=8082))
+    assert ("Route localhost/ws is bound to port 8081, "
+            "but there is no such port in `listen`" in err)
+
+
+def test_route_log_format(check_config):
+    cfg = """
+        routing:
+          localhost: gif ->short
+        handlers:
+          gif: !EmptyGif
+        log-formats:
+          short:
+            template: "{{ request.method }} {{ response.status_code }}"
+    """
+    assert check_config(cfg, returncode=0) == ''
+
+    err = check_config(cfg.replace('->short', '->missing'))
+    assert 'log format "missing" not found' in err
+
+    err = check_config(cfg.replace('response.status_code',
+                                   'response.upstream_time'))
+    assert "log format refers to unknown variable" in err
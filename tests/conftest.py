@@ -140,6 +140,7 @@ def assert_headers(headers, debug_routing):
 
 
 SwindonInfo = namedtuple('SwindonInfo', 'proc url proxy api api2 api3 api4')
+CustomSwindon = namedtuple('CustomSwindon', 'proc config')
 
 
 @pytest.fixture(scope='session')
@@ -313,16 +314,7 @@ def run_swindon(_proc, bin, config, log, *wait_ports, **options):
                  env={'RUST_LOG': log,
                       'RUST_BACKTRACE': os.environ.get('RUST_BACKTRACE', '0')},
                  )
-    wait_ports = set(wait_ports)
-    while wait_ports:
-        port = wait_ports.pop()
-        with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
-            try:
-                s.connect(('127.0.0.1', port))
-            except ConnectionRefusedError:
-                wait_ports.add(port)
-                continue
-            break
+    _wait_ports(proc, wait_ports)
 
     url = yarl.URL('http://localhost:{swindon_port}'.format(**options))
     proxy = yarl.URL('http://localhost:{proxy_port}'.format(**options))
@@ -337,6 +329,74 @@ def run_swindon(_proc, bin, config, log, *wait_ports, **options):
         os.remove(fname)
 
 
+def _wait_ports(proc, ports):
+    ports = set(ports)
+    while ports:
+        assert proc.poll() is None, \
+            "swindon exited with code {}".format(proc.returncode)
+        port = ports.pop()
+        with socket.socket(socket.AF_INET, socket.SOCK_STREAM) as s:
+            try:
+                s.connect(('127.0.0.1', port))
+            except ConnectionRefusedError:
+                ports.add(port)
+                time.sleep(0.01)
+
+
+@pytest.fixture(scope='module')
+def custom_swindon(_proc, swindon_bin, TESTS_DIR):
+    """Runs swindon with the config given by the test
+
+    This is for tests which need root options that would change behavior
+    of the shared `config.yaml.tpl`. Config and mixin `files` are
+    templates in the same format as `config.yaml.tpl`, substituted with
+    `options` (and `TESTS_DIR`). Process is stopped on exit.
+    """
+    @contextmanager
+    def run(config, *wait_ports, files={}, rust_log=None,
+            stdout=None, stderr=None, **options):
+        options.setdefault('TESTS_DIR', TESTS_DIR)
+        config = string.Template(config).substitute(**options)
+        files = {name: string.Template(text).substitute(**options)
+                 for name, text in files.items()}
+        env = dict(os.environ)
+        if rust_log is not None:
+            env['RUST_LOG'] = rust_log
+        with _write_configs(config, files) as main:
+            proc = _proc(swindon_bin, '--config', main,
+                         env=env, stdout=stdout, stderr=stderr)
+            try:
+                _wait_ports(proc, wait_ports)
+                yield CustomSwindon(proc, main)
+            finally:
+                proc.terminate()
+                proc.wait()
+    return run
+
+
+@pytest.fixture
+def raw_request(loop):
+    """Sends GET request with `extra` headers and returns status code
+
+    Request is written as is, so it can have headers which aiohttp
+    wouldn't send. Connection is closed after the response.
+    """
+    async def request(port, path, extra=b''):
+        reader, writer = await asyncio.open_connection('127.0.0.1', port,
+                                                       loop=loop)
+        try:
+            writer.write(b'GET ' + path + b' HTTP/1.1\r\n'
+                         b'Host: localhost\r\n' + extra +
+                         b'Connection: close\r\n'
+                         b'\r\n')
+            status = await asyncio.wait_for(reader.readline(), 1)
+            await reader.read()
+            return int(status.split()[1])
+        finally:
+            writer.close()
+    return request
+
+
 @pytest.fixture()
 def check_config(request, swindon_bin):
     return partial(_check_config, __swindon_bin=swindon_bin)
@@ -347,6 +407,11 @@ def check_fingerprint(swindon_bin):
     return partial(_check_fingerprint, __swindon_bin=swindon_bin)
 
 
+@pytest.fixture()
+def route_check(swindon_bin):
+    return partial(_route_check, __swindon_bin=swindon_bin)
+
+
 @contextmanager
 def _write_configs(cfg, files={}):
     cfg = textwrap.dedent(cfg)
@@ -392,6 +457,28 @@ def _check_fingerprint(cfg='', files={}, *, __swindon_bin):
         return res.stdout
 
 
+def _route_check(cfg, host, path='/', method='GET', returncode=0, *,
+                 port=None, __swindon_bin):
+    port_args = [] if port is None else ['--port', str(port)]
+    with _write_configs(cfg) as main:
+        res = subprocess.run([
+            __swindon_bin,
+            '--test-route',
+            '--host', host,
+            '--path', path,
+            '--method', method,
+            ] + port_args + [
+            '--config',
+            main,
+            ],
+            stdout=subprocess.PIPE,
+            stderr=subprocess.PIPE,
+            timeout=15,
+            )
+        assert res.returncode == returncode, res
+        return res.stdout.decode('utf-8')
+
+
 def _run_check(bin, file, returncode):
     res = subprocess.run([
         bin,
@@ -689,6 +776,8 @@ class _WSGIRequest:
         v = request.environ['SERVER_PROTOCOL']
         self._version = HttpVersion(*map(int, v.split('/')[1].split('.')))
         self._headers = CIMultiDictProxy(CIMultiDict(list(request.headers)))
+        self._peer_port = int(request.environ['REMOTE_PORT'])
+        self._raw_path = request.environ['REQUEST_URI']
         self._data = request.get_data()
         # XXX: werkzeug's form data returns dict of lists
         self._form = {k: v[0] if len(v) == 1 else v
@@ -710,6 +799,14 @@ class _WSGIRequest:
     def headers(self):
         return self._headers
 
+    @property
+    def peer_port(self):
+        return self._peer_port
+
+    @property
+    def raw_path(self):
+        return self._raw_path
+
     async def read(self):
         return self._data
 
@@ -753,6 +850,8 @@ def proxy_server(request, swindon, loop):
 
         __aexit__ = server.__aexit__
         send = server.send
+        wait_request = server.wait_request
+        set_response = server.set_response
         swindon_chat = server.start_ws_old
         swindon_lattice = server.start_ws
 
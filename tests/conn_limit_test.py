@@ -0,0 +1,64 @@
+import asyncio
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+max-connections-per-ip: 2
+routing:
+  localhost: empty_gif
+handlers:
+  empty_gif: !EmptyGif
+"""
+
+REQUEST = b'GET / HTTP/1.1\r\nHost: localhost\r\n\r\n'
+
+
+async def connect(port, loop, local_addr=None):
+    return await asyncio.open_connection('127.0.0.1', port,
+        local_addr=local_addr, loop=loop)
+
+
+async def request_ok(reader, writer):
+    writer.write(REQUEST)
+    status = await asyncio.wait_for(reader.readline(), 1)
+    assert status.startswith(b'HTTP/1.1 200 ')
+    while await reader.readline() != b'\r\n':
+        pass
+    await reader.readexactly(26)
+
+
+async def test_max_connections_per_ip(custom_swindon, swindon_ports, loop):
+    port = swindon_ports['conn_limit']['main']
+    with custom_swindon(CONFIG, port, port=port):
+        # let swindon release the slot of the connection made while
+        # waiting for the port
+        await asyncio.sleep(0.1, loop=loop)
+
+        conns = []
+        for _ in range(2):
+            reader, writer = await connect(port, loop)
+            await request_ok(reader, writer)
+            conns.append(writer)
+
+        # connection is accepted by kernel but closed by swindon at once
+        reader, writer = await connect(port, loop)
+        writer.write(REQUEST)
+        assert await asyncio.wait_for(reader.read(), 1) == b''
+        writer.close()
+
+        # other address is unaffected
+        reader, writer = await connect(port, loop,
+            local_addr=('127.0.0.2', 0))
+        await request_ok(reader, writer)
+        writer.close()
+
+        # slot is released when connection is closed
+        conns.pop().close()
+        await asyncio.sleep(0.1, loop=loop)
+        reader, writer = await connect(port, loop)
+        await request_ok(reader, writer)
+        writer.close()
+
+        for w in conns:
+            w.close()
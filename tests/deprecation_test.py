@@ -0,0 +1,29 @@
+import datetime
+from email.utils import parsedate_to_datetime
+
+
+async def test_deprecated_route(swindon, get_request):
+    resp, data = await get_request(swindon.url / 'deprecated.gif')
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'image/gif'
+    assert resp.headers['Deprecation'] == '@1717200000'
+    sunset = parsedate_to_datetime(resp.headers['Sunset'])
+    assert sunset == datetime.datetime(2030, 1, 1, 12, 0, 0,
+                                       tzinfo=datetime.timezone.utc)
+    assert resp.headers['Link'] == (
+        '<https://example.com/docs/new-gif>; rel="deprecation"')
+
+
+async def test_deprecation_defaults(swindon, get_request):
+    resp, data = await get_request(swindon.url / 'deprecated-plain.gif')
+    assert resp.status == 200
+    assert resp.headers['Deprecation'] == 'true'
+    assert 'Sunset' not in resp.headers
+    assert 'Link' not in resp.headers
+
+
+async def test_not_deprecated(swindon, get_request):
+    resp, data = await get_request(swindon.url / 'empty.gif')
+    assert resp.status == 200
+    assert 'Deprecation' not in resp.headers
+    assert 'Sunset' not in resp.headers
@@ -0,0 +1,77 @@
+import json
+
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${http_port}
+- 127.0.0.1:${chat_port}
+routing:
+  localhost/chat: chat listen-port=${chat_port}
+  localhost/chat-config.json: discovery
+handlers:
+  chat: !SwindonLattice
+    session-pool: pool
+    message-handlers:
+      "*": backend/
+  discovery: !Discovery
+    chat: chat
+session-pools:
+  pool:
+    listen:
+    - 127.0.0.1:${pool_port}
+    inactivity-handlers: []
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def test_document(swindon, get_request, static_request_method):
+    resp, data = await get_request(swindon.url / 'chat-config.json')
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'application/json'
+    assert resp.headers['Cache-Control'] == 'public, max-age=60'
+    if static_request_method == 'GET':
+        assert json.loads(data.decode('utf-8')) == {
+            'endpoint': '/swindon-lattice-w-discovery',
+            'subprotocols': ['v1.swindon-lattice+json'],
+            'max_message_size': 10485760,
+            'max_json_depth': 32,
+            'max_json_elements': 65536,
+            'max_subscriptions_per_connection': None,
+            'message_rate_limit': {'rate': 10, 'burst': 20},
+        }
+    else:
+        assert len(data) == 0
+
+
+async def test_request_methods(swindon, http_request, proxy_request_method):
+    resp, data = await http_request(swindon.url / 'chat-config.json')
+    if proxy_request_method == 'GET':
+        assert resp.status == 200
+    else:
+        assert resp.status == 405
+        assert resp.headers['Allow'] == 'GET, HEAD'
+
+
+async def test_listen_port(custom_swindon, swindon_ports, loop):
+    ports = swindon_ports['discovery_listen_port']
+    http_port, chat_port = ports['main'], ports['replication']
+    with custom_swindon(CONFIG, http_port, chat_port,
+                        http_port=http_port, chat_port=chat_port,
+                        pool_port=ports['session_pool_1'],
+                        proxy_port=ports['proxy']):
+        async with aiohttp.ClientSession(loop=loop) as s:
+            url = 'http://localhost:{}/chat-config.json'.format(chat_port)
+            async with s.get(url) as resp:
+                assert resp.status == 200
+                data = await resp.json()
+                assert data['endpoint'] == '/chat'
+
+            # chat is not reachable on the other port
+            url = 'http://localhost:{}/chat-config.json'.format(http_port)
+            async with s.get(url) as resp:
+                assert resp.status == 404
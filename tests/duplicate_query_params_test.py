@@ -0,0 +1,76 @@
+import asyncio
+
+import pytest
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+duplicate-query-params: ${policy}
+routing:
+  localhost/proxy: proxy
+handlers:
+  proxy: !Proxy
+    destination: backend/
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def echo_target_backend(port, loop):
+    """Backend which responds with the request target it received"""
+
+    async def handle(reader, writer):
+        try:
+            head = await reader.readuntil(b'\r\n\r\n')
+        except asyncio.IncompleteReadError:
+            writer.close()
+            return
+        target = head.split(b'\r\n', 1)[0].split(b' ')[1]
+        writer.write(b'HTTP/1.1 200 OK\r\n'
+                     b'Content-Type: text/plain\r\n'
+                     b'Content-Length: ' +
+                     str(len(target)).encode('ascii') + b'\r\n'
+                     b'Connection: close\r\n'
+                     b'\r\n' + target)
+        await writer.drain()
+        writer.close()
+
+    return await asyncio.start_server(handle, '127.0.0.1', port, loop=loop)
+
+
+async def get_target(port, path, loop):
+    reader, writer = await asyncio.open_connection(
+        '127.0.0.1', port, loop=loop)
+    writer.write('GET {} HTTP/1.1\r\nHost: localhost\r\n'
+                 'Connection: close\r\n\r\n'.format(path).encode('ascii'))
+    data = await asyncio.wait_for(reader.read(), 5, loop=loop)
+    writer.close()
+    head, _, body = data.partition(b'\r\n\r\n')
+    assert head.startswith(b'HTTP/1.1 200 ')
+    return body.decode('ascii')
+
+
+@pytest.mark.parametrize('policy,expected', [
+    ('all', '/proxy?a=1&b=2&a=3&%61=4'),
+    ('first', '/proxy?a=1&b=2'),
+    ('last', '/proxy?b=2&%61=4'),
+])
+async def test_forwarded_query(custom_swindon, swindon_ports, loop,
+                               policy, expected):
+    ports = swindon_ports['duplicate_query_params_' + policy]
+    port = ports['main']
+    backend = await echo_target_backend(ports['proxy'], loop)
+    try:
+        with custom_swindon(CONFIG, port, port=port,
+                            proxy_port=ports['proxy'], policy=policy):
+            target = await get_target(port, '/proxy?a=1&b=2&a=3&%61=4', loop)
+            assert target == expected
+            # no duplicates, nothing to normalize
+            target = await get_target(port, '/proxy?x=1&y=2', loop)
+            assert target == '/proxy?x=1&y=2'
+    finally:
+        backend.close()
+        await backend.wait_closed()
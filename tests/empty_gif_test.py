@@ -1,24 +1,32 @@
 import aiohttp
 
 
-async def test_ok(swindon, http_request, debug_routing):
-    resp, data = await http_request(swindon.url / 'empty.gif')
+async def test_ok(swindon, get_request, static_request_method,
+                  debug_routing):
+    resp, data = await get_request(swindon.url / 'empty.gif')
     assert resp.status == 200
     assert resp.headers['Content-Type'] == 'image/gif'
     assert resp.headers['Content-Length'] == '26'
     assert resp.headers['Server'] == 'swindon/func-tests'
     if debug_routing:
         assert resp.headers['X-Swindon-Route'] == 'empty_gif'
-    assert len(data) == 26
+    if static_request_method == 'GET':
+        assert len(data) == 26
+    else:
+        assert len(data) == 0
 
 
-async def test_request_methods(swindon, http_request):
+async def test_request_methods(swindon, http_request, proxy_request_method):
     resp, data = await http_request(swindon.url / 'empty.gif')
-    assert resp.status == 200
-    assert resp.headers['Content-Type'] == 'image/gif'
-    assert resp.headers['Content-Length'] == '26'
     assert resp.headers['Server'] == 'swindon/func-tests'
-    assert len(data) == 26
+    if proxy_request_method == 'GET':
+        assert resp.status == 200
+        assert resp.headers['Content-Type'] == 'image/gif'
+        assert resp.headers['Content-Length'] == '26'
+        assert len(data) == 26
+    else:
+        assert resp.status == 405
+        assert resp.headers['Allow'] == 'GET, HEAD'
 
 
 async def test_request_HEAD(swindon, loop):
@@ -32,15 +40,15 @@ async def test_request_HEAD(swindon, loop):
             assert len(data) == 0
 
 
-async def test_extra_headers(swindon, http_request):
-    resp, data = await http_request(swindon.url / 'empty-w-headers.gif')
+async def test_extra_headers(swindon, get_request):
+    resp, data = await get_request(swindon.url / 'empty-w-headers.gif')
     assert resp.status == 200
     assert resp.headers['X-Some-Header'] == 'some value'
 
 
-async def test_headers_override(swindon, http_request):
+async def test_headers_override(swindon, get_request):
     url = swindon.url / 'empty-w-content-length.gif'
-    resp, data = await http_request(url)
+    resp, data = await get_request(url)
     assert resp.status == 200
     clen = [val for key, val in resp.raw_headers
             if key == b'Content-Length']
@@ -0,0 +1,58 @@
+import asyncio
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+warmup-period: 1s
+shutdown-drain-period: 3s
+routing:
+  localhost/: empty_gif
+  localhost/healthz: liveness
+  localhost/readyz: readiness
+handlers:
+  empty_gif: !EmptyGif
+  liveness: !Health
+    probe: liveness
+  readiness: !Health
+    probe: readiness
+http-destinations:
+  backend:
+    addresses:
+    # never resolved, so swindon is warming up for the whole period
+    - swindon-test.invalid:1234
+"""
+
+
+async def get(session, url):
+    async with session.get(url) as resp:
+        body = await resp.read()
+        return resp.status, body
+
+
+async def test_liveness_and_readiness(custom_swindon, swindon_ports, loop):
+    port = swindon_ports['health']['main']
+    url = 'http://localhost:{}'.format(port)
+    with custom_swindon(CONFIG, port, port=port) as swindon:
+        async with aiohttp.ClientSession(loop=loop) as s:
+            # warming up
+            assert await get(s, url + '/healthz') == (200, b'ok\n')
+            assert await get(s, url + '/readyz') == (503, b'warming up\n')
+            status, _ = await get(s, url + '/')
+            assert status == 503
+
+            await asyncio.sleep(1.5, loop=loop)
+            assert await get(s, url + '/readyz') == (200, b'ok\n')
+            assert await get(s, url + '/healthz') == (200, b'ok\n')
+
+            # draining, requests are still served
+            swindon.proc.terminate()
+            await asyncio.sleep(0.5, loop=loop)
+            assert await get(s, url + '/readyz') == (
+                503, b'shutting down\n')
+            assert await get(s, url + '/healthz') == (200, b'ok\n')
+            status, _ = await get(s, url + '/')
+            assert status == 200
+
+        assert await loop.run_in_executor(None, swindon.proc.wait, 5) == 0
@@ -0,0 +1,30 @@
+import asyncio
+
+
+async def raw_request(swindon, loop, request):
+    reader, writer = await asyncio.open_connection(
+        swindon.url.host, swindon.url.port, loop=loop)
+    try:
+        writer.write(request)
+        status = await asyncio.wait_for(reader.readline(), 1)
+        return int(status.split()[1])
+    finally:
+        writer.close()
+
+
+async def test_http11_without_host(swindon, loop):
+    status = await raw_request(swindon, loop,
+        b'GET /empty.gif HTTP/1.1\r\n\r\n')
+    assert status == 400
+
+
+async def test_http10_without_host(swindon, loop):
+    status = await raw_request(swindon, loop,
+        b'GET /empty.gif HTTP/1.0\r\n\r\n')
+    assert status == 200
+
+
+async def test_http10_with_host(swindon, loop):
+    status = await raw_request(swindon, loop,
+        b'GET /empty.gif HTTP/1.0\r\nHost: example.org\r\n\r\n')
+    assert status == 404
@@ -0,0 +1,63 @@
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+ingress-remove-headers:
+- X-Real-IP
+- x-internal-user
+networks:
+  local:
+  - 127.0.0.0/8
+  balancers:
+  - 127.0.0.2/32
+authorizers:
+  trusted: !SourceIp
+    allowed-network: local
+    accept-forwarded-headers-from: balancers
+routing:
+  localhost/proxy: proxy @trusted
+handlers:
+  proxy: !Proxy
+    destination: backend/
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+HEADERS = {
+    'X-Real-IP': '10.0.0.1',
+    'X-Internal-User': 'admin',
+    'X-Other': 'value',
+}
+
+
+async def forwarded_headers(proxy_server, ports, loop, **kwargs):
+    url = 'http://localhost:{}/proxy'.format(ports['main'])
+    async with proxy_server(port=ports['proxy'], **kwargs) as proxy:
+        handler = proxy.send('GET', url, headers=HEADERS)
+        req = await handler.request()
+        resp, _ = await handler.response(b'ok')
+        assert resp.status == 200
+        return req.headers
+
+
+async def test_ingress_remove_headers(custom_swindon, swindon_ports,
+                                      proxy_server, loop):
+    ports = swindon_ports['ingress_headers']
+    with custom_swindon(CONFIG, ports['main'],
+                        port=ports['main'], proxy_port=ports['proxy']):
+        headers = await forwarded_headers(proxy_server, ports, loop)
+        assert 'X-Real-IP' not in headers
+        assert 'X-Internal-User' not in headers
+        assert headers['X-Other'] == 'value'
+
+        trusted = aiohttp.TCPConnector(local_addr=('127.0.0.2', 0),
+                                       loop=loop)
+        headers = await forwarded_headers(proxy_server, ports, loop,
+                                          connector=trusted)
+        assert headers['X-Real-IP'] == '10.0.0.1'
+        assert headers['X-Internal-User'] == 'admin'
+        assert headers['X-Other'] == 'value'
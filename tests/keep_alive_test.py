@@ -0,0 +1,88 @@
+import asyncio
+import pytest
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/big: big keep-alive=false
+handlers:
+  big: !Static
+    path: ${big_dir}
+"""
+
+# much larger than socket buffers
+BIG_SIZE = 8 << 20
+
+
+@pytest.fixture(scope='module')
+def big_dir(tmpdir_factory):
+    path = tmpdir_factory.mktemp('big')
+    path.join('big.bin').write_binary(b'0123456789abcdef' * (BIG_SIZE // 16))
+    return str(path)
+
+
+async def read_response(reader):
+    head = await asyncio.wait_for(reader.readuntil(b'\r\n\r\n'), 1)
+    lines = head.decode('ascii').split('\r\n')
+    headers = {}
+    for line in lines[1:]:
+        if line:
+            name, value = line.split(':', 1)
+            headers[name.strip().lower()] = value.strip()
+    body = await reader.readexactly(int(headers['content-length']))
+    return int(lines[0].split()[1]), headers, body
+
+
+def request(path):
+    return ('GET {} HTTP/1.1\r\n'
+            'Host: localhost\r\n'
+            '\r\n').format(path).encode('ascii')
+
+
+async def test_keep_alive_disabled(swindon, loop):
+    reader, writer = await asyncio.open_connection(
+        '127.0.0.1', swindon.url.port, loop=loop)
+    try:
+        writer.write(request('/no-keep-alive.gif'))
+        status, headers, _ = await read_response(reader)
+        assert status == 200
+        assert headers['connection'] == 'close'
+        # connection is closed by server
+        assert await asyncio.wait_for(reader.read(), 1) == b''
+    finally:
+        writer.close()
+
+
+async def test_keep_alive_default(swindon, loop):
+    reader, writer = await asyncio.open_connection(
+        '127.0.0.1', swindon.url.port, loop=loop)
+    try:
+        for _ in range(2):
+            writer.write(request('/empty.gif'))
+            status, headers, _ = await read_response(reader)
+            assert status == 200
+            assert 'connection' not in headers
+    finally:
+        writer.close()
+
+
+async def test_keep_alive_disabled_large(custom_swindon, swindon_ports,
+                                         big_dir, loop):
+    port = swindon_ports['keep_alive_large']['main']
+    with custom_swindon(CONFIG, port, port=port, big_dir=big_dir):
+        reader, writer = await asyncio.open_connection(
+            '127.0.0.1', port, loop=loop)
+        try:
+            writer.write(request('/big/big.bin'))
+            # let the response fill socket buffers before reading it
+            await asyncio.sleep(0.5, loop=loop)
+            status, headers, body = await read_response(reader)
+            assert status == 200
+            assert headers['connection'] == 'close'
+            assert len(body) == BIG_SIZE
+            # connection is closed only after the whole response is sent
+            assert await asyncio.wait_for(reader.read(), 1) == b''
+        finally:
+            writer.close()
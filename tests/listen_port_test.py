@@ -0,0 +1,42 @@
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${http_port}
+- 127.0.0.1:${ws_port}
+routing:
+  localhost/: empty_gif
+  localhost/ws: websocket_echo listen-port=${ws_port}
+handlers:
+  empty_gif: !EmptyGif
+  websocket_echo: !WebsocketEcho
+"""
+
+
+async def test_route_bound_to_listener(custom_swindon, swindon_ports, loop):
+    ports = swindon_ports['listen_port']
+    http_port, ws_port = ports['main'], ports['proxy']
+    with custom_swindon(CONFIG, http_port, ws_port,
+                        http_port=http_port, ws_port=ws_port):
+        async with aiohttp.ClientSession(loop=loop) as s:
+            url = 'http://localhost:{}/ws'.format(ws_port)
+            async with s.ws_connect(url) as ws:
+                await ws.send_str('hello')
+                msg = await ws.receive()
+                assert msg.data == 'hello'
+
+            url = 'http://localhost:{}/ws'.format(http_port)
+            async with s.get(url, headers={
+                    'Connection': 'Upgrade',
+                    'Upgrade': 'websocket',
+                    'Sec-WebSocket-Version': '13',
+                    'Sec-WebSocket-Key': 'dGhlIHNhbXBsZSBub25jZQ==',
+                    }) as resp:
+                assert resp.status == 404
+
+            # other routes are available on both listeners
+            for port in (http_port, ws_port):
+                url = 'http://localhost:{}/'.format(port)
+                async with s.get(url) as resp:
+                    assert resp.status == 200
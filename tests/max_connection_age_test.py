@@ -0,0 +1,134 @@
+import asyncio
+import pytest
+
+from aiohttp import WSMsgType
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+max-connection-age: 1s
+routing:
+  localhost/empty.gif: empty_gif
+  localhost/chat: chat
+  localhost/big: big
+handlers:
+  empty_gif: !EmptyGif
+  big: !Static
+    path: ${big_dir}
+  chat: !SwindonLattice
+    session-pool: pool
+    message-handlers:
+      "*": backend/
+session-pools:
+  pool:
+    listen:
+    - 127.0.0.1:${pool_port}
+    inactivity-handlers: []
+http-destinations:
+  backend:
+    override-host-header: swindon.internal
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+REQUEST = (b'GET /empty.gif HTTP/1.1\r\n'
+           b'Host: localhost\r\n'
+           b'\r\n')
+
+BIG_REQUEST = (b'GET /big/big.bin HTTP/1.1\r\n'
+               b'Host: localhost\r\n'
+               b'\r\n')
+
+# much larger than socket buffers
+BIG_SIZE = 8 << 20
+
+
+@pytest.fixture(scope='module')
+def big_dir(tmpdir_factory):
+    path = tmpdir_factory.mktemp('big')
+    path.join('big.bin').write_binary(b'0123456789abcdef' * (BIG_SIZE // 16))
+    return str(path)
+
+
+async def read_response(reader):
+    head = await asyncio.wait_for(reader.readuntil(b'\r\n\r\n'), 1)
+    lines = head.decode('ascii').split('\r\n')
+    headers = {}
+    for line in lines[1:]:
+        if line:
+            name, value = line.split(':', 1)
+            headers[name.strip().lower()] = value.strip()
+    body = await reader.readexactly(int(headers['content-length']))
+    return int(lines[0].split()[1]), headers, body
+
+
+def start_swindon(custom_swindon, ports, big_dir):
+    return custom_swindon(CONFIG, ports['main'], port=ports['main'],
+                          pool_port=ports['session_pool_1'],
+                          proxy_port=ports['proxy'],
+                          big_dir=big_dir)
+
+
+async def test_keep_alive_closed(custom_swindon, swindon_ports, big_dir,
+                                 loop):
+    ports = swindon_ports['max_connection_age']
+    with start_swindon(custom_swindon, ports, big_dir):
+        reader, writer = await asyncio.open_connection(
+            '127.0.0.1', ports['main'], loop=loop)
+        try:
+            writer.write(REQUEST)
+            status, headers, _ = await read_response(reader)
+            assert status == 200
+            assert headers.get('connection') != 'close'
+
+            # connection is kept alive before the limit
+            writer.write(REQUEST)
+            status, _, _ = await read_response(reader)
+            assert status == 200
+
+            # idle connection is closed by server after the limit
+            data = await asyncio.wait_for(reader.read(), 2)
+            assert data == b''
+        finally:
+            writer.close()
+
+
+async def test_websocket_closed(custom_swindon, swindon_ports, big_dir,
+                                proxy_server, user_id, loop):
+    ports = swindon_ports['max_connection_age_ws']
+    url = 'http://localhost:{}/chat'.format(ports['main'])
+    with start_swindon(custom_swindon, ports, big_dir):
+        async with proxy_server(port=ports['proxy']) as proxy:
+            handler = proxy.swindon_lattice(url, timeout=1)
+            req = await handler.request()
+            assert req.path == '/swindon/authorize_connection'
+            ws = await handler.json_response({"user_id": user_id})
+            hello = await ws.receive_json()
+            assert hello == ['hello', {}, {'user_id': user_id}]
+
+            msg = await ws.receive(timeout=2)
+            assert msg.type == WSMsgType.CLOSE
+            assert msg.data == 1001
+            assert msg.extra == 'max_connection_age'
+
+
+async def test_large_response_not_truncated(custom_swindon, swindon_ports,
+                                            big_dir, loop):
+    ports = swindon_ports['max_connection_age_large']
+    with start_swindon(custom_swindon, ports, big_dir):
+        reader, writer = await asyncio.open_connection(
+            '127.0.0.1', ports['main'], loop=loop)
+        try:
+            writer.write(BIG_REQUEST)
+            # response doesn't fit socket buffers, so connection expires
+            # while the rest of the response is still in the output buffer
+            await asyncio.sleep(1.5, loop=loop)
+            status, _, body = await read_response(reader)
+            assert status == 200
+            assert len(body) == BIG_SIZE
+            # connection is closed only after the whole response is sent
+            data = await asyncio.wait_for(reader.read(), 2)
+            assert data == b''
+        finally:
+            writer.close()
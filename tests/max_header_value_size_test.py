@@ -0,0 +1,24 @@
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+max-header-value-size: 1024
+routing:
+  localhost/empty.gif: empty_gif
+handlers:
+  empty_gif: !EmptyGif
+"""
+
+
+async def test_cookie_size(custom_swindon, swindon_ports, raw_request):
+    port = swindon_ports['max_header_value_size']['main']
+    with custom_swindon(CONFIG, port, port=port):
+        path = b'/empty.gif'
+        assert await raw_request(port, path) == 200
+        assert await raw_request(port, path,
+            b'Cookie: session=' + b'x' * 1000 + b'\r\n') == 200
+        assert await raw_request(port, path,
+            b'Cookie: session=' + b'x' * 2000 + b'\r\n') == 431
+        # limit is per value, not for the whole head
+        assert await raw_request(port, path,
+            b'X-A: ' + b'a' * 1000 + b'\r\n' +
+            b'X-B: ' + b'b' * 1000 + b'\r\n') == 200
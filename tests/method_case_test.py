@@ -0,0 +1,35 @@
+import asyncio
+import pytest
+
+
+async def raw_request(swindon, loop, method):
+    reader, writer = await asyncio.open_connection(
+        swindon.url.host, swindon.url.port, loop=loop)
+    try:
+        writer.write(method + b' /empty.gif HTTP/1.1\r\n'
+                     b'Host: localhost\r\n'
+                     b'\r\n')
+        status = await asyncio.wait_for(reader.readline(), 1)
+        return int(status.split()[1])
+    finally:
+        writer.close()
+
+
+async def test_uppercase(swindon, loop):
+    assert await raw_request(swindon, loop, b'GET') == 200
+
+
+@pytest.mark.parametrize('method', [b'get', b'Get', b'head', b'post'])
+async def test_lowercase(swindon, loop, method):
+    # methods are case-sensitive (RFC 7231, section 4.1)
+    assert await raw_request(swindon, loop, method) == 501
+
+
+@pytest.mark.parametrize('method', [b'PROPFIND', b'FOO'])
+async def test_unknown_method(swindon, loop, method):
+    assert await raw_request(swindon, loop, method) == 501
+
+
+async def test_extension_method(swindon, loop):
+    # allowed by `extension-methods`, handler doesn't support it though
+    assert await raw_request(swindon, loop, b'MKCOL') == 405
@@ -0,0 +1,38 @@
+import asyncio
+import pytest
+
+
+# `!Panic` is compiled only with `test-handlers` cargo feature
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/panic: panic
+  localhost/empty.gif: empty_gif
+handlers:
+  panic: !Panic
+  empty_gif: !EmptyGif
+"""
+
+
+async def request_status(port, path, loop):
+    reader, writer = await asyncio.open_connection(
+        '127.0.0.1', port, loop=loop)
+    try:
+        writer.write(('GET {} HTTP/1.1\r\n'
+                      'Host: localhost\r\n'
+                      '\r\n').format(path).encode('ascii'))
+        status = await asyncio.wait_for(reader.readline(), 1)
+        return int(status.split()[1])
+    finally:
+        writer.close()
+
+
+@pytest.mark.parametrize('path', ['/panic', '/panic/early'])
+async def test_panic(custom_swindon, swindon_ports, loop, path):
+    ports = swindon_ports['panic']
+    with custom_swindon(CONFIG, ports['main'], port=ports['main']):
+        assert await request_status(ports['main'], path, loop) == 500
+
+        # server keeps serving
+        assert await request_status(ports['main'], '/empty.gif', loop) == 200
@@ -0,0 +1,79 @@
+import asyncio
+
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/default: default_ctype
+  localhost/nosniff: nosniff
+handlers:
+  default_ctype: !Proxy
+    destination: backend/
+    default-content-type: application/octet-stream
+  nosniff: !Proxy
+    destination: backend/
+    nosniff: true
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def raw_backend(port, loop):
+    """Backend which responds without Content-Type unless path is /typed"""
+
+    async def handle(reader, writer):
+        while True:
+            try:
+                head = await reader.readuntil(b'\r\n\r\n')
+            except asyncio.IncompleteReadError:
+                break
+            path = head.split(b' ')[1]
+            if path.endswith(b'/typed'):
+                extra = b'Content-Type: text/plain\r\n'
+            else:
+                extra = b''
+            writer.write(b'HTTP/1.1 200 OK\r\n'
+                         b'Content-Length: 5\r\n' + extra +
+                         b'\r\n'
+                         b'hello')
+        writer.close()
+
+    return await asyncio.start_server(handle, '127.0.0.1', port, loop=loop)
+
+
+async def test_default_content_type(custom_swindon, swindon_ports, loop):
+    ports = swindon_ports['proxy_content_type']
+    url = 'http://localhost:{}'.format(ports['main'])
+    backend = await raw_backend(ports['proxy'], loop)
+    try:
+        with custom_swindon(CONFIG, ports['main'],
+                            port=ports['main'], proxy_port=ports['proxy']):
+            async with aiohttp.ClientSession(loop=loop) as s:
+                async with s.get(url + '/default/untyped') as resp:
+                    assert resp.status == 200
+                    assert await resp.read() == b'hello'
+                    assert (resp.headers['Content-Type'] ==
+                            'application/octet-stream')
+                    assert 'X-Content-Type-Options' not in resp.headers
+
+                async with s.get(url + '/default/typed') as resp:
+                    assert resp.status == 200
+                    assert resp.headers['Content-Type'] == 'text/plain'
+
+                async with s.get(url + '/nosniff/untyped') as resp:
+                    assert resp.status == 200
+                    assert 'Content-Type' not in resp.headers
+                    assert resp.headers['X-Content-Type-Options'] == 'nosniff'
+
+                async with s.get(url + '/nosniff/typed') as resp:
+                    assert resp.status == 200
+                    assert resp.headers['Content-Type'] == 'text/plain'
+                    assert resp.headers['X-Content-Type-Options'] == 'nosniff'
+    finally:
+        backend.close()
+        await backend.wait_closed()
@@ -0,0 +1,174 @@
+import asyncio
+import gzip
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/negotiate: negotiate
+  localhost/plain: plain
+  localhost/compress: compress
+handlers:
+  negotiate: !Proxy
+    destination: backend/
+    negotiate-encoding: true
+  plain: !Proxy
+    destination: backend/
+  compress: !Proxy
+    destination: backend/
+    proxy-compression:
+      content-types: [application/json]
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+TEXT = b'hello world ' * 100
+JSON = b'[' + b', '.join([b'{"hello": "world"}'] * 100) + b']'
+
+
+async def encoding_backend(port, loop):
+    """Backend which sends gzipped body for `/gzip`, JSON for `/json`
+    and plain text otherwise
+    """
+
+    async def handle(reader, writer):
+        try:
+            head = await reader.readuntil(b'\r\n\r\n')
+        except asyncio.IncompleteReadError:
+            writer.close()
+            return
+        path = head.split(b' ')[1]
+        ctype = b'text/plain'
+        if b'/gzip' in path:
+            body = gzip.compress(TEXT)
+            extra = b'Content-Encoding: gzip\r\n'
+        elif b'/json' in path:
+            body = JSON
+            ctype = b'application/json'
+            extra = b''
+        else:
+            body = TEXT
+            extra = b''
+        writer.write(b'HTTP/1.1 200 OK\r\n'
+                     b'Content-Type: ' + ctype + b'\r\n'
+                     b'ETag: "v1"\r\n' + extra +
+                     b'Content-Length: ' + str(len(body)).encode() + b'\r\n'
+                     b'Connection: close\r\n'
+                     b'\r\n' + body)
+        await writer.drain()
+        writer.close()
+
+    return await asyncio.start_server(handle, '127.0.0.1', port, loop=loop)
+
+
+async def raw_request(port, path, accept_encoding, loop):
+    reader, writer = await asyncio.open_connection(
+        '127.0.0.1', port, loop=loop)
+    req = 'GET {} HTTP/1.1\r\nHost: localhost\r\n'.format(path)
+    if accept_encoding is not None:
+        req += 'Accept-Encoding: {}\r\n'.format(accept_encoding)
+    writer.write((req + 'Connection: close\r\n\r\n').encode('ascii'))
+    data = await asyncio.wait_for(reader.read(), 5, loop=loop)
+    writer.close()
+    head, _, body = data.partition(b'\r\n\r\n')
+    lines = head.decode('ascii').split('\r\n')
+    headers = {}
+    for line in lines[1:]:
+        k, _, v = line.partition(':')
+        headers[k.strip().lower()] = v.strip()
+    assert int(headers['content-length']) == len(body)
+    return lines[0], headers, body
+
+
+async def test_negotiate_encoding(custom_swindon, swindon_ports, loop):
+    ports = swindon_ports['proxy_encoding']
+    port = ports['main']
+    backend = await encoding_backend(ports['proxy'], loop)
+    try:
+        with custom_swindon(CONFIG, port,
+                            port=port, proxy_port=ports['proxy']):
+            # compressed upstream, client doesn't accept gzip
+            status, headers, body = await raw_request(
+                port, '/negotiate/gzip', None, loop)
+            assert status == 'HTTP/1.1 200 OK'
+            assert 'content-encoding' not in headers
+            assert headers['etag'] == 'W/"v1"'
+            assert headers['vary'] == 'Accept-Encoding'
+            assert body == TEXT
+
+            # compressed upstream, client accepts gzip
+            status, headers, body = await raw_request(
+                port, '/negotiate/gzip', 'gzip, deflate', loop)
+            assert headers['content-encoding'] == 'gzip'
+            assert headers['etag'] == '"v1"'
+            assert gzip.decompress(body) == TEXT
+
+            # plain upstream, client accepts gzip
+            status, headers, body = await raw_request(
+                port, '/negotiate/text', 'gzip', loop)
+            assert status == 'HTTP/1.1 200 OK'
+            assert headers['content-encoding'] == 'gzip'
+            assert headers['etag'] == 'W/"v1"'
+            assert headers['vary'] == 'Accept-Encoding'
+            assert gzip.decompress(body) == TEXT
+
+            # gzip is explicitly refused
+            status, headers, body = await raw_request(
+                port, '/negotiate/text', 'gzip;q=0, *', loop)
+            assert 'content-encoding' not in headers
+            assert body == TEXT
+
+            # without the setting response is passed as is
+            status, headers, body = await raw_request(
+                port, '/plain/gzip', None, loop)
+            assert headers['content-encoding'] == 'gzip'
+            assert 'vary' not in headers
+            assert gzip.decompress(body) == TEXT
+    finally:
+        backend.close()
+        await backend.wait_closed()
+
+
+async def test_proxy_compression(custom_swindon, swindon_ports, loop):
+    ports = swindon_ports['proxy_compression']
+    port = ports['main']
+    backend = await encoding_backend(ports['proxy'], loop)
+    try:
+        with custom_swindon(CONFIG, port,
+                            port=port, proxy_port=ports['proxy']):
+            # JSON upstream, client accepts gzip
+            status, headers, body = await raw_request(
+                port, '/compress/json', 'gzip', loop)
+            assert status == 'HTTP/1.1 200 OK'
+            assert headers['content-encoding'] == 'gzip'
+            assert headers['etag'] == 'W/"v1"'
+            assert headers['vary'] == 'Accept-Encoding'
+            assert gzip.decompress(body) == JSON
+
+            # JSON upstream, client doesn't accept gzip
+            status, headers, body = await raw_request(
+                port, '/compress/json', None, loop)
+            assert 'content-encoding' not in headers
+            assert headers['vary'] == 'Accept-Encoding'
+            assert body == JSON
+
+            # content type is not in the list
+            status, headers, body = await raw_request(
+                port, '/compress/text', 'gzip', loop)
+            assert 'content-encoding' not in headers
+            assert body == TEXT
+
+            # already gzipped upstream is passed untouched
+            expected = gzip.compress(TEXT)
+            status, headers, body = await raw_request(
+                port, '/compress/gzip', None, loop)
+            assert headers['content-encoding'] == 'gzip'
+            assert headers['etag'] == '"v1"'
+            assert gzip.decompress(body) == TEXT
+            assert len(body) == len(expected)
+    finally:
+        backend.close()
+        await backend.wait_closed()
@@ -0,0 +1,74 @@
+import asyncio
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/length: length
+handlers:
+  length: !Proxy
+    destination: backend/
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def close_delimited_backend(port, loop):
+    """Backend which marks end of the body by closing the connection"""
+
+    async def handle(reader, writer):
+        try:
+            await reader.readuntil(b'\r\n\r\n')
+        except asyncio.IncompleteReadError:
+            writer.close()
+            return
+        writer.write(b'HTTP/1.1 200 OK\r\n'
+                     b'Content-Type: text/plain\r\n'
+                     b'Connection: close\r\n'
+                     b'\r\n'
+                     b'hello world')
+        await writer.drain()
+        writer.close()
+
+    return await asyncio.start_server(handle, '127.0.0.1', port, loop=loop)
+
+
+async def raw_request(port, path, version, loop):
+    reader, writer = await asyncio.open_connection(
+        '127.0.0.1', port, loop=loop)
+    writer.write('GET {} HTTP/{}\r\nHost: localhost\r\n\r\n'
+                 .format(path, version).encode('ascii'))
+    head = await asyncio.wait_for(
+        reader.readuntil(b'\r\n\r\n'), 5, loop=loop)
+    lines = head.decode('ascii').rstrip('\r\n').split('\r\n')
+    headers = {}
+    for line in lines[1:]:
+        k, _, v = line.partition(':')
+        headers[k.strip().lower()] = v.strip()
+    body = await asyncio.wait_for(
+        reader.readexactly(int(headers['content-length'])), 5, loop=loop)
+    writer.close()
+    return lines[0], headers, body
+
+
+async def test_unknown_length(custom_swindon, swindon_ports, loop):
+    ports = swindon_ports['proxy_framing']
+    port = ports['main']
+    backend = await close_delimited_backend(ports['proxy'], loop)
+    try:
+        with custom_swindon(CONFIG, port,
+                            port=port, proxy_port=ports['proxy']):
+            # body is buffered, so it's sent with length to any client
+            for version in ['1.1', '1.0']:
+                status, headers, body = await raw_request(
+                    port, '/length/x', version, loop)
+                assert status.endswith(' 200 OK')
+                assert headers['content-length'] == '11'
+                assert 'transfer-encoding' not in headers
+                assert body == b'hello world'
+    finally:
+        backend.close()
+        await backend.wait_closed()
@@ -1,7 +1,10 @@
 import asyncio
+import aiohttp
 import async_timeout
+import pytest
 
 from aiohttp import HttpVersion11
+from yarl import URL
 
 
 async def test_simple_request(proxy_server, swindon,
@@ -51,6 +54,24 @@ async def test_host_override(proxy_server, swindon,
         assert body == b'OK'
 
 
+
+async def test_http10_without_host(proxy_server, swindon, loop):
+    async with proxy_server() as proxy:
+        reader, writer = await asyncio.open_connection(
+            swindon.url.host, swindon.url.port, loop=loop)
+        try:
+            writer.write(b'GET /proxy/hello HTTP/1.0\r\n\r\n')
+            with async_timeout.timeout(5):
+                req = await proxy.wait_request()
+            assert req.path == '/proxy/hello'
+            # routed and proxied as `default-host`
+            assert req.headers['Host'] == 'localhost'
+            await proxy.set_response((b'OK',), {})
+            status = await asyncio.wait_for(reader.readline(), 5)
+            assert status.split()[1] == b'200'
+        finally:
+            writer.close()
+
 async def test_method(proxy_server, swindon, proxy_request_method):
     url = swindon.url / 'proxy/hello'
     async with proxy_server() as proxy:
@@ -65,6 +86,21 @@ async def test_method(proxy_server, swindon, proxy_request_method):
         assert resp.status == 200
 
 
+@pytest.mark.parametrize('method', ['PROPFIND', 'FOO'])
+async def test_unknown_method(proxy_server, swindon, method):
+    # not in `extension-methods`, but proxy passes any method to upstream
+    url = swindon.url / 'proxy/hello'
+    async with proxy_server() as proxy:
+        handler = proxy.send(method, url, timeout=5)
+
+        req = await handler.request()
+        assert req.method == method
+        assert req.path == '/proxy/hello'
+
+        resp, _ = await handler.response('OK')
+        assert resp.status == 200
+
+
 async def test_prefix(proxy_server, swindon):
     url = swindon.url / 'proxy-w-prefix/tail'
     async with proxy_server() as proxy:
@@ -152,3 +188,279 @@ async def test_request_timeout(proxy_server, swindon, loop):
         with async_timeout.timeout(5, loop=loop):
             resp, _ = await client_resp
         assert resp.status == 502
+
+
+async def test_stale_on_error(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-stale/stale-on-error'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'max-age=0'})
+        assert resp.status == 200
+        assert 'Warning' not in resp.headers
+        assert body == b'v1'
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 200
+        assert resp.headers['Warning'] == '110 - "Response is Stale"'
+        assert resp.headers['Content-Type'] == 'text/test'
+        assert body == b'v1'
+
+
+async def test_stale_fresh(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-stale/fresh'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'max-age=0'})
+        assert body == b'v1'
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v2', content_type='text/test')
+        assert resp.status == 200
+        assert 'Warning' not in resp.headers
+        assert body == b'v2'
+
+
+async def test_stale_not_cached(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-stale/no-store'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'no-store'})
+        assert body == b'v1'
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 503
+        assert 'Warning' not in resp.headers
+
+
+async def test_stale_no_max_age(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-stale/no-max-age'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'public'})
+        assert body == b'v1'
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 503
+        assert 'Warning' not in resp.headers
+
+
+async def test_stale_expired(proxy_server, swindon, loop):
+    url = swindon.url / 'proxy-w-short-stale/expired'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'max-age=0'})
+        assert body == b'v1'
+
+        await asyncio.sleep(1.5, loop=loop)
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 503
+        assert body == b'down'
+
+
+async def test_stale_age(proxy_server, swindon, loop):
+    url = swindon.url / 'proxy-w-stale/age'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'max-age=0'})
+        assert 'Age' not in resp.headers
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 200
+        assert resp.headers['Age'] in ('0', '1')
+        assert body == b'v1'
+
+        await asyncio.sleep(2, loop=loop)
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 200
+        assert resp.headers['Age'] in ('2', '3')
+        assert body == b'v1'
+
+
+async def test_stale_upstream_age(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-stale/upstream-age'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'max-age=0', 'Age': '100'})
+        assert resp.headers['Age'] == '100'
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 200
+        assert resp.headers.getall('Age') in (['100'], ['101'])
+
+
+async def test_stale_no_age(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-stale-no-age/age'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'v1', content_type='text/test',
+            headers={'Cache-Control': 'max-age=0'})
+        assert body == b'v1'
+
+        handler = proxy.send('GET', url, timeout=5)
+        await handler.request()
+        resp, body = await handler.response(b'down', status=503)
+        assert resp.status == 200
+        assert resp.headers['Warning'] == '110 - "Response is Stale"'
+        assert 'Age' not in resp.headers
+
+
+async def test_max_response_size_ok(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-max-response-size/small'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url)
+        await handler.request()
+        resp, body = await handler.response(b'x' * 100)
+        assert resp.status == 200
+        assert body == b'x' * 100
+
+
+async def test_max_response_size_exceeded(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-max-response-size/big'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url)
+        await handler.request()
+        resp, body = await handler.response(b'x' * 101)
+        assert resp.status == 502
+        assert body != b'x' * 101
+
+
+async def test_shadow_upstream(proxy_server, swindon):
+    url = swindon.url / 'proxy-w-shadow/hello'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        first = await handler.request()
+        with async_timeout.timeout(5):
+            second = await proxy.wait_request()
+        assert sorted([first.path, second.path]) == [
+            '/proxy-w-shadow/hello',
+            '/shadow/proxy-w-shadow/hello',
+        ]
+        # responses are given in order requests are received
+        for req in (first, second):
+            if req.path.startswith('/shadow/'):
+                await handler.handler.response(b'shadow', status=500)
+            else:
+                await handler.handler.response(b'primary')
+        resp, body = await handler.client_response
+        assert resp.status == 200
+        assert body == b'primary'
+
+
+async def test_merge_slashes(proxy_server, swindon):
+    url = URL('{}/proxy-w-merge-slashes//a//b?x=//y'.format(swindon.url),
+              encoded=True)
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url)
+        req = await handler.request()
+        assert req.raw_path == '/proxy-w-merge-slashes/a/b?x=//y'
+        resp, body = await handler.response(b'ok')
+        assert resp.status == 200
+
+
+async def test_slashes_kept_by_default(proxy_server, swindon):
+    url = URL('{}/proxy//a//b'.format(swindon.url), encoded=True)
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url)
+        req = await handler.request()
+        assert req.raw_path == '/proxy//a//b'
+        resp, body = await handler.response(b'ok')
+        assert resp.status == 200
+
+
+def peer_port(req):
+    if hasattr(req, 'peer_port'):
+        return req.peer_port
+    return req.transport.get_extra_info('peername')[1]
+
+
+async def test_upstream_connection_close(proxy_server, swindon):
+    url = swindon.url / 'proxy/conn-close'
+    async with proxy_server() as proxy:
+        handler = proxy.send('GET', url, timeout=5)
+        req = await handler.request()
+        first_port = peer_port(req)
+        resp, body = await handler.response(b'first',
+            headers={'Connection': 'close'})
+        assert resp.status == 200
+        assert body == b'first'
+
+        # connection which upstream asked to close must not be reused
+        handler = proxy.send('GET', url, timeout=5)
+        req = await handler.request()
+        assert peer_port(req) != first_port
+        resp, body = await handler.response(b'second')
+        assert resp.status == 200
+        assert body == b'second'
+
+
+async def http10_backend(port, peers, loop):
+    """Backend which responds with HTTP/1.0 without keep-alive, but leaves
+    the connection open, so reusing it would be visible"""
+
+    async def handle(reader, writer):
+        peers.append(writer.get_extra_info('peername')[1])
+        while True:
+            try:
+                await reader.readuntil(b'\r\n\r\n')
+            except asyncio.IncompleteReadError:
+                writer.close()
+                return
+            writer.write(b'HTTP/1.0 200 OK\r\n'
+                         b'Content-Type: text/plain\r\n'
+                         b'Content-Length: 2\r\n'
+                         b'\r\n'
+                         b'ok')
+            await writer.drain()
+
+    return await asyncio.start_server(handle, '127.0.0.1', port,
+                                      loop=loop, reuse_address=True)
+
+
+async def test_upstream_http10(swindon, loop):
+    url = swindon.url / 'proxy/conn-close'
+    peers = []
+    backend = await http10_backend(swindon.proxy.port, peers, loop)
+    try:
+        async with aiohttp.ClientSession(loop=loop) as s:
+            for _ in range(2):
+                with async_timeout.timeout(5, loop=loop):
+                    async with s.get(url) as resp:
+                        assert resp.status == 200
+                        assert await resp.read() == b'ok'
+        # HTTP/1.0 response without `Connection: keep-alive` means
+        # the connection must not be reused
+        assert len(peers) == 2
+        assert peers[0] != peers[1]
+    finally:
+        backend.close()
+        await backend.wait_closed()
@@ -0,0 +1,39 @@
+import json
+
+from yarl import URL
+
+
+async def test_query_params(swindon, get_request, static_request_method):
+    url = swindon.url / 'query-echo'
+    resp, data = await get_request(url.with_query(
+        [('page', '2'), ('tag', 'a'), ('tag', 'b')]))
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'application/json'
+    if static_request_method == 'GET':
+        assert json.loads(data.decode('utf-8')) == {
+            'page': ['2'],
+            'tag': ['a', 'b'],
+        }
+    else:
+        assert len(data) == 0
+
+
+async def test_query_decoded(swindon, get_request, static_request_method):
+    url = swindon.url / 'query-echo'
+    resp, data = await get_request(url.with_query({'q': 'hello world!'}))
+    assert resp.status == 200
+    if static_request_method == 'GET':
+        assert json.loads(data.decode('utf-8')) == {'q': ['hello world!']}
+
+
+async def test_no_query(swindon, get_request, static_request_method):
+    resp, data = await get_request(swindon.url / 'query-echo')
+    assert resp.status == 200
+    if static_request_method == 'GET':
+        assert json.loads(data.decode('utf-8')) == {}
+
+
+async def test_bad_escape(swindon, get_request):
+    url = URL(str(swindon.url / 'query-echo') + '?page=%zz', encoded=True)
+    resp, data = await get_request(url)
+    assert resp.status == 400
@@ -0,0 +1,117 @@
+import asyncio
+import aiohttp
+import os
+import string
+
+import pytest
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+reload-mode: ${mode}
+routing:
+  localhost/app.txt: app-text
+  localhost/other.txt: other-text
+mixins:
+  app-: app.yaml
+  other-: other.yaml
+"""
+
+MIXIN = """
+handlers:
+  ${prefix}-text: !RobotsTxt
+    content: ${content}
+"""
+
+# handler without prefix is rejected
+BAD_MIXIN = """
+handlers:
+  text: !RobotsTxt
+    content: broken
+"""
+
+# unknown destination is only found when config is validated as a whole
+INVALID_MIXIN = """
+handlers:
+  app-text: !Proxy
+    destination: app-missing/
+"""
+
+INCLUDING_MIXIN = """
+handlers: !Include app-handlers.yaml
+"""
+
+INCLUDED = """
+app-text: !RobotsTxt
+  content: ${content}
+"""
+
+# Config is checked for updates every 10 seconds
+RELOAD_TIME = 12
+
+
+def mixin(prefix, content):
+    return string.Template(MIXIN).substitute(prefix=prefix, content=content)
+
+
+async def get(url, loop):
+    async with aiohttp.ClientSession(loop=loop) as s:
+        async with s.get(url) as resp:
+            assert resp.status == 200
+            return (await resp.read()).decode('utf-8')
+
+
+@pytest.mark.parametrize('mode,other', [
+    ('all-or-nothing', 'other v1'),
+    ('best-effort', 'other version 2'),
+])
+@pytest.mark.parametrize('bad', [BAD_MIXIN, INVALID_MIXIN])
+async def test_bad_mixin(custom_swindon, swindon_ports, loop, mode, other,
+                         bad):
+    port = swindon_ports['reload_mode_' + mode]['main']
+    url = 'http://localhost:{}'.format(port)
+    files = {
+        'app': mixin('app', 'app v1'),
+        'other': mixin('other', 'other v1'),
+    }
+    with custom_swindon(CONFIG, port, files=files,
+                        port=port, mode=mode) as swindon:
+        assert await get(url + '/app.txt', loop) == 'app v1'
+        assert await get(url + '/other.txt', loop) == 'other v1'
+
+        dir = os.path.dirname(swindon.config)
+        with open(os.path.join(dir, 'app.yaml'), 'wt') as f:
+            f.write(bad)
+        with open(os.path.join(dir, 'other.yaml'), 'wt') as f:
+            f.write(mixin('other', 'other version 2'))
+        await asyncio.sleep(RELOAD_TIME, loop=loop)
+
+        # failed mixin keeps its previous version in any mode
+        assert await get(url + '/app.txt', loop) == 'app v1'
+        assert await get(url + '/other.txt', loop) == other
+
+
+async def test_includes_of_kept_mixin(custom_swindon, swindon_ports, loop):
+    port = swindon_ports['reload_mode_includes']['main']
+    url = 'http://localhost:{}'.format(port)
+    files = {
+        'app': INCLUDING_MIXIN,
+        'app-handlers': string.Template(INCLUDED).substitute(
+            content='app v1'),
+        'other': mixin('other', 'other v1'),
+    }
+    with custom_swindon(CONFIG, port, files=files,
+                        port=port, mode='best-effort') as swindon:
+        assert await get(url + '/app.txt', loop) == 'app v1'
+
+        dir = os.path.dirname(swindon.config)
+        with open(os.path.join(dir, 'app.yaml'), 'wt') as f:
+            f.write(BAD_MIXIN)
+        with open(os.path.join(dir, 'app-handlers.yaml'), 'wt') as f:
+            f.write(string.Template(INCLUDED).substitute(
+                content='app version 2'))
+        await asyncio.sleep(RELOAD_TIME, loop=loop)
+
+        # previous version of the mixin uses previous version of includes
+        assert await get(url + '/app.txt', loop) == 'app v1'
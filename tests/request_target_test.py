@@ -0,0 +1,40 @@
+import asyncio
+
+
+async def raw_request(swindon, loop, request_line):
+    reader, writer = await asyncio.open_connection(
+        swindon.url.host, swindon.url.port, loop=loop)
+    try:
+        writer.write(request_line + b'\r\n'
+                     b'Host: localhost\r\n'
+                     b'\r\n')
+        status = await asyncio.wait_for(reader.readline(), 1)
+        headers = {}
+        while True:
+            line = await asyncio.wait_for(reader.readline(), 1)
+            if line == b'\r\n':
+                break
+            name, value = line.decode('ascii').split(':', 1)
+            headers[name.strip().lower()] = value.strip()
+        return int(status.split()[1]), headers
+    finally:
+        writer.close()
+
+
+async def test_options_asterisk(swindon, loop):
+    status, headers = await raw_request(swindon, loop,
+        b'OPTIONS * HTTP/1.1')
+    assert status == 200
+    assert 'OPTIONS' in headers['allow']
+    assert headers['content-length'] == '0'
+
+
+async def test_get_asterisk(swindon, loop):
+    status, _ = await raw_request(swindon, loop, b'GET * HTTP/1.1')
+    assert status == 400
+
+
+async def test_connect(swindon, loop):
+    status, _ = await raw_request(swindon, loop,
+        b'CONNECT localhost:443 HTTP/1.1')
+    assert status == 400
@@ -0,0 +1,45 @@
+async def test_map_denied(swindon, get_request):
+    resp, data = await get_request(
+        swindon.url / 'static-maps-public/app.js.map')
+    assert resp.status == 404
+
+
+async def test_other_file_public(swindon, get_request, static_request_method):
+    resp, data = await get_request(swindon.url / 'static-maps-public/app.js')
+    assert resp.status == 200
+    if static_request_method == 'GET':
+        assert b'sourceMappingURL' in data
+
+
+async def test_map_allowed(swindon, get_request, static_request_method):
+    resp, data = await get_request(
+        swindon.url / 'static-maps-local/app.js.map')
+    assert resp.status == 200
+    if static_request_method == 'GET':
+        assert b'"mappings"' in data
+
+
+async def test_map_not_listed(swindon, get_request, static_request_method):
+    resp, data = await get_request(swindon.url / 'static-maps-public/')
+    assert resp.status == 200
+    if static_request_method == 'GET':
+        assert b'app.js' in data
+        assert b'app.js.map' not in data
+
+
+async def test_versioned_denied(swindon, get_request):
+    resp, data = await get_request((swindon.url / 'versioned-restricted' /
+        'test.html').with_query(r='aabbbbbb'))
+    assert resp.status == 404
+
+
+async def test_plain_fallback_denied(swindon, get_request):
+    resp, data = await get_request(
+        swindon.url / 'versioned-restricted' / 'test.html')
+    assert resp.status == 404
+
+
+async def test_versioned_other_file(swindon, get_request):
+    resp, data = await get_request((swindon.url / 'versioned-restricted' /
+        'a+b.txt').with_query(r='aabbbbbb'))
+    assert resp.status == 200
@@ -0,0 +1,52 @@
+import aiohttp
+
+
+ROBOTS = b'User-agent: *\nDisallow: /private/\n'
+
+
+async def test_ok(swindon, get_request, static_request_method,
+                  debug_routing):
+    resp, data = await get_request(swindon.url / 'robots.txt')
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'text/plain; charset=utf-8'
+    assert resp.headers['Content-Length'] == str(len(ROBOTS))
+    assert resp.headers['Cache-Control'] == 'public, max-age=3600'
+    if debug_routing:
+        assert resp.headers['X-Swindon-Route'] == 'robots_txt'
+    if static_request_method == 'GET':
+        assert data == ROBOTS
+    else:
+        assert len(data) == 0
+
+
+async def test_request_methods(swindon, http_request, proxy_request_method):
+    resp, data = await http_request(swindon.url / 'robots.txt')
+    if proxy_request_method == 'GET':
+        assert resp.status == 200
+        assert data == ROBOTS
+    else:
+        assert resp.status == 405
+        assert resp.headers['Allow'] == 'GET, HEAD'
+
+
+async def test_not_modified(swindon, loop):
+    url = swindon.url / 'robots.txt'
+    async with aiohttp.ClientSession(loop=loop) as s:
+        async with s.get(url) as resp:
+            assert resp.status == 200
+            etag = resp.headers['ETag']
+            assert await resp.read() == ROBOTS
+
+        async with s.get(url, headers={'If-None-Match': etag}) as resp:
+            assert resp.status == 304
+            assert resp.headers['ETag'] == etag
+            assert 'Content-Length' not in resp.headers
+            assert await resp.read() == b''
+
+        async with s.get(url, headers={'If-None-Match': 'W/' + etag}) as resp:
+            assert resp.status == 304
+
+        async with s.get(url, headers={'If-None-Match': '"other"'}) as resp:
+            assert resp.status == 200
+            assert resp.headers['ETag'] == etag
+            assert await resp.read() == ROBOTS
@@ -0,0 +1,44 @@
+import asyncio
+import tempfile
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+debug-logging: true
+routing:
+  localhost/api: api ->api-log
+  localhost/static: static ->static-log
+handlers:
+  api: !EmptyGif
+  static: !EmptyGif
+log-formats:
+  api-log:
+    template: "api {{ request.request_id }} {{ request.method }}
+               {{ request.path }}"
+  static-log:
+    template: "static {{ request.method }} {{ request.path }}"
+"""
+
+
+async def test_route_formats(custom_swindon, swindon_ports, raw_request,
+                             loop):
+    port = swindon_ports['route_log_format']['main']
+    with tempfile.TemporaryFile() as log, \
+            custom_swindon(CONFIG, port, stdout=log, port=port):
+        assert await raw_request(port, b'/api/x') == 200
+        assert await raw_request(port, b'/static/y') == 200
+        # not routed, so the default `debug-log` format is used
+        assert await raw_request(port, b'/other') == 404
+
+        await asyncio.sleep(0.1, loop=loop)
+        log.seek(0)
+        lines = log.read().decode('utf-8').splitlines()
+
+    api = [line for line in lines if line.startswith('api ')]
+    static = [line for line in lines if line.startswith('static ')]
+    assert len(api) == 1
+    assert api[0].endswith(' GET /api/x')
+    assert len(api[0].split()) == 4  # request id is included
+    assert static == ['static GET /static/y']
+    assert any(line.endswith(' 404') for line in lines)
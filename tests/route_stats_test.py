@@ -0,0 +1,50 @@
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/file: file
+  localhost/status: status
+handlers:
+  file: !SingleFile
+    path: ${TESTS_DIR}/assets/static_file.txt
+    content-type: text/plain
+  status: !SelfStatus
+"""
+
+
+def route_metrics(data, route):
+    """Returns values of `frontend.routes.<route>` metrics of self-status
+
+    Metrics are a flat list of `[{"group": .., "metric": ..}, [type, value]]`
+    """
+    group = 'frontend.routes.' + route
+    return {name['metric']: value
+            for name, (_, value) in data['metrics']
+            if name['group'] == group}
+
+
+async def test_response_bytes(custom_swindon, swindon_ports, TESTS_DIR,
+                              loop):
+    port = swindon_ports['route_stats']['main']
+    url = 'http://localhost:{}'.format(port)
+    with open(TESTS_DIR + '/assets/static_file.txt', 'rb') as f:
+        file_size = len(f.read())
+    with custom_swindon(CONFIG, port, port=port):
+        async with aiohttp.ClientSession(loop=loop) as s:
+            for _ in range(2):
+                async with s.get(url + '/file') as resp:
+                    assert resp.status == 200
+                    assert len(await resp.read()) == file_size
+
+            async with s.get(url + '/status') as resp:
+                assert resp.status == 200
+                data = await resp.json()
+
+    assert route_metrics(data, 'file') == {
+        'requests': 2,
+        'request_bytes': 0,
+        'response_bytes': 2 * file_size,
+    }
@@ -0,0 +1,137 @@
+import aiohttp
+
+import pytest
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+routing:
+  localhost/full: full
+  localhost/bytes: bytes
+handlers:
+  full: !Static
+    path: ${TESTS_DIR}/assets/ranges
+    text-charset: null
+  bytes: !Static
+    path: ${TESTS_DIR}/assets/ranges
+    text-charset: null
+    compressed-ranges: compressed_bytes
+"""
+
+GZIP = {'Accept-Encoding': 'gzip'}
+IDENTITY = {'Accept-Encoding': 'identity'}
+
+
+@pytest.fixture(scope='module')
+def ranges_url(custom_swindon, swindon_ports):
+    port = swindon_ports['static_ranges']['main']
+    with custom_swindon(CONFIG, port, port=port):
+        yield 'http://localhost:{}'.format(port)
+
+
+async def fetch(url, loop, **headers):
+    async with aiohttp.ClientSession(loop=loop, auto_decompress=False) as s:
+        async with s.get(url, headers=headers) as resp:
+            return resp, await resp.read()
+
+
+def read_asset(TESTS_DIR, name):
+    with open(TESTS_DIR + '/assets/ranges/' + name, 'rb') as f:
+        return f.read()
+
+
+async def test_identity_range(ranges_url, TESTS_DIR, loop):
+    data = read_asset(TESTS_DIR, 'data.txt')
+    for handler in ('full', 'bytes'):
+        resp, body = await fetch(ranges_url + '/' + handler + '/data.txt',
+                                 loop, Range='bytes=0-9', **IDENTITY)
+        assert resp.status == 206
+        assert 'Content-Encoding' not in resp.headers
+        assert body == data[:10]
+
+
+async def test_compressed_range_full(ranges_url, TESTS_DIR, loop):
+    gz = read_asset(TESTS_DIR, 'data.txt.gz')
+    resp, body = await fetch(ranges_url + '/full/data.txt',
+                             loop, Range='bytes=0-9', **GZIP)
+    assert resp.status == 200
+    assert resp.headers['Content-Encoding'] == 'gzip'
+    assert body == gz
+
+
+async def test_compressed_range_bytes(ranges_url, TESTS_DIR, loop):
+    gz = read_asset(TESTS_DIR, 'data.txt.gz')
+    resp, body = await fetch(ranges_url + '/bytes/data.txt',
+                             loop, Range='bytes=0-9', **GZIP)
+    assert resp.status == 206
+    assert resp.headers['Content-Encoding'] == 'gzip'
+    assert body == gz[:10]
+
+
+async def test_compressed_unsatisfiable_full(ranges_url, TESTS_DIR, loop):
+    # range is past the end of compressed file, but it's ignored anyway
+    gz = read_asset(TESTS_DIR, 'data.txt.gz')
+    resp, body = await fetch(ranges_url + '/full/data.txt',
+                             loop, Range='bytes=100-110', **GZIP)
+    assert resp.status == 200
+    assert body == gz
+    resp, body = await fetch(ranges_url + '/bytes/data.txt',
+                             loop, Range='bytes=100-110', **GZIP)
+    assert resp.status == 416
+
+
+async def test_if_range_etag(ranges_url, TESTS_DIR, loop):
+    data = read_asset(TESTS_DIR, 'plain.txt')
+    resp, _ = await fetch(ranges_url + '/full/plain.txt', loop)
+    etag = resp.headers['ETag']
+    resp, body = await fetch(ranges_url + '/full/plain.txt', loop,
+                             Range='bytes=0-9', **{'If-Range': etag})
+    assert resp.status == 206
+    assert body == data[:10]
+    resp, body = await fetch(ranges_url + '/full/plain.txt', loop,
+                             Range='bytes=0-9', **{'If-Range': '"stale"'})
+    assert resp.status == 200
+    assert body == data
+    # weak validators never match
+    resp, body = await fetch(ranges_url + '/full/plain.txt', loop,
+                             Range='bytes=0-9', **{'If-Range': 'W/' + etag})
+    assert resp.status == 200
+    assert body == data
+
+
+async def test_if_range_date(ranges_url, TESTS_DIR, loop):
+    data = read_asset(TESTS_DIR, 'plain.txt')
+    resp, _ = await fetch(ranges_url + '/full/plain.txt', loop)
+    modified = resp.headers['Last-Modified']
+    resp, body = await fetch(ranges_url + '/full/plain.txt', loop,
+                             Range='bytes=0-9', **{'If-Range': modified})
+    assert resp.status == 206
+    assert body == data[:10]
+    resp, body = await fetch(ranges_url + '/full/plain.txt', loop,
+                             Range='bytes=0-9', **{
+                                'If-Range': 'Sun, 06 Nov 1994 08:49:37 GMT'})
+    assert resp.status == 200
+    assert body == data
+
+
+async def test_if_range_other_encoding(ranges_url, TESTS_DIR, loop):
+    # validator of the identity representation doesn't match the
+    # compressed one, so full compressed file is served
+    gz = read_asset(TESTS_DIR, 'data.txt.gz')
+    resp, _ = await fetch(ranges_url + '/bytes/data.txt', loop, **IDENTITY)
+    identity_etag = resp.headers['ETag']
+    resp, _ = await fetch(ranges_url + '/bytes/data.txt', loop, **GZIP)
+    gzip_etag = resp.headers['ETag']
+    assert gzip_etag != identity_etag
+
+    resp, body = await fetch(ranges_url + '/bytes/data.txt', loop,
+                             Range='bytes=0-9',
+                             **{'If-Range': identity_etag}, **GZIP)
+    assert resp.status == 200
+    assert body == gz
+    resp, body = await fetch(ranges_url + '/bytes/data.txt', loop,
+                             Range='bytes=0-9',
+                             **{'If-Range': gzip_etag}, **GZIP)
+    assert resp.status == 206
+    assert body == gz[:10]
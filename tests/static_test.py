@@ -41,6 +41,39 @@ async def test_index(swindon, get_request, static_request_method,
         b'<!DOCTYPE html>\n<title>Hello</title>\n')
 
 
+async def test_root_index_policy(swindon, get_request,
+        static_request_method, debug_routing):
+    resp, data = await get_request(swindon.url / 'static-root-index')
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'text/html; charset=utf-8'
+    data_check(data, static_request_method, b'Static file test\n')
+
+
+async def test_dir_index_listing(swindon, get_request,
+        static_request_method, debug_routing):
+    # directory has index.html but policy for subdirectories is `listing`
+    resp, data = await get_request(swindon.url / 'static-root-index/index/')
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'text/html; charset=utf-8'
+    if static_request_method == 'GET':
+        assert b'index.html' in data
+        assert b'href=' in data
+
+
+async def test_root_forbidden(swindon, get_request, debug_routing):
+    resp, data = await get_request(swindon.url / 'static-dir-redirect/')
+    assert resp.status == 403
+
+
+async def test_dir_index_redirect(swindon, loop):
+    url = swindon.url / 'static-dir-redirect/index/'
+    async with aiohttp.ClientSession(loop=loop) as s:
+        async with s.get(url, allow_redirects=False) as resp:
+            assert resp.status == 302
+            assert resp.headers['Location'] == \
+                '/static-dir-redirect/index/index.html'
+
+
 async def test_disabled_index(swindon, get_request, debug_routing):
     # XXX: on resp.read() connection gets closed
     resp, data = await get_request(swindon.url / 'static-wo-index')
@@ -191,3 +224,27 @@ async def test_url_with_query(static_request_method,
             '"{}/assets/static_file.txt"'.format(TESTS_DIR)
     else:
         assert 'X-Swindon-File-Path' not in resp.headers
+
+
+async def test_host_in_path(swindon, get_request, static_request_method,
+        debug_routing, TESTS_DIR):
+    url = swindon.url / 'test.txt'
+    resp, data = await get_request(url,
+        headers={'Host': 'a.sites.example.com'})
+    assert resp.status == 200
+    assert resp.headers['Content-Type'] == 'text/plain; charset=utf-8'
+    data_check(data, static_request_method, b'a.sites.example.com\n')
+    if debug_routing:
+        assert resp.headers['X-Swindon-File-Path'] == \
+            '"{}/assets/sites/a.sites.example.com/test.txt"'.format(TESTS_DIR)
+
+
+@pytest.mark.parametrize('host,status', [
+    ('..sites.example.com', 403),
+    ('a..sites.example.com', 403),
+    ('b.sites.example.com', 404),
+])
+async def test_host_in_path_bad(swindon, get_request, host, status):
+    url = swindon.url / 'test.txt'
+    resp, data = await get_request(url, headers={'Host': host})
+    assert resp.status == status
@@ -398,6 +398,48 @@ async def test_topic_subscribe_publish(proxy_server, swindon, loop, user_id):
         assert msg == ['message', {'topic': 'some.topic'}, 'other message']
 
 
+async def test_subscription_limit(proxy_server, swindon, loop, user_id):
+    url = swindon.url / 'swindon-lattice-w-subscription-limit'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        meta, args, kwargs = await req.json()
+        cid = meta['connection_id']
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        async with aiohttp.ClientSession(loop=loop) as s:
+            sub_url = swindon.api3 / 'v1/connection' / cid / 'subscriptions'
+            for topic in ['topic/1', 'topic/2']:
+                async with s.put(sub_url / topic) as resp:
+                    assert resp.status == 204
+            # re-subscribing to the same topic doesn't count
+            async with s.put(sub_url / 'topic/1') as resp:
+                assert resp.status == 204
+            async with s.put(sub_url / 'topic/3') as resp:
+                assert resp.status == 403
+
+            msg = await ws.receive_json()
+            assert msg == [
+                'error',
+                {'error_kind': 'subscription_limit_exceeded'},
+                'subscription_limit_exceeded']
+
+            for topic in ['topic/1', 'topic/3']:
+                publish_url = swindon.api3 / 'v1/publish' / topic
+                async with s.post(publish_url, data=json.dumps(topic),
+                        headers={'Content-Type': 'application/json'}) as resp:
+                    assert resp.status == 204
+            # connection is still open, but is subscribed to first two only
+            msg = await ws.receive_json()
+            assert msg == ['message', {'topic': 'topic.1'}, 'topic/1']
+            with pytest.raises(asyncio.TimeoutError):
+                await ws.receive_json(timeout=0.2)
+            assert not ws.closed
+
+
 async def test_lattice_subscribe_update(proxy_server, swindon, loop, user_id):
     url = swindon.url / 'swindon-lattice'
     async with proxy_server() as proxy:
@@ -780,6 +822,109 @@ async def test_client_auth_timeout(proxy_server, swindon, loop):
         assert ws.close_code == 4500
 
 
+async def test_handshake_timeout(swindon, loop):
+    reader, writer = await asyncio.open_connection(
+        swindon.url.host, swindon.url.port, loop=loop)
+    try:
+        writer.write(b'GET /swindon-lattice-w-handshake-timeout HTTP/1.1\r\n'
+                     b'Host: localhost\r\n')
+        # client stalls in the middle of the upgrade request
+        await asyncio.sleep(1.5, loop=loop)
+        writer.write(b'Upgrade: websocket\r\n'
+                     b'Connection: upgrade\r\n'
+                     b'Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n'
+                     b'Sec-WebSocket-Version: 13\r\n'
+                     b'Sec-WebSocket-Protocol: v1.swindon-lattice+json\r\n'
+                     b'\r\n')
+        status = await asyncio.wait_for(reader.readline(), 1)
+        assert int(status.split()[1]) == 408
+    finally:
+        writer.close()
+
+
+async def test_handshake_timeout_slow_backend(proxy_server, swindon, loop):
+    url = swindon.url / 'swindon-lattice-w-handshake-timeout'
+    async with proxy_server() as proxy:
+        handler, ws_fut = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        # upgrade isn't delayed by the authorization backend
+        await asyncio.sleep(0.5, loop=loop)
+        assert ws_fut.done()
+        ws = await ws_fut
+        assert not ws.closed
+        await ws.close()
+
+
+async def test_auth_data_too_large(proxy_server, swindon):
+    url = swindon.url / 'swindon-lattice-w-auth-limit'
+    h = {"Cookie": "session=" + "x" * 200}
+    async with proxy_server() as proxy:
+        handler, ws_fut = proxy.swindon_lattice(url, headers=h, timeout=1)
+        ws = await ws_fut
+        msg = await ws.receive()
+        assert msg.type == WSMsgType.TEXT
+        assert json.loads(msg.data) == ["fatal_error",
+            {"error_kind": "http_error", 'http_error': 400},
+            None]
+        msg = await ws.receive()
+        assert msg.type == WSMsgType.CLOSE
+        assert msg.data == 4400
+        assert ws.closed
+        assert ws.close_code == 4400
+
+
+async def test_auth_data_within_limit(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice-w-auth-limit'
+    h = {"Cookie": "session=abc"}
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, headers=h, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        msg = await ws.receive_json()
+        assert msg == ['hello', {}, {'user_id': user_id}]
+
+
+async def test_auth_limit_slow_backend(proxy_server, swindon, loop):
+    url = swindon.url / 'swindon-lattice-w-auth-limit'
+    async with proxy_server() as proxy:
+        handler, ws_fut = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        # backend is too slow to respond
+        await asyncio.sleep(1.5, loop=loop)
+        assert ws_fut.done()
+
+        ws = await ws_fut
+        msg = await ws.receive()
+        assert json.loads(msg.data) == ["fatal_error",
+            {"error_kind": "auth_timeout"},
+            None]
+        msg = await ws.receive()
+        assert msg.type == WSMsgType.CLOSE
+        assert ws.close_code == 4408
+
+
+async def test_auth_timeout_close_code(proxy_server, swindon, loop):
+    url = swindon.url / 'swindon-lattice-w-auth-timeout'
+    async with proxy_server() as proxy:
+        handler, ws_fut = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await ws_fut
+        # backend never responds
+        msg = await ws.receive()
+        assert json.loads(msg.data) == ["fatal_error",
+            {"error_kind": "auth_timeout"},
+            None]
+        msg = await ws.receive()
+        assert msg.type == WSMsgType.CLOSE
+        assert msg.data == 4504
+        assert msg.extra == 'auth_timeout'
+        assert ws.close_code == 4504
+
+
 async def test_client_call_timeout(proxy_server, swindon, loop, user_id):
     url = swindon.url / 'swindon-lattice-w-client-timeout'
     async with proxy_server() as proxy:
@@ -888,3 +1033,256 @@ async def test_swindon_user(proxy_server, swindon, loop,
             {'namespace': 'swindon.user'},
             {user_id: {'status_register': [mock.ANY, 'active']},
              user_id2: {'status_register': [mock.ANY, 'active']}}]
+
+
+async def test_rate_limit_drop(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice-w-rate-limit'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        for i in range(3):
+            await ws.send_json(['chat.flood', {'request_id': str(i)}, [], {}])
+        msg = await ws.receive_json()
+        assert msg == [
+            'error', {'request_id': '2', 'error_kind': 'rate_limit_exceeded'},
+            'rate_limit_exceeded']
+
+        for i in range(2):
+            req = await handler.request()
+            assert req.path == '/chat/flood'
+            assert await req.json() == [
+                {'request_id': str(i), 'connection_id': mock.ANY}, [], {},
+            ]
+            await handler.json_response({'n': i})
+            msg = await ws.receive_json()
+            assert msg == ['result', {'request_id': str(i)}, {'n': i}]
+        assert not ws.closed
+
+
+async def test_rate_limit_close(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice-w-rate-limit-close'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        for i in range(3):
+            await ws.send_json(['chat.flood', {'request_id': str(i)}, [], {}])
+        msg = await ws.receive()
+        assert msg.type == WSMsgType.CLOSE
+        assert msg.data == 1008
+        assert msg.extra == 'rate_limit_exceeded'
+
+
+async def test_rate_limit_before_decode(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice-w-rate-limit-close'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        for i in range(2):
+            await ws.send_json(['chat.flood', {'request_id': str(i)}, [], {}])
+        # message over the limit is not even decoded
+        await ws.send_str('not a json')
+        msg = await ws.receive()
+        assert msg.type == WSMsgType.CLOSE
+        assert msg.data == 1008
+        assert msg.extra == 'rate_limit_exceeded'
+
+
+async def test_duplicate_request_id(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice-w-unique-ids'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        await ws.send_json(['chat.slow', {'request_id': 'dup'}, [], {}])
+        req = await handler.request()
+        assert req.path == '/chat/slow'
+
+        # same id while the first call is pending
+        await ws.send_json(['chat.slow', {'request_id': 'dup'}, [1], {}])
+        msg = await ws.receive_json()
+        assert msg == [
+            'error',
+            {'request_id': 'dup', 'error_kind': 'duplicate_request_id'},
+            'duplicate_request_id']
+
+        await handler.json_response({'n': 1})
+        msg = await ws.receive_json()
+        assert msg == ['result', {'request_id': 'dup'}, {'n': 1}]
+
+        # id is free again when the call is completed
+        await ws.send_json(['chat.slow', {'request_id': 'dup'}, [2], {}])
+        req = await handler.request()
+        assert req.path == '/chat/slow'
+        assert await req.json() == [
+            {'request_id': 'dup', 'connection_id': mock.ANY}, [2], {},
+        ]
+        await handler.json_response({'n': 2})
+        msg = await ws.receive_json()
+        assert msg == ['result', {'request_id': 'dup'}, {'n': 2}]
+        assert not ws.closed
+
+
+@pytest.mark.parametrize('args', [
+    [[[[1]]]],
+    list(range(9)),
+], ids=['too_deep', 'too_long'])
+async def test_json_limits(proxy_server, swindon, user_id, args):
+    url = swindon.url / 'swindon-lattice-w-json-limits'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        # fits the limits
+        await ws.send_json(['chat.ok', {'request_id': '1'},
+                            [[[1]], list(range(8))], {}])
+        req = await handler.request()
+        assert req.path == '/chat/ok'
+        await handler.json_response({'ok': True})
+        msg = await ws.receive_json()
+        assert msg == ['result', {'request_id': '1'}, {'ok': True}]
+
+        await ws.send_json(['chat.bad', {'request_id': '2'}, args, {}])
+        msg = await ws.receive()
+        assert msg.type in (WSMsgType.CLOSE, WSMsgType.CLOSED)
+        assert ws.closed
+
+
+@pytest.mark.parametrize('route', [
+    'swindon-lattice',
+    'swindon-lattice-w-orphan-log',
+])
+async def test_orphaned_response(proxy_server, swindon, user_id, route):
+    url = swindon.url / route
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        await ws.send_json(['chat.slow', {'request_id': 'gone'}, [], {}])
+        req = await handler.request()
+        assert req.path == '/chat/slow'
+        await ws.close()
+        assert ws.closed
+
+        # response is accepted by swindon even if nobody is waiting for it
+        await handler.json_response({'late': True})
+
+        # and swindon keeps serving new connections
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+        await ws.send_json(['chat.fast', {'request_id': 'ok'}, [], {}])
+        req = await handler.request()
+        assert req.path == '/chat/fast'
+        await handler.json_response({'late': False})
+        msg = await ws.receive_json()
+        assert msg == ['result', {'request_id': 'ok'}, {'late': False}]
+
+
+async def test_reserved_meta_override(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        await ws.send_json(['chat.fast', {
+            'request_id': 'r1', 'connection_id': '321', 'user_token': 'x',
+            }, [], {}])
+        req = await handler.request()
+        meta, _, _ = await req.json()
+        assert meta['connection_id'] != '321'
+        # not reserved for this handler
+        assert meta['user_token'] == 'x'
+        await handler.json_response({'ok': 1})
+        msg = await ws.receive_json()
+        # meta is echoed as is
+        assert msg == ['result', {
+            'request_id': 'r1', 'connection_id': '321', 'user_token': 'x',
+            }, {'ok': 1}]
+
+
+@pytest.mark.parametrize('key', ['connection_id', 'user_token'])
+async def test_reserved_meta_reject(proxy_server, swindon, user_id, key):
+    url = swindon.url / 'swindon-lattice-w-reserved-reject'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        await ws.send_json(['chat.fast',
+            {'request_id': 'bad', key: '321'}, [], {}])
+        msg = await ws.receive_json()
+        meta = {'request_id': 'bad', 'error_kind': 'reserved_meta_key'}
+        if key == 'connection_id':
+            meta['connection_id'] = '321'
+        assert msg == ['error', meta, 'reserved_meta_key']
+
+        # messages without reserved keys are forwarded as usual
+        await ws.send_json(['chat.fast', {'request_id': 'good'}, [], {}])
+        req = await handler.request()
+        assert req.path == '/chat/fast'
+        await handler.json_response({'ok': 1})
+        msg = await ws.receive_json()
+        assert msg == ['result', {'request_id': 'good'}, {'ok': 1}]
+        assert not ws.closed
+
+
+async def test_reserved_meta_log(proxy_server, swindon, user_id):
+    url = swindon.url / 'swindon-lattice-w-reserved-log'
+    async with proxy_server() as proxy:
+        handler = proxy.swindon_lattice(url, timeout=1)
+        req = await handler.request()
+        assert_auth(req)
+        ws = await handler.json_response({"user_id": user_id})
+        hello = await ws.receive_json()
+        assert hello == ['hello', {}, {'user_id': user_id}]
+
+        await ws.send_json(['chat.fast', {
+            'request_id': 'r1', 'connection_id': '321', 'user_token': 'x',
+            }, [], {}])
+        req = await handler.request()
+        assert await req.json() == [
+            {'request_id': 'r1', 'connection_id': mock.ANY}, [], {},
+        ]
+        meta, _, _ = await req.json()
+        assert meta['connection_id'] != '321'
+        await handler.json_response({'ok': 1})
+        msg = await ws.receive_json()
+        assert msg == ['result', {'request_id': 'r1', 'connection_id': '321'},
+                       {'ok': 1}]
@@ -0,0 +1,64 @@
+import asyncio
+import tempfile
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+debug-tracing: true
+routing:
+  localhost/proxy: proxy
+handlers:
+  proxy: !Proxy
+    destination: backend/
+http-destinations:
+  backend:
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+EVENTS = [
+    'accepted',
+    'routed',
+    'handler-start',
+    'upstream-connect',
+    'response-start',
+    'completed',
+]
+
+
+async def test_request_events(custom_swindon, swindon_ports,
+                              proxy_server, loop):
+    ports = swindon_ports['tracing']
+    url = 'http://localhost:{}/proxy/hello'.format(ports['main'])
+    with tempfile.TemporaryFile() as log, \
+            custom_swindon(CONFIG, ports['main'],
+                           rust_log='swindon::trace=info', stderr=log,
+                           port=ports['main'], proxy_port=ports['proxy']):
+
+        async with proxy_server(port=ports['proxy']) as proxy:
+            handler = proxy.send('GET', url)
+            await handler.request()
+            resp, body = await handler.response(b'hello')
+            assert resp.status == 200
+
+        await asyncio.sleep(0.1, loop=loop)
+        log.seek(0)
+        lines = [line for line in log.read().decode('utf-8').splitlines()
+                 if 'GET /proxy/hello' in line]
+        assert len(lines) == 1
+        # line is "<timestamp> <request_id> accepted GET /proxy/hello ..."
+        request_id = lines[0].split(' accepted ')[0].split()[-1]
+
+        log.seek(0)
+        events = []
+        stamps = []
+        for line in log.read().decode('utf-8').splitlines():
+            words = line.split()
+            if request_id not in words:
+                continue
+            idx = words.index(request_id)
+            stamps.append(float(words[idx-1]))
+            events.append(words[idx+1])
+        assert events == EVENTS
+        assert stamps == sorted(stamps)
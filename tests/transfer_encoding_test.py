@@ -0,0 +1,32 @@
+import asyncio
+import pytest
+
+
+async def raw_request(swindon, loop, transfer_encoding):
+    reader, writer = await asyncio.open_connection(
+        swindon.url.host, swindon.url.port, loop=loop)
+    try:
+        writer.write(b'GET /empty.gif HTTP/1.1\r\n'
+                     b'Host: localhost\r\n'
+                     b'Transfer-Encoding: ' + transfer_encoding + b'\r\n'
+                     b'\r\n'
+                     b'0\r\n\r\n')
+        status = await asyncio.wait_for(reader.readline(), 1)
+        return int(status.split()[1])
+    finally:
+        writer.close()
+
+
+async def test_chunked(swindon, loop):
+    assert await raw_request(swindon, loop, b'chunked') == 200
+
+
+@pytest.mark.parametrize('coding', [
+    b'gzip',
+    b'gzip, chunked',
+    b'chunked, gzip',
+    b'identity',
+    b'x-unknown',
+])
+async def test_unsupported(swindon, loop, coding):
+    assert await raw_request(swindon, loop, coding) == 501
@@ -0,0 +1,61 @@
+import asyncio
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+max-upstream-connections: 1
+routing:
+  localhost/a: proxy_a
+  localhost/b: proxy_b
+handlers:
+  proxy_a: !Proxy
+    destination: backend_a/
+  proxy_b: !Proxy
+    destination: backend_b/
+http-destinations:
+  backend_a:
+    keep-alive-timeout: 300ms
+    addresses:
+    - 127.0.0.1:${proxy_port}
+  backend_b:
+    keep-alive-timeout: 300ms
+    queue-size-for-503: 1
+    addresses:
+    - 127.0.0.1:${proxy_port}
+"""
+
+
+async def test_max_upstream_connections(custom_swindon, swindon_ports,
+                                        proxy_server, loop):
+    ports = swindon_ports['upstream_limit']
+    url = 'http://localhost:{}'.format(ports['main'])
+    with custom_swindon(CONFIG, ports['main'],
+                        port=ports['main'], proxy_port=ports['proxy']):
+        async with proxy_server(port=ports['proxy']) as proxy:
+            # first request opens the only allowed upstream connection
+            first = proxy.send('GET', url + '/a/first')
+            req = await first.request()
+            assert req.path == '/a/first'
+
+            # pool of another destination can't connect, request is queued
+            second = proxy.send('GET', url + '/b/second')
+            await asyncio.sleep(0.5, loop=loop)
+            assert len(proxy.futures) == 1
+
+            # queue of the destination is full
+            async with aiohttp.ClientSession(loop=loop) as s:
+                async with s.get(url + '/b/third') as resp:
+                    assert resp.status == 503
+
+            resp, body = await first.response(b'first')
+            assert resp.status == 200
+            assert body == b'first'
+
+            # connection is released when it's closed by keep-alive-timeout
+            req = await second.request(timeout=2)
+            assert req.path == '/b/second'
+            resp, body = await second.response(b'second')
+            assert resp.status == 200
+            assert body == b'second'
@@ -0,0 +1,58 @@
+import asyncio
+import aiohttp
+
+
+CONFIG = """
+listen:
+- 127.0.0.1:${port}
+warmup-period: ${warmup_period}
+routing:
+  localhost: empty_gif
+handlers:
+  empty_gif: !EmptyGif
+http-destinations:
+  backend:
+    addresses:
+    - ${backend}
+"""
+
+
+async def get(session, url):
+    async with session.get(url) as resp:
+        await resp.read()
+        return resp
+
+
+async def test_ready_when_initialized(custom_swindon, swindon_ports, loop):
+    port = swindon_ports['warmup_ready']['main']
+    url = 'http://localhost:{}/'.format(port)
+    with custom_swindon(CONFIG, port, port=port, warmup_period='1 hour',
+                        backend='127.0.0.1:1234'):
+        async with aiohttp.ClientSession(loop=loop) as s:
+            # name is resolved at once, so requests are served long
+            # before `warmup-period` is over
+            for _ in range(50):
+                resp = await get(s, url)
+                if resp.status == 200:
+                    break
+                assert resp.status == 503
+                await asyncio.sleep(0.1, loop=loop)
+            assert resp.status == 200
+            assert resp.headers['Content-Type'] == 'image/gif'
+
+
+async def test_warmup_period(custom_swindon, swindon_ports, loop):
+    port = swindon_ports['warmup']['main']
+    url = 'http://localhost:{}/'.format(port)
+    # name is never resolved, so we wait for the whole `warmup-period`
+    with custom_swindon(CONFIG, port, port=port, warmup_period='2s',
+                        backend='swindon-test.invalid:1234'):
+        async with aiohttp.ClientSession(loop=loop) as s:
+            resp = await get(s, url)
+            assert resp.status == 503
+            assert resp.headers['Retry-After'] == '1'
+
+            await asyncio.sleep(2.5, loop=loop)
+            resp = await get(s, url)
+            assert resp.status == 200
+            assert resp.headers['Content-Type'] == 'image/gif'